@@ -0,0 +1,53 @@
+use std::ops::ControlFlow;
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use stack_frame_allocators::stack_frame_allocator::StackFrameAllocator;
+
+const PUSHES: u64 = 10_000_000;
+
+fn push_u64(c: &mut Criterion) {
+    //blocks are kept around after the scope pops,
+    //so only the first iteration pays for allocating them
+    let stack = StackFrameAllocator::<u64>::new();
+    c.bench_function("push 10M u64", |b| b.iter(|| {
+        stack.new_scope(|stack| {
+            for i in 0..PUSHES {
+                black_box(stack.push(i));
+            }
+        });
+    }));
+
+    //the same amount of pushes, but into frames small enough
+    //to stay in cache, so the cost of push itself dominates
+    let stack = StackFrameAllocator::<u64>::new();
+    c.bench_function("push 10M u64 in frames of 100", |b| b.iter(|| {
+        for _ in 0..PUSHES / 100 {
+            stack.new_scope(|stack| {
+                for i in 0..100 {
+                    black_box(stack.push(i));
+                }
+            });
+        }
+    }));
+
+    //the same frames of 100, but with the frame's header only written once
+    let stack = StackFrameAllocator::<u64>::new();
+    c.bench_function("push 10M u64 in a reused frame of 100", |b| b.iter(|| {
+        let mut frames = 0;
+        stack.with_reused_frame(|stack| {
+            for i in 0..100 {
+                black_box(stack.push(i));
+            }
+
+            frames += 1;
+            if frames < PUSHES / 100 {ControlFlow::Continue(())} else {ControlFlow::Break(())}
+        });
+    }));
+}
+
+criterion_group!{
+    name = benches;
+    config = Criterion::default().sample_size(10);
+    targets = push_u64
+}
+criterion_main!(benches);
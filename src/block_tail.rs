@@ -1,8 +1,14 @@
 /// A block of data placed at the end of memory blocks 
 /// to keep track of allocated blocks and help
 /// traverse the stack
+///
+/// Blocks can differ in size, so the tail also keeps track
+/// of the size of the blocks it points to,
+/// which is needed to find the tails of those blocks.
 pub(crate) struct BlockTail {
     pub(crate) prev_block: *mut u8,
     pub(crate) prev_block_bytes_used: usize,
-    pub(crate) next_block: *mut u8
+    pub(crate) prev_block_size: usize,
+    pub(crate) next_block: *mut u8,
+    pub(crate) next_block_size: usize
 }
\ No newline at end of file
@@ -0,0 +1,58 @@
+//! Errors returned by the allocators.
+
+use core::fmt::{self, Display, Formatter};
+
+/// The error returned when the allocator backing a
+/// [StackFrameAllocator](crate::stack_frame_allocator::StackFrameAllocator) or
+/// [StackFrameDictAllocator](crate::stack_frame_dict_allocator::StackFrameDictAllocator)
+/// fails to allocate a new block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AllocError;
+
+impl Display for AllocError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "could not allocate memory")
+    }
+}
+
+impl core::error::Error for AllocError {}
+
+/// The error returned when pushing onto a
+/// [StackFrameAllocator](crate::stack_frame_allocator::StackFrameAllocator),
+/// [StackFrameDictAllocator](crate::stack_frame_dict_allocator::StackFrameDictAllocator) or
+/// [StackFrameGeneralAllocator](crate::stack_frame_general_allocator::StackFrameGeneralAllocator) fails.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PushError {
+    /// A new block was needed but couldn't be allocated.
+    Alloc(AllocError),
+    /// The item can't fit even in an empty block,
+    /// so the allocator would have to be made with larger blocks to hold it.
+    TooLarge {
+        /// How many bytes the item takes up in a block.
+        required: usize,
+        /// The most bytes an item can take up in an empty block.
+        block_capacity: usize
+    }
+}
+
+impl From<AllocError> for PushError {
+    fn from(error: AllocError) -> Self {
+        PushError::Alloc(error)
+    }
+}
+
+impl Display for PushError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            PushError::Alloc(error) => error.fmt(f),
+            PushError::TooLarge { required, block_capacity } => write!(
+                f,
+                "an item of {} bytes can't fit in a block of {} usable bytes",
+                required,
+                block_capacity
+            )
+        }
+    }
+}
+
+impl core::error::Error for PushError {}
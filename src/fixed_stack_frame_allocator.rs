@@ -0,0 +1,164 @@
+//! A StackFrameAllocator whose block size is fixed at compile time,
+//! for when every block has to be the same, known size.
+
+use core::ops::Deref;
+
+use allocator_api2::alloc::{Allocator, Global};
+
+use crate::{error::AllocError, growth_policy::GrowthPolicy, stack_frame_allocator::StackFrameAllocator, stack_size::StackSize};
+
+/// A [StackFrameAllocator](crate::stack_frame_allocator::StackFrameAllocator)
+/// whose every block is exactly `BLOCK` bytes.
+///
+/// The block size is a constant, so it can't be changed with a builder,
+/// picked to fit a capacity, or grown with a
+/// [GrowthPolicy](crate::growth_policy::GrowthPolicy).
+/// Values are pushed through the StackFrameAllocator it dereferences to.
+/// Methods taking `&mut self` are reached through frames made with
+/// [new_scope](crate::stack_frame_allocator::StackFrameAllocator::new_scope),
+/// so the allocator can't be swapped for one with a different block size.
+///
+/// `BLOCK` has to be a power of two, large enough to hold a frame header and a block tail,
+/// which is checked at compile time.
+///
+/// # Examples
+///
+/// ```edition2020
+/// # use stack_frame_allocators::fixed_stack_frame_allocator::FixedStackFrameAllocator;
+///
+/// let stack = FixedStackFrameAllocator::<u64, 4096>::new();
+/// for i in 0..1000 {
+///     stack.push(i);
+/// }
+///
+/// assert_eq!(stack.mem_usage().total_bytes(), stack.allocated_blocks() * 4096);
+/// ```
+///
+/// ```compile_fail
+/// # use stack_frame_allocators::fixed_stack_frame_allocator::FixedStackFrameAllocator;
+///
+/// //blocks have to be a power of two in size
+/// let stack = FixedStackFrameAllocator::<u64, 1000>::new();
+/// ```
+pub struct FixedStackFrameAllocator<'s, Value, const BLOCK: usize, A: Allocator = Global> {
+    stack: StackFrameAllocator<'s, Value, A>
+}
+
+impl<'s, Value, const BLOCK: usize> FixedStackFrameAllocator<'s, Value, BLOCK> {
+    /// Creates a new FixedStackFrameAllocator.
+    pub fn new() -> Self {
+        Self::new_in(Global)
+    }
+}
+
+/// Creates a new FixedStackFrameAllocator, the same as
+/// [new](crate::fixed_stack_frame_allocator::FixedStackFrameAllocator::new).
+impl<'s, Value, const BLOCK: usize> Default for FixedStackFrameAllocator<'s, Value, BLOCK> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'s, Value, const BLOCK: usize, A: Allocator> FixedStackFrameAllocator<'s, Value, BLOCK, A> {
+    /// The size of every block in bytes.
+    pub const BLOCK_SIZE: usize = {
+        //block sizes are kept as powers of two,
+        //so the block tail at the end of the block stays aligned
+        assert!(BLOCK.is_power_of_two(), "block size must be a power of two");
+        //the root frame's header has to fit in the first block,
+        //and the top of a block never reaches the block's tail
+        assert!(
+            BLOCK > StackFrameAllocator::<Value, A>::SIZE_HEADER + StackFrameAllocator::<Value, A>::SIZE_TAIL,
+            "block size is too small to hold a frame header and a block tail"
+        );
+        BLOCK
+    };
+
+    /// The size of a block in bytes, excluding the block tail and the root frame's header,
+    /// the same as [usable_block_size](crate::stack_frame_allocator::StackFrameAllocator::usable_block_size).
+    pub const USABLE_BLOCK_SIZE: usize = Self::BLOCK_SIZE
+        - StackFrameAllocator::<Value, A>::SIZE_TAIL
+        - StackFrameAllocator::<Value, A>::SIZE_HEADER;
+
+    /// How many values always fit in a single block,
+    /// the same as [capacity_per_block](crate::stack_frame_allocator::StackFrameAllocator::capacity_per_block).
+    pub const CAPACITY_PER_BLOCK: usize = {
+        let size_value = StackFrameAllocator::<Value, A>::SIZE_VALUE;
+        let align_value = StackFrameAllocator::<Value, A>::ALIGN_VALUE;
+        //at most ALIGN_VALUE - 1 bytes of padding before the first value,
+        //and the byte the top of a block never reaches
+        match (Self::USABLE_BLOCK_SIZE - (align_value - 1) - 1).checked_div(size_value) {
+            Some(capacity) => capacity,
+            //zero sized values never take up room
+            None => usize::MAX
+        }
+    };
+
+    /// Creates a new FixedStackFrameAllocator,
+    /// whose blocks are allocated and deallocated with `allocator`.
+    pub fn new_in(allocator: A) -> Self {
+        FixedStackFrameAllocator {
+            stack: StackFrameAllocator::with_stack_size_in(
+                StackSize::from_num_bytes(Self::BLOCK_SIZE),
+                GrowthPolicy::Fixed,
+                allocator
+            )
+        }
+    }
+
+    /// Creates a new FixedStackFrameAllocator like
+    /// [new_in](crate::fixed_stack_frame_allocator::FixedStackFrameAllocator::new_in),
+    /// but returns an error instead of aborting if the first block can't be allocated.
+    pub fn try_new_in(allocator: A) -> Result<Self, AllocError> {
+        Ok(FixedStackFrameAllocator {
+            stack: StackFrameAllocator::try_with_stack_size_in(
+                StackSize::from_num_bytes(Self::BLOCK_SIZE),
+                GrowthPolicy::Fixed,
+                allocator
+            )?
+        })
+    }
+}
+
+impl<'s, Value, const BLOCK: usize, A: Allocator> Deref for FixedStackFrameAllocator<'s, Value, BLOCK, A> {
+    type Target = StackFrameAllocator<'s, Value, A>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.stack
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    pub fn block_size_test() {
+        let stack = FixedStackFrameAllocator::<u64, 4096>::new();
+        assert_eq!(FixedStackFrameAllocator::<u64, 4096>::BLOCK_SIZE, 4096);
+        assert_eq!(stack.allocated_blocks(), 1);
+        assert_eq!(stack.mem_usage().total_bytes(), 4096);
+
+        //4096 bytes, minus the 40 byte tail and the 16 byte root header
+        assert_eq!(FixedStackFrameAllocator::<u64, 4096>::USABLE_BLOCK_SIZE, 4096 - 40 - 16);
+        assert_eq!(FixedStackFrameAllocator::<u64, 4096>::USABLE_BLOCK_SIZE, stack.usable_block_size());
+        assert_eq!(FixedStackFrameAllocator::<u64, 4096>::CAPACITY_PER_BLOCK, stack.capacity_per_block());
+
+        for i in 0..FixedStackFrameAllocator::<u64, 4096>::CAPACITY_PER_BLOCK {
+            stack.push(i as u64);
+        }
+        assert_eq!(stack.allocated_blocks(), 1);
+
+        //every block after the first is just as large
+        stack.new_scope(|stack| {
+            for i in 0..2000 {
+                stack.push(i);
+            }
+        });
+        assert_eq!(stack.allocated_blocks(), 5);
+        assert_eq!(stack.mem_usage().total_bytes(), 5 * 4096);
+
+        assert_eq!(FixedStackFrameAllocator::<u8, 64>::CAPACITY_PER_BLOCK, 64 - 40 - 16 - 1);
+        assert_eq!(FixedStackFrameAllocator::<(), 64>::CAPACITY_PER_BLOCK, usize::MAX);
+    }
+}
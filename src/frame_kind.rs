@@ -0,0 +1,12 @@
+//! Markers telling apart an allocator owning its blocks
+//! from a frame sharing the blocks of the allocator it was made from.
+//!
+//! Only a [Root](crate::frame_kind::Root) allocator can be sent to another thread,
+//! since a [Child](crate::frame_kind::Child) frame and its parent push onto the same blocks.
+
+/// Marks an allocator created by a constructor, owning every block it pushes onto.
+pub enum Root {}
+
+/// Marks a frame created by `new_frame` or `new_scope`,
+/// pushing onto the blocks of the allocator it was made from.
+pub enum Child {}
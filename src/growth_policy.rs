@@ -0,0 +1,59 @@
+//! Policies deciding how large the blocks an allocator chains together are.
+
+use crate::stack_size::StackSize;
+
+/// Decides how large every block allocated after the first block is.
+///
+/// # Examples
+///
+/// ```edition2020
+/// # use stack_frame_allocators::stack_frame_allocator::StackFrameAllocator;
+/// use stack_frame_allocators::growth_policy::GrowthPolicy;
+///
+/// let stack = StackFrameAllocator::<u64>::builder()
+///     .growth(GrowthPolicy::Geometric { factor: 2, cap: 1 << 16 })
+///     .build();
+///
+/// for i in 0..1000 {
+///     stack.push(i);
+/// }
+///
+/// //blocks of 1, 2, 4 and 8 KiB instead of 8 blocks of 1 KiB
+/// assert_eq!(stack.allocated_blocks(), 4);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum GrowthPolicy {
+    /// Every block is as large as the first block.
+    #[default]
+    Fixed,
+    /// Every new block is `factor` times as large as the block before it,
+    /// until blocks reach `cap` bytes.
+    ///
+    /// Blocks never shrink, so a `factor` below 2 behaves like
+    /// [Fixed](crate::growth_policy::GrowthPolicy::Fixed),
+    /// and a `cap` smaller than the first block is never reached.
+    Geometric {
+        /// How many times larger every new block is than the block before it.
+        factor: usize,
+        /// The largest size a block can grow to in bytes,
+        /// rounded down to a power of two to keep block tails aligned.
+        cap: usize
+    }
+}
+
+impl GrowthPolicy {
+    /// Gets the size of the block allocated after a block of size `block_size`.
+    pub(crate) fn next_block_size(self, block_size: StackSize) -> StackSize {
+        match self {
+            GrowthPolicy::Fixed => block_size,
+            GrowthPolicy::Geometric { factor, cap } => {
+                let cap = match cap {
+                    0 => 0,
+                    cap => 1 << cap.ilog2()
+                };
+
+                StackSize(block_size.bytes().saturating_mul(factor).min(cap).max(block_size.bytes()))
+            }
+        }
+    }
+}
@@ -1,15 +1,63 @@
+#![cfg_attr(not(feature = "std"), no_std)]
 #![warn(missing_docs)]
-#![feature(ptr_as_ref_unchecked)]
+#![allow(clippy::needless_return)]
+#![cfg_attr(feature = "nightly", feature(allocator_api))]
 
 //! A set of Allocators based on the concept of a stack
 //! and enforcing memory safety via scopes.  These allocators
 //! use frames, where values can be pushed onto the frames.
 //! The allocators can only pop whole frames and all of its
 //! associated values.
+//!
+//! The `std` feature is enabled by default.  Without it, the crate is `no_std`,
+//! only depending on the `alloc` crate, and printing the stack is unavailable.
+//!
+//! Blocks can be allocated with any [Allocator](allocator_api2::alloc::Allocator),
+//! which works on stable without any feature.
+//! The `nightly` feature uses the standard library's unstable `Allocator` trait instead
+//! of the one from `allocator_api2`.  A [StackPool](crate::stack_pool::StackPool)
+//! is an Allocator recycling the blocks of dropped allocators.
+//! A [FixedStackFrameAllocator](crate::fixed_stack_frame_allocator::FixedStackFrameAllocator)
+//! fixes the size of every block at compile time.
+//!
+//! The `serde` feature implements `Serialize` and `Deserialize` for the StackFrameDictAllocator.
+//!
+//! The `zeroize` feature overwrites the bytes of every popped value (and key) with zeros
+//! right after its destructor runs.  These are plain writes, so unlike the `zeroize` crate
+//! nothing stops the compiler from reordering or eliding them; it scrubs secrets from
+//! recycled blocks on a best effort basis only.
+
+extern crate alloc;
 
 pub(crate) mod block_tail;
+pub mod error;
+pub mod fixed_stack_frame_allocator;
+pub mod frame_kind;
+pub mod growth_policy;
 pub mod stack_frame_allocator;
 pub mod stack_frame_dict_allocator;
+pub mod stack_frame_general_allocator;
 pub(crate) mod stack_frame_header;
+pub mod stack_pool;
 pub mod stack_ref;
-pub(crate) mod stack_size;
\ No newline at end of file
+pub(crate) mod stack_size;
+pub(crate) mod stack_walker;
+#[cfg(test)]
+pub(crate) mod test_alloc;
+
+/// The larger of two alignments, usable in constants.
+pub(crate) const fn max_align(a: usize, b: usize) -> usize {
+    if a > b { a } else { b }
+}
+
+/// Runs the destructor of the element at `ptr`,
+/// then zeroes its bytes if the `zeroize` feature is enabled.
+///
+/// # Safety
+///
+/// `ptr` must point to a valid `T` that is never used again.
+pub(crate) unsafe fn drop_element<T>(ptr: *mut T) {
+    core::ptr::drop_in_place(ptr);
+    #[cfg(feature = "zeroize")]
+    core::ptr::write_bytes(ptr, 0, 1);
+}
@@ -8,9 +8,15 @@
 //! for a given value, both [get] and [get_mut] functions are safe,
 //! due to being able to be validated by the borrow checker at compile time.
 
-use std::{alloc::Layout, cell::UnsafeCell, fmt::Display, marker::PhantomData, ptr::NonNull};
+use core::{cell::UnsafeCell, fmt::{self, Debug, Formatter}, marker::PhantomData, mem::MaybeUninit, ops::{ControlFlow, Deref}, ptr::NonNull};
+#[cfg(feature = "std")]
+use std::{fmt::Display, io::{self, Write}};
 
-use crate::{block_tail::BlockTail, stack_frame_header::StackFrameHeader, stack_ref::safe_ref::StackRef, stack_size::StackSize};
+use alloc::{alloc::{handle_alloc_error, Layout}, sync::Arc, vec::Vec};
+
+use allocator_api2::alloc::{Allocator, Global};
+
+use crate::{block_tail::BlockTail, max_align, error::{AllocError, PushError}, frame_kind::{Child, Root}, growth_policy::GrowthPolicy, stack_frame_header::StackFrameHeader, stack_ref::safe_ref::StackRef, stack_size::StackSize, stack_walker::StackWalker};
 
 /// The StackFrameAllocator allows the creation of "Frames"
 /// where key value pairs can be pushed onto this frame.
@@ -52,23 +58,134 @@ use crate::{block_tail::BlockTail, stack_frame_header::StackFrameHeader, stack_r
 ///     //are not reachable past this point
 /// });
 /// ```
-pub struct StackFrameAllocator<'s, Value> {
+///
+/// # Pushing while holding StackRefs
+///
+/// Pushing only ever writes above the top of the current frame,
+/// or into a new block chained after the current block,
+/// so Values never move once they're pushed.
+/// The bookkeeping a push updates lives in the allocator, not next to the Values,
+/// so StackRefs, and the references grabbed from them,
+/// stay valid while pushing onto the same frame or a new frame.
+///
+/// ```edition2020
+/// # use stack_frame_allocators::stack_frame_allocator::StackFrameAllocator;
+///
+/// let stack = StackFrameAllocator::<usize>::new();
+/// let x = stack.push(1).get_mut();
+///
+/// stack.new_scope(|stack| {
+///     let y = stack.push(*x + 1).get();
+///     *x += *y;
+/// });
+///
+/// assert_eq!(*x, 3);
+/// ```
+///
+/// StackRefs can't outlive the frame they were pushed onto though.
+///
+/// ```compile_fail
+/// # use stack_frame_allocators::stack_frame_allocator::StackFrameAllocator;
+///
+/// let stack = StackFrameAllocator::<usize>::new();
+///
+/// let y = stack.new_scope(|stack| {
+///     stack.push(2)
+/// });
+/// ```
+///
+/// # Block size
+///
+/// Blocks are 1024 bytes large unless the allocator is made with
+/// [with_capacity](crate::stack_frame_allocator::StackFrameAllocator::with_capacity),
+/// [block_size](crate::stack_frame_allocator::StackFrameAllocatorBuilder::block_size)
+/// or grows its blocks with a [GrowthPolicy](crate::growth_policy::GrowthPolicy).
+/// Since blocks can differ in size at runtime, the block size is kept in the allocator
+/// instead of being fixed by its type.
+///
+/// ```edition2020
+/// # use stack_frame_allocators::stack_frame_allocator::StackFrameAllocator;
+///
+/// let stack = StackFrameAllocator::<u64>::builder().block_size(4096).build();
+/// for i in 0..500 {
+///     stack.push(i);
+/// }
+/// assert_eq!(stack.allocated_blocks(), 1);
+/// ```
+pub struct StackFrameAllocator<'s, Value, A: Allocator = Global, Kind = Root> {
     pub(crate) size: StackSize,
+    pub(crate) growth: GrowthPolicy,
+    /// Called with the size of every block allocated after the first block.
+    pub(crate) on_block_alloc: Option<Arc<dyn Fn(usize) + Send + Sync>>,
+    pub(crate) allocator: A,
     pub(crate) current_frame: UnsafeCell<NonNull<StackFrameHeader<'s>>>,
     pub(crate) buffer_bytes_used: UnsafeCell<usize>,
-    pub(crate) phantom: PhantomData<Value>
+    pub(crate) block_size: UnsafeCell<StackSize>,
+    pub(crate) peak_bytes: UnsafeCell<usize>,
+    /// The block the top of the stack was last seen in,
+    /// and how many bytes the blocks before it use.
+    pub(crate) bytes_below: UnsafeCell<(*mut u8, usize)>,
+    /// The peak of the allocator a scope was made from,
+    /// which is raised to this allocator's peak when it's dropped.
+    pub(crate) parent_peak: *const UnsafeCell<usize>,
+    pub(crate) phantom: PhantomData<Value>,
+    pub(crate) kind: PhantomData<Kind>
 }
 
-impl<'s, Value> StackFrameAllocator<'s, Value> {
-    const SIZE_HEADER:   usize = std::mem::size_of::<StackFrameHeader>();
-    const SIZE_VALUE:    usize = std::mem::size_of::<Value>();
-    const SIZE_TAIL:     usize = std::mem::size_of::<BlockTail>();
-
-    const ALIGN_HEADER:     usize = std::mem::align_of::<StackFrameHeader>();
-    const ALIGN_VALUE:      usize = std::mem::align_of::<Value>();
-    #[allow(dead_code)]
-    const ALIGN_TAIL:       usize = std::mem::align_of::<BlockTail>();
+/// A frame made with
+/// [new_frame](crate::stack_frame_allocator::StackFrameAllocator::new_frame) or
+/// [new_scope](crate::stack_frame_allocator::StackFrameAllocator::new_scope).
+///
+/// A frame pushes onto the same blocks as the allocator it was made from,
+/// so unlike a StackFrameAllocator made by a constructor, it can't be sent to another thread.
+///
+/// ```edition2020
+/// # use stack_frame_allocators::stack_frame_allocator::{StackFrame, StackFrameAllocator};
+///
+/// fn push_twice(stack: StackFrame<u64>, value: u64) {
+///     stack.push(value);
+///     stack.push(value);
+/// }
+///
+/// let stack = StackFrameAllocator::<u64>::new();
+/// push_twice(stack.new_frame(), 1);
+///
+/// let mut stack = std::thread::spawn(move || {
+///     stack.push(2);
+///     stack
+/// }).join().unwrap();
+/// assert_eq!(*stack.peek().unwrap(), 2);
+/// ```
+///
+/// ```compile_fail
+/// # use stack_frame_allocators::stack_frame_allocator::StackFrameAllocator;
+///
+/// let stack = StackFrameAllocator::<u64>::new();
+/// let frame = stack.new_frame();
+///
+/// std::thread::spawn(move || {
+///     frame.push(1);
+/// });
+/// stack.push(2);
+/// ```
+///
+/// A frame borrows the allocator it was made from,
+/// so the allocator can't be sent away while the frame is still pushing onto its blocks.
+///
+/// ```compile_fail
+/// # use stack_frame_allocators::stack_frame_allocator::StackFrameAllocator;
+///
+/// let stack = StackFrameAllocator::<u64>::new();
+/// let frame = stack.new_frame();
+///
+/// std::thread::spawn(move || {
+///     stack.push(1);
+/// });
+/// frame.push(2);
+/// ```
+pub type StackFrame<'s, Value, A = Global> = StackFrameAllocator<'s, Value, A, Child>;
 
+impl<'s, Value> StackFrameAllocator<'s, Value> {
     /// Creates a new StackFrameAllocator
     /// 
     /// The StackFrameAllocator allows the creation of "Frames"
@@ -110,14 +227,180 @@ impl<'s, Value> StackFrameAllocator<'s, Value> {
     /// });
     /// ```
     pub fn new() -> Self {
-        let size = StackSize::default();
+        Self::new_in(Global)
+    }
+
+    /// Creates a new StackFrameAllocator like
+    /// [new](crate::stack_frame_allocator::StackFrameAllocator::new),
+    /// but returns an error instead of aborting if the first block can't be allocated.
+    ///
+    /// # Examples
+    ///
+    /// ```edition2020
+    /// # use stack_frame_allocators::stack_frame_allocator::StackFrameAllocator;
+    ///
+    /// let stack = StackFrameAllocator::<usize>::try_new().unwrap();
+    /// stack.push(1);
+    /// ```
+    pub fn try_new() -> Result<Self, AllocError> {
+        Self::try_new_in(Global)
+    }
+
+    /// Creates a new StackFrameAllocator with `n` values already pushed
+    /// onto the root frame, where the value at index `i`
+    /// (from the first push) is `f(i)`.
+    ///
+    /// Blocks are made large enough to fit all `n` values in the first block.
+    ///
+    /// # Examples
+    ///
+    /// ```edition2020
+    /// # use stack_frame_allocators::stack_frame_allocator::StackFrameAllocator;
+    ///
+    /// let mut stack = StackFrameAllocator::from_fn(5, |i| i * i);
+    /// assert_eq!(stack.peek(), Some(&16));
+    /// ```
+    pub fn from_fn(n: usize, mut f: impl FnMut(usize) -> Value) -> Self {
+        let stack = Self::with_capacity(n);
+        for i in 0..n {
+            stack.push(f(i));
+        }
+
+        stack
+    }
+
+    /// Creates a new StackFrameAllocator whose first block is large enough
+    /// to fit `elements` Values in the root frame.
+    ///
+    /// Blocks are never made smaller than the default block size,
+    /// and blocks allocated after the first block are just as large.
+    ///
+    /// # Examples
+    ///
+    /// ```edition2020
+    /// # use stack_frame_allocators::stack_frame_allocator::StackFrameAllocator;
+    ///
+    /// let stack = StackFrameAllocator::<u64>::with_capacity(1000);
+    /// for i in 0..1000 {
+    ///     stack.push(i);
+    /// }
+    ///
+    /// assert_eq!(stack.allocated_blocks(), 1);
+    /// ```
+    pub fn with_capacity(elements: usize) -> Self {
+        Self::with_stack_size_in(Self::capacity_block_size(elements), GrowthPolicy::Fixed, Global)
+    }
+
+    /// Creates a builder to configure a StackFrameAllocator before creating it.
+    ///
+    /// # Examples
+    ///
+    /// ```edition2020
+    /// # use stack_frame_allocators::stack_frame_allocator::StackFrameAllocator;
+    /// use stack_frame_allocators::growth_policy::GrowthPolicy;
+    ///
+    /// let stack = StackFrameAllocator::<usize>::builder()
+    ///     .growth(GrowthPolicy::Geometric { factor: 2, cap: 1 << 20 })
+    ///     .build();
+    /// stack.push(1);
+    /// ```
+    pub fn builder() -> StackFrameAllocatorBuilder<Value> {
+        StackFrameAllocatorBuilder {
+            size: StackSize::default(),
+            growth: GrowthPolicy::default(),
+            on_block_alloc: None,
+            allocator: Global,
+            phantom: PhantomData
+        }
+    }
+}
+
+impl<'s, Value, A: Allocator, Kind> StackFrameAllocator<'s, Value, A, Kind> {
+    pub(crate) const SIZE_HEADER:   usize = core::mem::size_of::<StackFrameHeader>();
+    pub(crate) const SIZE_VALUE:    usize = core::mem::size_of::<Value>();
+    pub(crate) const SIZE_TAIL:     usize = core::mem::size_of::<BlockTail>();
+
+    const ALIGN_HEADER:     usize = core::mem::align_of::<StackFrameHeader>();
+    pub(crate) const ALIGN_VALUE:      usize = core::mem::align_of::<Value>();
+    const ALIGN_TAIL:       usize = core::mem::align_of::<BlockTail>();
+
+    //blocks are aligned to everything placed at a fixed spot in them,
+    //that way no padding is needed at the start of a block,
+    //and the block tail at the end of the block stays aligned
+    const ALIGN_BLOCK:      usize = max_align(max_align(Self::ALIGN_HEADER, Self::ALIGN_TAIL), Self::ALIGN_VALUE);
+}
+
+impl<'s, Value, A: Allocator> StackFrameAllocator<'s, Value, A, Root> {
+    /// Creates a new StackFrameAllocator,
+    /// whose blocks are allocated and deallocated with `allocator`.
+    ///
+    /// Frames made from this StackFrameAllocator use clones of `allocator`.
+    ///
+    /// # Examples
+    ///
+    /// ```edition2020
+    /// # #![cfg_attr(feature = "nightly", feature(allocator_api))]
+    /// # use stack_frame_allocators::stack_frame_allocator::StackFrameAllocator;
+    /// use allocator_api2::alloc::Global;
+    ///
+    /// let stack = StackFrameAllocator::<usize>::new_in(Global);
+    /// stack.push(1);
+    /// ```
+    pub fn new_in(allocator: A) -> Self {
+        Self::with_stack_size_in(StackSize::default(), GrowthPolicy::default(), allocator)
+    }
+
+    /// Creates a new StackFrameAllocator like
+    /// [new_in](crate::stack_frame_allocator::StackFrameAllocator::new_in),
+    /// but returns an error instead of aborting if the first block can't be allocated.
+    pub fn try_new_in(allocator: A) -> Result<Self, AllocError> {
+        Self::try_with_stack_size_in(StackSize::default(), GrowthPolicy::default(), allocator)
+    }
+
+}
+
+impl<'s, Value, A: Allocator, Kind> StackFrameAllocator<'s, Value, A, Kind> {
+    /// Gets the size of a block large enough to fit a header and `elements` Values.
+    fn capacity_block_size(elements: usize) -> StackSize {
+        //Values are a multiple of their alignment in size,
+        //so only the first Value after the header can need padding
+        let bytes_needed = Self::SIZE_HEADER
+            + Self::ALIGN_VALUE
+            + elements * Self::SIZE_VALUE
+            + Self::SIZE_TAIL;
+
+        //block sizes are kept as powers of two,
+        //so the block tail at the end of the block stays aligned
+        StackSize::from_num_bytes(
+            bytes_needed.next_power_of_two().max(StackSize::default().bytes())
+        )
+    }
+
+    pub(crate) fn with_stack_size_in(size: StackSize, growth: GrowthPolicy, allocator: A) -> Self {
+        match Self::try_with_stack_size_in(size, growth, allocator) {
+            Ok(stack) => stack,
+            Err(AllocError) => handle_alloc_error(Self::block_layout(size))
+        }
+    }
+
+    pub(crate) fn try_with_stack_size_in(size: StackSize, growth: GrowthPolicy, allocator: A) -> Result<Self, AllocError> {
+        //the root frame's header has to fit in the first block,
+        //and the top of a block never reaches the block's tail
+        let min_bytes = Self::SIZE_HEADER + Self::SIZE_TAIL + 1;
+        assert!(
+            size.bytes() >= min_bytes,
+            "a block of {} bytes is too small, blocks must be at least {} bytes",
+            size.bytes(), min_bytes
+        );
 
         let allocated_block;
         let current_frame_pointer;
         unsafe {
-            allocated_block = std::alloc::alloc(
-                Layout::array::<u8>(size.bytes()).expect("could not allocate memory")
-            );
+            let layout = Self::block_layout(size);
+            allocated_block = match allocator.allocate(layout) {
+                Ok(block) => block.cast::<u8>().as_ptr(),
+                Err(_) => return Err(AllocError)
+            };
             
             //size.bytes() should be a multiple of a large power of two,
             //therefore size.bytes() should be aligned to BlockTail already,
@@ -125,9 +408,11 @@ impl<'s, Value> StackFrameAllocator<'s, Value> {
             //at the end of the block
             let block_tail = allocated_block.add(size.bytes() - Self::SIZE_TAIL);
             (block_tail as *mut BlockTail).write(BlockTail {
-                prev_block: std::ptr::null_mut(),
+                prev_block: core::ptr::null_mut(),
                 prev_block_bytes_used: 0 /* we'll never read this value if prev_block is null */,
-                next_block: std::ptr::null_mut()
+                prev_block_size: 0,
+                next_block: core::ptr::null_mut(),
+                next_block_size: 0
             });
 
             current_frame_pointer = allocated_block.add(Self::SIZE_HEADER);
@@ -142,14 +427,22 @@ impl<'s, Value> StackFrameAllocator<'s, Value> {
             (allocated_block as *mut StackFrameHeader).write(init_frame) 
         };
         
-        StackFrameAllocator {
+        Ok(StackFrameAllocator {
             size,
+            growth,
+            on_block_alloc: None,
+            allocator,
             current_frame: UnsafeCell::new(unsafe {
                 NonNull::new_unchecked(allocated_block as *mut StackFrameHeader)
             }),
             buffer_bytes_used: UnsafeCell::new(Self::SIZE_HEADER),
-            phantom: PhantomData::default()
-        }
+            block_size: UnsafeCell::new(size),
+            peak_bytes: UnsafeCell::new(Self::SIZE_HEADER),
+            bytes_below: UnsafeCell::new((allocated_block, 0)),
+            parent_peak: core::ptr::null(),
+            phantom: PhantomData,
+            kind: PhantomData
+        })
     }
 
     /// Creates a new frame to push elements onto.
@@ -173,16 +466,19 @@ impl<'s, Value> StackFrameAllocator<'s, Value> {
     /// It is still memory safe to pass references to the stack, 
     /// it is just not preferred.
     /// 
+    /// Whatever `scope` returns is handed back once the new frame has been popped,
+    /// so the result can't borrow from the new frame.
+    /// 
     /// # Examples
     /// 
     /// ```edition2020
-    /// # use stack_frame_allocators::stack_frame_allocator::StackFrameAllocator;
+    /// # use stack_frame_allocators::stack_frame_allocator::{StackFrame, StackFrameAllocator};
     /// 
     /// pub fn bad_foo(stack: &StackFrameAllocator<&str>) {
     ///     //do stuff here
     /// }
     /// 
-    /// pub fn good_foo(stack: StackFrameAllocator<&str>) {
+    /// pub fn good_foo(stack: StackFrame<&str>) {
     ///     //do stuff here
     /// }
     /// 
@@ -206,28 +502,92 @@ impl<'s, Value> StackFrameAllocator<'s, Value> {
     /// });
     /// # }
     /// ``` 
-    pub fn new_scope<'n, F>(&self, mut scope: F) 
+    pub fn new_scope<'n, R, F>(&self, scope: F) -> R
     where 
         's : 'n,
-        F : FnMut(StackFrameAllocator<'n, Value>)
+        A : Clone,
+        F : FnOnce(StackFrameAllocator<'n, Value, A, Child>) -> R
     {
         unsafe {
             let new_frame = StackFrameAllocator {
                 size: self.size,
-                current_frame: UnsafeCell::new((*self.current_frame.get()).clone()),
-                buffer_bytes_used: UnsafeCell::new(
-                    (*self.buffer_bytes_used.get()).clone()
-                ),
-                phantom: self.phantom
+                growth: self.growth,
+                on_block_alloc: self.on_block_alloc.clone(),
+                allocator: self.allocator.clone(),
+                current_frame: UnsafeCell::new(*self.current_frame.get()),
+                buffer_bytes_used: UnsafeCell::new(*self.buffer_bytes_used.get()),
+                block_size: UnsafeCell::new(*self.block_size.get()),
+                peak_bytes: UnsafeCell::new(*self.peak_bytes.get()),
+                bytes_below: UnsafeCell::new(*self.bytes_below.get()),
+                parent_peak: &self.peak_bytes,
+                phantom: self.phantom,
+                kind: PhantomData
             };
 
             new_frame.generate_frame();
 
-            //scope will automatically pop the new frame
-            scope(new_frame);
+            //scope will automatically pop the new frame,
+            //before its result is handed back
+            scope(new_frame)
         }
     }
 
+    /// Creates a new frame once, and calls `body` with it until `body` breaks,
+    /// dropping every value `body` pushed onto the frame between calls.
+    ///
+    /// This is the same as calling
+    /// [new_scope](crate::stack_frame_allocator::StackFrameAllocator::new_scope) in a loop,
+    /// except the frame's header is only written once, so hot loops,
+    /// like an interpreter running a loop body, skip setting up a new frame every iteration.
+    /// Whatever `body` breaks with is handed back once the frame has been popped.
+    ///
+    /// # Examples
+    ///
+    /// ```edition2020
+    /// # use stack_frame_allocators::stack_frame_allocator::StackFrameAllocator;
+    /// use std::ops::ControlFlow;
+    ///
+    /// let stack = StackFrameAllocator::<u64>::new();
+    /// let mut i = 0;
+    /// let total = stack.with_reused_frame(|stack| {
+    ///     //the frame starts out empty every iteration
+    ///     assert_eq!(stack.stats().items_in_frame, 0);
+    ///     stack.push(i);
+    ///
+    ///     i += 1;
+    ///     if i < 10 {ControlFlow::Continue(())} else {ControlFlow::Break(i)}
+    /// });
+    /// assert_eq!(total, 10);
+    /// ```
+    pub fn with_reused_frame<'n, R, F>(&self, mut body: F) -> R
+    where
+        's : 'n,
+        A : Clone,
+        F : FnMut(&StackFrameAllocator<'n, Value, A, Child>) -> ControlFlow<R>
+    {
+        self.new_scope(|stack| {
+            let start = stack.checkpoint();
+            loop {
+                if let ControlFlow::Break(result) = body(&stack) {
+                    return result;
+                }
+
+                //StackRefs made by body borrow the frame only for a single call,
+                //so none of them can point to the values being dropped
+                unsafe {
+                    if core::mem::needs_drop::<Value>() || cfg!(feature = "zeroize") {
+                        stack.restore(start);
+                    } else {
+                        //there's nothing to drop, so the frame only has to be rewound
+                        (*(*stack.current_frame.get()).as_ptr()).current_frame_ptr = start.current_frame_ptr;
+                        *stack.buffer_bytes_used.get() = start.buffer_bytes_used;
+                        *stack.block_size.get() = start.block_size;
+                    }
+                }
+            }
+        })
+    }
+
     /// Creates a new frame to push elements onto within the same scope
     /// 
     /// [new_scope][stack_frame_allocators::stack_frame_allocator::StackFrameDictAllocator::new_scope]
@@ -248,14 +608,14 @@ impl<'s, Value> StackFrameAllocator<'s, Value> {
     /// # Examples
     /// 
     /// ```edition2020
-    /// # use stack_frame_allocators::stack_frame_allocator::StackFrameAllocator;
+    /// # use stack_frame_allocators::stack_frame_allocator::{StackFrame, StackFrameAllocator};
     /// 
     /// pub struct Chainable {
     ///     //input fields here
     /// }
     /// 
     /// impl Chainable {
-    ///     pub fn chain(&self, stack: StackFrameAllocator<&str>, input: usize) -> Chainable {
+    ///     pub fn chain(&self, stack: StackFrame<&str>, input: usize) -> Chainable {
     ///         //do stuff
     ///         # return Chainable {};
     ///     }
@@ -271,16 +631,23 @@ impl<'s, Value> StackFrameAllocator<'s, Value> {
     ///      .chain(stack.new_frame(), 3);
     /// # }
     /// ```
-    pub fn new_frame(&self) -> StackFrameAllocator<'s, Value> {
+    pub fn new_frame<'a>(&'a self) -> StackFrameAllocator<'a, Value, A, Child> where A: Clone {
         let stack;
         unsafe {
             stack = StackFrameAllocator {
                 size: self.size,
-                current_frame: UnsafeCell::new((*self.current_frame.get()).clone()),
-                buffer_bytes_used: UnsafeCell::new(
-                    (*self.buffer_bytes_used.get()).clone()
-                ),
-                phantom: self.phantom
+                growth: self.growth,
+                on_block_alloc: self.on_block_alloc.clone(),
+                allocator: self.allocator.clone(),
+                current_frame: UnsafeCell::new(*self.current_frame.get()),
+                buffer_bytes_used: UnsafeCell::new(*self.buffer_bytes_used.get()),
+                block_size: UnsafeCell::new(*self.block_size.get()),
+                peak_bytes: UnsafeCell::new(*self.peak_bytes.get()),
+                bytes_below: UnsafeCell::new(*self.bytes_below.get()),
+                //the frame borrows this allocator, so its peak can be handed back
+                parent_peak: &self.peak_bytes,
+                phantom: self.phantom,
+                kind: PhantomData
             };
 
             stack.generate_frame();
@@ -289,7 +656,466 @@ impl<'s, Value> StackFrameAllocator<'s, Value> {
         return stack;
     }
 
-    unsafe fn generate_frame<'n>(&self) {
+    /// Counts how many frames are on the stack, by following the headers
+    /// from the current frame down to the root frame.
+    ///
+    /// The root frame has a depth of 1, and every nested frame adds 1.
+    ///
+    /// # Examples
+    ///
+    /// ```edition2020
+    /// # use stack_frame_allocators::stack_frame_allocator::StackFrameAllocator;
+    ///
+    /// let stack = StackFrameAllocator::<u64>::new();
+    /// assert_eq!(stack.depth(), 1);
+    ///
+    /// stack.new_scope(|stack| {
+    ///     stack.new_scope(|stack| {
+    ///         assert_eq!(stack.depth(), 3);
+    ///     });
+    /// });
+    /// ```
+    pub fn depth(&self) -> usize {
+        let mut stack_frame = unsafe {(*self.current_frame.get()).as_ref()};
+        let mut depth = 1;
+
+        while let Some(previous_frame) = stack_frame.previous_frame {
+            stack_frame = previous_frame;
+            depth += 1;
+        }
+
+        return depth;
+    }
+
+    /// Whether the current frame is the root frame, which is the only frame
+    /// owning the whole stack, like the allocator made by
+    /// [new](crate::stack_frame_allocator::StackFrameAllocator::new).
+    ///
+    /// # Examples
+    ///
+    /// ```edition2020
+    /// # use stack_frame_allocators::stack_frame_allocator::StackFrameAllocator;
+    ///
+    /// let stack = StackFrameAllocator::<u64>::new();
+    /// assert!(stack.is_root());
+    ///
+    /// stack.new_scope(|stack| {
+    ///     assert!(!stack.is_root());
+    /// });
+    /// ```
+    pub fn is_root(&self) -> bool {
+        unsafe {(*self.current_frame.get()).as_ref().previous_frame.is_none()}
+    }
+
+    /// Gets the amount of bytes in the current block that can hold frames and values,
+    /// which is the block size minus the block tail and the root header.
+    ///
+    /// # Examples
+    ///
+    /// ```edition2020
+    /// # use stack_frame_allocators::stack_frame_allocator::StackFrameAllocator;
+    ///
+    /// let stack = StackFrameAllocator::<u64>::new();
+    /// assert!(stack.usable_block_size() < 1024);
+    /// ```
+    pub fn usable_block_size(&self) -> usize {
+        return self.real_size().bytes() - Self::SIZE_HEADER;
+    }
+
+    /// Gets how many values always fit in a single block of the current block size,
+    /// assuming the worst case padding between the header and the first value.
+    ///
+    /// # Examples
+    ///
+    /// ```edition2020
+    /// # use stack_frame_allocators::stack_frame_allocator::StackFrameAllocator;
+    ///
+    /// let stack = StackFrameAllocator::<u64>::new();
+    /// for i in 0..stack.capacity_per_block() {
+    ///     stack.push(i as u64);
+    /// }
+    /// assert_eq!(stack.stats().using_blocks, 1);
+    /// ```
+    pub fn capacity_per_block(&self) -> usize {
+        if Self::SIZE_VALUE == 0 {
+            return usize::MAX;
+        }
+
+        //the top of a block never reaches its tail,
+        //so the last byte before it can't hold a value either
+        return (self.usable_block_size() - (Self::ALIGN_VALUE - 1) - 1) / Self::SIZE_VALUE;
+    }
+
+    /// Gets how many more values can be pushed onto the current block
+    /// before pushing spills over into the next block.
+    ///
+    /// Zero sized values never take up room in a block, so their capacity is `usize::MAX`.
+    ///
+    /// # Examples
+    ///
+    /// ```edition2020
+    /// # use stack_frame_allocators::stack_frame_allocator::StackFrameAllocator;
+    ///
+    /// let stack = StackFrameAllocator::<u64>::new();
+    /// stack.push(0);
+    ///
+    /// for i in 0..stack.capacity() {
+    ///     stack.push(i as u64);
+    /// }
+    /// assert_eq!(stack.capacity(), 0);
+    /// assert_eq!(stack.stats().using_blocks, 1);
+    /// ```
+    pub fn capacity(&self) -> usize {
+        if Self::SIZE_VALUE == 0 {
+            return usize::MAX;
+        }
+
+        unsafe {
+            let value_padding = (*(*self.current_frame.get()).as_ptr())
+                .current_frame_ptr
+                .align_offset(Self::ALIGN_VALUE);
+
+            //the top of a block never reaches its tail
+            let bytes_left = self.real_size().bytes() - *self.buffer_bytes_used.get() - 1;
+
+            return bytes_left.saturating_sub(value_padding) / Self::SIZE_VALUE;
+        }
+    }
+
+    /// Gets the amount of bytes the current frame takes up, including its header,
+    /// summed over every block the frame spans.
+    ///
+    /// Unlike the `bytes_used` of [stats](crate::stack_frame_allocator::StackFrameAllocator::stats),
+    /// this doesn't count previous frames sharing the current block.
+    ///
+    /// # Examples
+    ///
+    /// ```edition2020
+    /// # use stack_frame_allocators::stack_frame_allocator::StackFrameAllocator;
+    ///
+    /// let stack = StackFrameAllocator::<u64>::new();
+    /// stack.push(1);
+    ///
+    /// stack.new_scope(|stack| {
+    ///     stack.push(2);
+    ///     stack.push(3);
+    ///     //the header, and two u64s
+    ///     assert_eq!(stack.frame_bytes(), 16 + 2 * 8);
+    /// });
+    /// ```
+    pub fn frame_bytes(&self) -> usize {
+        return unsafe {self.walker()}.frame_bytes();
+    }
+
+    /// Pops the current frame without having to wait for a scope to end,
+    /// dropping every value in it, and continues pushing onto the previous frame.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the current frame is the root frame.
+    ///
+    /// # Safety
+    ///
+    /// All StackRefs into the current frame must have been dropped,
+    /// since they would otherwise point to dropped values.
+    ///
+    /// The previous frame must belong to this allocator.
+    /// Popping the frame of an allocator created by
+    /// [new_frame](crate::stack_frame_allocator::StackFrameAllocator::new_frame) or
+    /// [new_scope](crate::stack_frame_allocator::StackFrameAllocator::new_scope)
+    /// moves it onto its parent's frame, which it would then drop
+    /// a second time when it goes out of scope.
+    pub unsafe fn pop_frame(&self) {
+        let stack_frame = (*self.current_frame.get()).as_ref();
+        let Some(previous_frame) = stack_frame.previous_frame else {
+            panic!("cannot pop the root frame");
+        };
+
+        let mut walker = self.walker();
+        while let Some(value_ptr) = walker.next_in_frame() {
+            crate::drop_element(value_ptr.cast::<Value>());
+        }
+
+        //moving onto the previous frame leaves the walker
+        //at the top of the previous frame
+        walker.next_frame();
+        let (_, bytes_used) = walker.position();
+        let block_size = StackSize(walker.block_size());
+
+        *self.current_frame.get() = NonNull::from(previous_frame);
+        *self.buffer_bytes_used.get() = bytes_used;
+        *self.block_size.get() = block_size;
+    }
+
+    /// Drops the most recently pushed Values in the current frame,
+    /// until only the first `keep` Values pushed onto it remain.
+    ///
+    /// Later pushes reuse the space of the dropped Values,
+    /// including any blocks the dropped Values spilled over to.
+    /// Nothing happens if the current frame holds `keep` Values or less.
+    ///
+    /// # Safety
+    ///
+    /// All StackRefs to the dropped Values must have been dropped,
+    /// since they would otherwise point to dropped Values.
+    ///
+    /// # Examples
+    ///
+    /// ```edition2020
+    /// # use stack_frame_allocators::stack_frame_allocator::StackFrameAllocator;
+    ///
+    /// let mut stack = StackFrameAllocator::<u64>::new();
+    /// for i in 0..5 {
+    ///     stack.push(i);
+    /// }
+    ///
+    /// unsafe {stack.truncate_frame(2)};
+    /// assert_eq!(stack.peek(), Some(&1));
+    ///
+    /// stack.push(5);
+    /// assert_eq!(stack.peek(), Some(&5));
+    /// ```
+    pub unsafe fn truncate_frame(&self, keep: usize) {
+        let mut counter = self.walker();
+        let mut len = 0;
+        while counter.next_in_frame().is_some() {
+            len += 1;
+        }
+
+        if len <= keep {
+            return;
+        }
+
+        let mut walker = self.walker();
+        for _ in keep..len {
+            let value_ptr = walker.next_in_frame().expect("the frame holds len values");
+            crate::drop_element(value_ptr.cast::<Value>());
+        }
+
+        let stack_frame = (*self.current_frame.get()).as_ptr();
+        let (top, bytes_used) = match walker.next_in_frame() {
+            //the top of the frame sits right after the last Value kept
+            Some(value_ptr) => {
+                let (_, bytes_before) = walker.position();
+                (value_ptr.add(Self::SIZE_VALUE), bytes_before + Self::SIZE_VALUE)
+            },
+            //the walker stops at the first Value's place after the header,
+            //but an empty frame's top sits right after the header
+            None => {
+                let (items_start, bytes_before) = walker.position();
+                let header_end = stack_frame.cast::<u8>().add(Self::SIZE_HEADER);
+                (header_end, bytes_before - (items_start as usize - header_end as usize))
+            }
+        };
+
+        (*stack_frame).current_frame_ptr = top;
+        *self.buffer_bytes_used.get() = bytes_used;
+        *self.block_size.get() = StackSize(walker.block_size());
+    }
+
+    /// Moves every Value out of the current frame, from the last pushed to the first pushed,
+    /// leaving the frame empty.
+    ///
+    /// Values the iterator hasn't yielded yet are dropped when the iterator is dropped.
+    /// If the iterator is leaked instead, they're left on the frame.
+    ///
+    /// frames made with [new_frame](crate::stack_frame_allocator::StackFrameAllocator::new_frame)
+    /// must already have been dropped, the same as with
+    /// [clear](crate::stack_frame_allocator::StackFrameAllocator::clear).
+    ///
+    /// # Examples
+    ///
+    /// ```edition2020
+    /// # use stack_frame_allocators::stack_frame_allocator::StackFrameAllocator;
+    ///
+    /// let mut stack = StackFrameAllocator::<String>::new();
+    /// stack.push(String::from("a"));
+    /// stack.push(String::from("b"));
+    ///
+    /// let owned: Vec<String> = stack.drain_frame().collect();
+    /// assert_eq!(owned, ["b", "a"]);
+    /// assert_eq!(stack.peek(), None);
+    /// ```
+    pub fn drain_frame(&mut self) -> DrainFrame<'_, 's, Value, A, Kind> {
+        DrainFrame {
+            walker: unsafe {self.walker()},
+            stack: self
+        }
+    }
+
+    /// Marks the top of the current frame, so everything pushed after it
+    /// can be dropped with [restore](crate::stack_frame_allocator::StackFrameAllocator::restore).
+    ///
+    /// Unlike creating a new frame, nothing is pushed onto the stack.
+    ///
+    /// # Examples
+    ///
+    /// ```edition2020
+    /// # use stack_frame_allocators::stack_frame_allocator::StackFrameAllocator;
+    ///
+    /// let mut stack = StackFrameAllocator::<u64>::new();
+    /// stack.push(1);
+    ///
+    /// let checkpoint = stack.checkpoint();
+    /// stack.push(2);
+    /// stack.push(3);
+    ///
+    /// unsafe {stack.restore(checkpoint)};
+    /// assert_eq!(stack.peek(), Some(&1));
+    /// ```
+    pub fn checkpoint(&self) -> Checkpoint {
+        unsafe {
+            let stack_frame = (*self.current_frame.get()).as_ptr();
+
+            Checkpoint {
+                stack_frame: stack_frame.cast::<u8>(),
+                current_frame_ptr: (*stack_frame).current_frame_ptr,
+                buffer_bytes_used: *self.buffer_bytes_used.get(),
+                block_size: *self.block_size.get()
+            }
+        }
+    }
+
+    /// Drops every Value pushed onto the current frame since `checkpoint` was made,
+    /// and continues pushing from where the checkpoint was made.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `checkpoint` was made in a different frame than the current frame.
+    ///
+    /// # Safety
+    ///
+    /// All StackRefs to the dropped Values must have been dropped,
+    /// since they would otherwise point to dropped Values.
+    ///
+    /// `checkpoint` must have been made by this allocator,
+    /// and the frame must not have been restored or truncated
+    /// to before `checkpoint` since it was made.
+    pub unsafe fn restore(&self, checkpoint: Checkpoint) {
+        let stack_frame = (*self.current_frame.get()).as_ptr();
+        assert!(
+            stack_frame.cast::<u8>() == checkpoint.stack_frame,
+            "cannot restore a checkpoint made in a different frame"
+        );
+
+        //Values pushed before the checkpoint are either in the checkpoint's block,
+        //below the checkpoint, or in a block before it
+        let checkpoint_block = checkpoint.current_frame_ptr.sub(checkpoint.buffer_bytes_used);
+        let mut walker = self.walker();
+        while let Some(value_ptr) = walker.next_in_frame() {
+            let in_checkpoint_block = checkpoint_block <= value_ptr &&
+                value_ptr < checkpoint.current_frame_ptr;
+            if in_checkpoint_block {
+                break;
+            }
+
+            crate::drop_element(value_ptr.cast::<Value>());
+        }
+
+        (*stack_frame).current_frame_ptr = checkpoint.current_frame_ptr;
+        *self.buffer_bytes_used.get() = checkpoint.buffer_bytes_used;
+        *self.block_size.get() = checkpoint.block_size;
+    }
+
+    /// Creates a new frame on top of the current frame, returning a guard
+    /// which pops the new frame and drops all of its items when it's dropped.
+    ///
+    /// Unlike [new_scope](crate::stack_frame_allocator::StackFrameAllocator::new_scope),
+    /// the new frame lives as long as the guard does, instead of as long as a closure,
+    /// so entering and exiting frames doesn't have to follow the shape of the code.
+    /// The guard dereferences to this allocator, so items pushed through it
+    /// are pushed onto the new frame, and can't outlive the guard.
+    ///
+    /// # Safety
+    ///
+    /// Guards must be dropped in the reverse order they were created in.
+    /// Entering a frame through an existing guard borrows that guard,
+    /// so the borrow checker enforces this, however two guards made
+    /// directly from this allocator can be dropped out of order,
+    /// which pops the wrong frame while the other guard is still alive.
+    ///
+    /// This allocator must not be used directly while a guard is alive,
+    /// since items pushed that way aren't tied to the guard
+    /// and are dropped along with the guard's frame.
+    ///
+    /// Guards must not be forgotten, the guard's frame would never be popped,
+    /// so every later push would land on it.
+    ///
+    /// # Examples
+    ///
+    /// ```edition2020
+    /// # use stack_frame_allocators::stack_frame_allocator::StackFrameAllocator;
+    ///
+    /// let stack = StackFrameAllocator::<u64>::new();
+    /// let a = stack.push(0);
+    ///
+    /// {
+    ///     let guard = unsafe {stack.enter_frame()};
+    ///     let mut b = guard.push(1);
+    ///
+    ///     let inner = unsafe {guard.enter_frame()};
+    ///     let c = inner.push(2);
+    ///     *b += *c + *a;
+    ///     assert_eq!(*b, 3);
+    /// }
+    ///
+    /// assert_eq!(*a, 0);
+    /// ```
+    pub unsafe fn enter_frame(&self) -> FrameGuard<'_, 's, Value, A, Kind> {
+        self.generate_frame();
+
+        FrameGuard { stack: self }
+    }
+
+    /// Drops every value on the stack, keeping every allocated block
+    /// around to be reused by later pushes.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the current frame is not the root frame,
+    /// since only the allocator owning the root frame owns the whole stack.
+    ///
+    /// frames made with [new_frame](crate::stack_frame_allocator::StackFrameAllocator::new_frame)
+    /// must already have been dropped, the same as when dropping the allocator.
+    ///
+    /// # Examples
+    ///
+    /// ```edition2020
+    /// # use stack_frame_allocators::stack_frame_allocator::StackFrameAllocator;
+    ///
+    /// let mut stack = StackFrameAllocator::<u64>::new();
+    /// for i in 0..200 {
+    ///     stack.push(i);
+    /// }
+    /// assert_eq!(stack.allocated_blocks(), 2);
+    ///
+    /// stack.clear();
+    /// for i in 0..200 {
+    ///     stack.push(i);
+    /// }
+    /// assert_eq!(stack.allocated_blocks(), 2);
+    /// ```
+    pub fn clear(&mut self) {
+        unsafe {
+            if !self.is_root() {
+                panic!("cannot clear a frame that isn't the root frame");
+            }
+            let stack_frame = (*self.current_frame.get()).as_ptr();
+
+            //the root frame holds every value on the stack
+            let mut walker = self.walker();
+            while let Some(value_ptr) = walker.next_in_frame() {
+                crate::drop_element(value_ptr.cast::<Value>());
+            }
+
+            //the root header always sits at the start of the first block
+            (*stack_frame).current_frame_ptr = stack_frame.cast::<u8>().add(Self::SIZE_HEADER);
+            *self.buffer_bytes_used.get() = Self::SIZE_HEADER;
+            *self.block_size.get() = self.size;
+        }
+    }
+
+    unsafe fn generate_frame(&self) {
         let header_padding = (*(*self.current_frame.get()).as_ptr())
             .current_frame_ptr
             .align_offset(Self::ALIGN_HEADER);
@@ -302,30 +1128,21 @@ impl<'s, Value> StackFrameAllocator<'s, Value> {
 
             (*(*self.current_frame.get()).as_ptr())
                 .current_frame_ptr
-                .add(header_padding + Self::SIZE_HEADER)
+                .add(header_padding)
         } else {
-            let curr_block_tail = self.get_block_tail();
-            
-            if curr_block_tail.next_block.is_null() {
-                let allocated_block = unsafe {std::alloc::alloc(
-                    Layout::array::<u8>(self.size.bytes())
-                        .expect("could not allocate memory")
-                )};
-
-                let next_block_tail = allocated_block.add(
-                    self.size.bytes() - Self::SIZE_TAIL
-                );
-                //eprintln!("writing block tail at {:?}", next_block_tail);
-                (next_block_tail as *mut BlockTail).write(BlockTail {
-                    prev_block: (*self.current_frame.get()).as_ptr().cast(),
-                    prev_block_bytes_used: (*self.buffer_bytes_used.get()),
-                    next_block: std::ptr::null_mut()
-                });
+            let (next_block, next_block_size) = self.link_next_block(
+                self.get_block_tail(),
+                (*self.current_frame.get()).as_ref().current_frame_ptr,
+                *self.buffer_bytes_used.get(),
+                *self.block_size.get()
+            );
 
-                curr_block_tail.next_block = allocated_block;
-            }
+            //the header sits at the start of the next block,
+            //so the only bytes used in that block are the header's
+            *self.buffer_bytes_used.get() = Self::SIZE_HEADER;
+            *self.block_size.get() = next_block_size;
 
-            curr_block_tail.next_block
+            next_block
         };
 
         let current_frame_ptr = mem.add(Self::SIZE_HEADER);
@@ -338,12 +1155,38 @@ impl<'s, Value> StackFrameAllocator<'s, Value> {
         (mem as *mut StackFrameHeader).write(new_frame);
 
         *self.current_frame.get() = NonNull::new_unchecked(mem as *mut StackFrameHeader);
+
+        self.track_peak(current_frame_ptr, *self.buffer_bytes_used.get());
+    }
+
+    /// Raises the peak to the bytes used by every block up to the block `top` is in,
+    /// where `bytes_used` bytes of that block are used.
+    #[inline]
+    unsafe fn track_peak(&self, top: *mut u8, bytes_used: usize) {
+        let block_start = top.sub(bytes_used);
+        let (known_block, mut bytes_below) = *self.bytes_below.get();
+
+        //the blocks before the top only change once the top moves to another block
+        if known_block != block_start {
+            bytes_below = 0;
+            let mut block_tail = &*block_start.add(self.real_size().bytes()).cast::<BlockTail>();
+            while !block_tail.prev_block.is_null() {
+                let prev_block_start = block_tail.prev_block.sub(block_tail.prev_block_bytes_used);
+                bytes_below += block_tail.prev_block_bytes_used;
+                block_tail = Self::block_tail_at(prev_block_start, StackSize(block_tail.prev_block_size));
+            }
+            *self.bytes_below.get() = (block_start, bytes_below);
+        }
+
+        let peak = &mut *self.peak_bytes.get();
+        *peak = (*peak).max(bytes_below + bytes_used);
     }
 
     /// The Tail End of a Memory Block is reserved for storing
     /// the address to the previous block, 
     /// how many bytes of the previous block is used,
-    /// and the address to the next block.
+    /// the size of the previous block,
+    /// the address to the next block, and the size of the next block.
     /// This Tail effectively reduces the usable size of the block
     /// 
     /// # Examples
@@ -356,15 +1199,20 @@ impl<'s, Value> StackFrameAllocator<'s, Value> {
     ///   0x0000_0000_0000_0005
     ///   0x0000_aaaa_aaaa_aaa0 <- address to prev block
     ///   0x0000_0000_0000_0400 <- bytes used of prev block
+    ///   0x0000_0000_0000_0400 <- size of prev block
     ///   0x0000_ffff_ffff_fff0 <- address to next block
+    ///   0x0000_0000_0000_0800 <- size of next block
     /// ```
-    /// has size 8 words, however 3 words are reserved
+    /// has size 10 words, however 5 words are reserved
     /// so `real_size(&self)` will return 5 words worth of space
+    /// for the block the top of the current frame lives in
     #[inline]
     fn real_size(&self) -> StackSize {
-        StackSize(self.size.bytes() - Self::SIZE_TAIL)
+        StackSize(unsafe {(*self.block_size.get()).bytes()} - Self::SIZE_TAIL)
     }
 
+    //the tail lives in a block the allocator points to, not in the allocator itself
+    #[allow(clippy::mut_from_ref)]
     unsafe fn get_block_tail(&self) -> &mut BlockTail {
         let offset = self.real_size().bytes() - *self.buffer_bytes_used.get();
         
@@ -379,6 +1227,10 @@ impl<'s, Value> StackFrameAllocator<'s, Value> {
 
     /// Pushes a Value into the current frame,
     /// returning a StackRef to the Value.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the Value can't fit in an empty block.
     /// 
     /// # Examples
     /// 
@@ -396,90 +1248,781 @@ impl<'s, Value> StackFrameAllocator<'s, Value> {
     pub fn push<'a>(
         &'a self, 
         value: Value
-    ) -> StackRef<'a, Value> {
-        let (value_padding, can_push_to_block, current_frame_ptr);
-        let value_ptr: *mut u8;
-        
-        unsafe {
-            current_frame_ptr = (*(*self.current_frame.get()).as_ptr())
-                .current_frame_ptr;
-            value_padding = current_frame_ptr
-                .align_offset(Self::ALIGN_VALUE);
-            value_ptr = current_frame_ptr.add(value_padding);
-            can_push_to_block = *self.buffer_bytes_used.get() + 
-                value_padding + Self::SIZE_VALUE < 
-                self.real_size().bytes();
-        }
-        
-        if can_push_to_block { unsafe {
-            // eprintln!("writing key of size {} at {:?} with {}",
-            //     Self::SIZE_KEY, key_ptr, &key
-            // );
-            // eprintln!("writing key of size {} at {:?} with {}",
-            //     Self::SIZE_VALUE, value_ptr, &value
-            // );
-            (value_ptr as *mut Value).write(value);
-            let offset = value_padding + Self::SIZE_VALUE;
-            (*(*self.current_frame.get()).as_ptr()).current_frame_ptr = {
-                current_frame_ptr.add(offset)
-            };
+    ) -> StackRef<'a, 's, Value> {
+        self.try_push(value).unwrap_or_else(|error| self.push_failed(error))
+    }
 
-            *self.buffer_bytes_used.get() += offset;
+    /// Pushes the default Value into the current frame,
+    /// returning a StackRef to the Value.
+    ///
+    /// # Examples
+    ///
+    /// ```edition2020
+    /// # use stack_frame_allocators::stack_frame_allocator::StackFrameAllocator;
+    ///
+    /// let stack = StackFrameAllocator::<Vec<u8>>::new();
+    ///
+    /// let mut slot = stack.push_default();
+    /// slot.get_mut().push(1);
+    /// assert_eq!(*slot.get(), vec![1]);
+    /// ```
+    pub fn push_default(&self) -> StackRef<'_, 's, Value> where Value: Default {
+        self.push(Value::default())
+    }
 
-            return StackRef {
-                value: value_ptr as *mut Value,
-                phantom: PhantomData::default()
-            };
-        }} else { unsafe {
-            let curr_block_tail = self.get_block_tail();
-            
-            //if there is no next block, create one
-            if curr_block_tail.next_block.is_null() {
-                let allocated_block = std::alloc::alloc(
-                    Layout::array::<u8>(self.size.bytes())
-                        .expect("could not allocate memory")
-                );
+    /// Pushes a Value into the current frame,
+    /// returning a StackRef to the Value,
+    /// or a PushError if a new block was needed but couldn't be allocated,
+    /// or if the Value can't fit in an empty block.
+    /// 
+    /// # Examples
+    /// 
+    /// ```edition2020
+    /// # use stack_frame_allocators::stack_frame_allocator::StackFrameAllocator;
+    /// use stack_frame_allocators::error::PushError;
+    /// 
+    /// let stack = StackFrameAllocator::<usize>::new();
+    /// 
+    /// let a = stack.try_push(1).unwrap().get();
+    /// assert_eq!(*a, 1);
+    ///
+    /// let huge = StackFrameAllocator::<[u8; 4096]>::new();
+    /// assert!(matches!(huge.try_push([0; 4096]), Err(PushError::TooLarge { required: 4096, .. })));
+    /// ```
+    pub fn try_push<'a>(
+        &'a self, 
+        value: Value
+    ) -> Result<StackRef<'a, 's, Value>, PushError> {
+        let value_ptr = self.try_bump(1)?;
 
-                let next_block_tail = allocated_block
-                    .add(self.size.bytes() - Self::SIZE_TAIL);
-                //eprintln!("writing block tail at {:?}", next_block_tail);
-                (next_block_tail as *mut BlockTail).write(BlockTail {
-                    prev_block: (*self.current_frame.get()).as_ref().current_frame_ptr,
-                    prev_block_bytes_used: (*self.buffer_bytes_used.get()),
-                    next_block: std::ptr::null_mut()
-                });
+        unsafe {
+            value_ptr.write(value);
+        }
+
+        return Ok(StackRef {
+            value: value_ptr,
+            phantom: PhantomData
+        });
+    }
+
+    /// Pushes the Value returned by `f` into the current frame,
+    /// returning a StackRef to the Value.
+    ///
+    /// The Value's place in the frame is found before `f` is called,
+    /// so `f`'s result can be written straight into the frame,
+    /// instead of being moved through a temporary first like with
+    /// [push](crate::stack_frame_allocator::StackFrameAllocator::push).
+    ///
+    /// If `f` panics, the frame is rolled back to how it was before calling alloc_with.
+    /// The allocator is borrowed mutably, so `f` can't push onto the frame
+    /// above the Value's place, which rolling back would hand out a second time.
+    ///
+    /// # Examples
+    ///
+    /// ```edition2020
+    /// # use stack_frame_allocators::stack_frame_allocator::StackFrameAllocator;
+    ///
+    /// let mut stack = StackFrameAllocator::<[u64; 64]>::new();
+    ///
+    /// let table = stack.alloc_with(|| core::array::from_fn(|i| i as u64 * 2)).get();
+    /// assert_eq!(table[32], 64);
+    /// ```
+    ///
+    /// ```compile_fail
+    /// # use stack_frame_allocators::stack_frame_allocator::StackFrameAllocator;
+    ///
+    /// let mut stack = StackFrameAllocator::<u64>::new();
+    /// stack.alloc_with(|| *stack.push(1).get());
+    /// ```
+    pub fn alloc_with<F: FnOnce() -> Value>(&mut self, f: F) -> StackRef<'_, 's, Value> {
+        //restores the top of the current frame if f panics,
+        //so the Value's place is never read or dropped while uninitialized
+        struct Rollback<'r, 's, Value, A: Allocator, Kind> {
+            stack: &'r StackFrameAllocator<'s, Value, A, Kind>,
+            current_frame_ptr: *mut u8,
+            buffer_bytes_used: usize,
+            block_size: StackSize
+        }
+
+        impl<'r, 's, Value, A: Allocator, Kind> Drop for Rollback<'r, 's, Value, A, Kind> {
+            fn drop(&mut self) {
+                unsafe {
+                    (*(*self.stack.current_frame.get()).as_ptr()).current_frame_ptr =
+                        self.current_frame_ptr;
+                    *self.stack.buffer_bytes_used.get() = self.buffer_bytes_used;
+                    *self.stack.block_size.get() = self.block_size;
+                }
+            }
+        }
+
+        let rollback = unsafe {
+            Rollback {
+                stack: self,
+                current_frame_ptr: (*(*self.current_frame.get()).as_ptr()).current_frame_ptr,
+                buffer_bytes_used: *self.buffer_bytes_used.get(),
+                block_size: *self.block_size.get()
+            }
+        };
+
+        let value_ptr = self.try_bump(1)
+            .unwrap_or_else(|error| self.push_failed(error));
+
+        let value = f();
+        core::mem::forget(rollback);
+
+        unsafe {
+            value_ptr.write(value);
+        }
+
+        return StackRef {
+            value: value_ptr,
+            phantom: PhantomData
+        };
+    }
+
+    /// Copies a slice of Values into the current frame,
+    /// returning a StackRef to the copied slice.
+    ///
+    /// The Values are pushed contiguously, so if they don't fit
+    /// in the current block, they're all placed in the next block.
+    /// Each Value is still its own Value in the frame afterwards,
+    /// the same as if they had been pushed one by one.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the slice is too large to fit in a single block.
+    ///
+    /// # Examples
+    ///
+    /// ```edition2020
+    /// # use stack_frame_allocators::stack_frame_allocator::StackFrameAllocator;
+    ///
+    /// let stack = StackFrameAllocator::<i32>::new();
+    ///
+    /// let slice = stack.push_slice(&[1, 2, 3, 4]).get();
+    /// assert_eq!(slice, &[1, 2, 3, 4]);
+    /// ```
+    pub fn push_slice<'a>(&'a self, values: &[Value]) -> StackRef<'a, 's, [Value]> where Value: Copy {
+        let value_ptr = self.try_bump(values.len())
+            .unwrap_or_else(|error| self.push_failed(error));
+
+        unsafe {
+            core::ptr::copy_nonoverlapping(values.as_ptr(), value_ptr, values.len());
+        }
+
+        return StackRef {
+            value: core::ptr::slice_from_raw_parts_mut(value_ptr, values.len()),
+            phantom: PhantomData
+        };
+    }
+
+    /// Moves every Value out of a Vec into the current frame,
+    /// returning a StackRef to the moved Values as a slice.
+    ///
+    /// Like [push_slice](crate::stack_frame_allocator::StackFrameAllocator::push_slice),
+    /// the Values are pushed contiguously into a single block,
+    /// and each Value is still its own Value in the frame afterwards,
+    /// but the Values don't have to be Copy.
+    /// The Vec's buffer is freed once the Values are moved.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the Values are too large to fit in a single block.
+    ///
+    /// # Examples
+    ///
+    /// ```edition2020
+    /// # use stack_frame_allocators::stack_frame_allocator::StackFrameAllocator;
+    ///
+    /// let stack = StackFrameAllocator::<String>::new();
+    ///
+    /// let words = stack.push_all(vec!["a".to_string(), "b".to_string()]).get();
+    /// assert_eq!(words, ["a", "b"]);
+    /// ```
+    pub fn push_all<'a>(&'a self, mut values: Vec<Value>) -> StackRef<'a, 's, [Value]> {
+        let len = values.len();
+        let value_ptr = self.try_bump(len)
+            .unwrap_or_else(|error| self.push_failed(error));
+
+        unsafe {
+            core::ptr::copy_nonoverlapping(values.as_ptr(), value_ptr, len);
+            //the Values now belong to the frame,
+            //so the Vec only frees its buffer when it's dropped
+            values.set_len(0);
+        }
+
+        return StackRef {
+            value: core::ptr::slice_from_raw_parts_mut(value_ptr, len),
+            phantom: PhantomData
+        };
+    }
+
+    /// Pushes `len` Values into the current frame, where the Value at index `i` is `f(i)`,
+    /// returning a StackRef to them as a slice.
+    ///
+    /// Like [push_slice](crate::stack_frame_allocator::StackFrameAllocator::push_slice),
+    /// the Values are pushed contiguously into a single block,
+    /// and each Value is still its own Value in the frame afterwards.
+    /// Each Value is written straight into the frame, without building a temporary slice first.
+    ///
+    /// If `f` panics, the Values it already made are dropped,
+    /// and the frame is rolled back to how it was before calling alloc_slice_fill_with.
+    /// The allocator is borrowed mutably, so `f` can't push onto the frame
+    /// above the slice, which rolling back would hand out a second time.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `len` Values are too large to fit in a single block.
+    ///
+    /// # Examples
+    ///
+    /// ```edition2020
+    /// # use stack_frame_allocators::stack_frame_allocator::StackFrameAllocator;
+    ///
+    /// let mut stack = StackFrameAllocator::<String>::new();
+    ///
+    /// let names = stack.alloc_slice_fill_with(3, |i| format!("item {}", i)).get();
+    /// assert_eq!(names, ["item 0", "item 1", "item 2"]);
+    /// ```
+    pub fn alloc_slice_fill_with<F: FnMut(usize) -> Value>(
+        &mut self,
+        len: usize,
+        mut f: F
+    ) -> StackRef<'_, 's, [Value]> {
+        //drops the Values made so far and restores the top of the current frame if f panics,
+        //so the slice is never read or dropped while partially uninitialized
+        struct Fill<'r, 's, Value, A: Allocator, Kind> {
+            stack: &'r StackFrameAllocator<'s, Value, A, Kind>,
+            current_frame_ptr: *mut u8,
+            buffer_bytes_used: usize,
+            block_size: StackSize,
+            values: *mut Value,
+            initialized: usize
+        }
+
+        impl<'r, 's, Value, A: Allocator, Kind> Drop for Fill<'r, 's, Value, A, Kind> {
+            fn drop(&mut self) {
+                unsafe {
+                    for i in 0..self.initialized {
+                        crate::drop_element(self.values.add(i));
+                    }
+
+                    (*(*self.stack.current_frame.get()).as_ptr()).current_frame_ptr =
+                        self.current_frame_ptr;
+                    *self.stack.buffer_bytes_used.get() = self.buffer_bytes_used;
+                    *self.stack.block_size.get() = self.block_size;
+                }
+            }
+        }
+
+        let mut fill = unsafe {
+            Fill {
+                stack: self,
+                current_frame_ptr: (*(*self.current_frame.get()).as_ptr()).current_frame_ptr,
+                buffer_bytes_used: *self.buffer_bytes_used.get(),
+                block_size: *self.block_size.get(),
+                values: core::ptr::null_mut(),
+                initialized: 0
+            }
+        };
+
+        fill.values = self.try_bump(len)
+            .unwrap_or_else(|error| self.push_failed(error));
+
+        while fill.initialized < len {
+            let value = f(fill.initialized);
+            unsafe {
+                fill.values.add(fill.initialized).write(value);
+            }
+            fill.initialized += 1;
+        }
+
+        let values = fill.values;
+        core::mem::forget(fill);
+
+        return StackRef {
+            value: core::ptr::slice_from_raw_parts_mut(values, len),
+            phantom: PhantomData
+        };
+    }
 
-                curr_block_tail.next_block = allocated_block;
+    /// Makes room for `count` contiguous Values at the top of the current frame,
+    /// returning a pointer to the first of them,
+    /// or a PushError if a new block was needed but couldn't be allocated,
+    /// or if `count` Values can't fit in the next block.
+    ///
+    /// If the Values don't fit in the current block, they're all placed in the next block.
+    /// The Values are part of the current frame once this returns,
+    /// so they have to be written before the frame is read or dropped.
+    fn try_bump(&self, count: usize) -> Result<*mut Value, PushError> {
+        let bytes = count * Self::SIZE_VALUE;
+
+        unsafe {
+            //everything the fast path needs is loaded once,
+            //and stored back once, since this runs on every push
+            let stack_frame = (*self.current_frame.get()).as_ptr();
+            let current_frame_ptr = (*stack_frame).current_frame_ptr;
+            let bytes_used = *self.buffer_bytes_used.get();
+            let block_size = *self.block_size.get();
+
+            let value_padding = current_frame_ptr
+                .align_offset(Self::ALIGN_VALUE);
+            let offset = value_padding + bytes;
+
+            if bytes_used + offset < block_size.bytes() - Self::SIZE_TAIL {
+                (*stack_frame).current_frame_ptr = current_frame_ptr.add(offset);
+                *self.buffer_bytes_used.get() = bytes_used + offset;
+                self.track_peak(current_frame_ptr.add(offset), bytes_used + offset);
+
+                return Ok(current_frame_ptr.add(value_padding).cast::<Value>());
             }
 
-            let next_block_addr_ptr = curr_block_tail.next_block;
-            //value_padding is not needed, 
-            //because the block should already be aligned to Key, 
+            let (next_block_addr_ptr, next_block_size) = self.try_link_next_block(
+                self.get_block_tail(),
+                current_frame_ptr,
+                bytes_used,
+                block_size
+            )?;
+
+            //value_padding is not needed,
+            //because blocks are aligned to Value,
             //but its added for consistency
             let value_padding = next_block_addr_ptr
                 .align_offset(Self::ALIGN_VALUE);
-            let value_ptr = next_block_addr_ptr.add(value_padding);
+            let block_offset = value_padding + bytes;
 
-            let block_offset = value_padding + Self::SIZE_VALUE;
+            //the top of a block never reaches its tail
+            let block_capacity = next_block_size.bytes() - Self::SIZE_TAIL - 1;
+            if block_offset > block_capacity {
+                return Err(PushError::TooLarge { required: block_offset, block_capacity });
+            }
 
+            *self.block_size.get() = next_block_size;
             *self.buffer_bytes_used.get() = block_offset;
+            (*stack_frame).current_frame_ptr = next_block_addr_ptr.add(block_offset);
+            self.track_peak(next_block_addr_ptr.add(block_offset), block_offset);
+
+            return Ok(next_block_addr_ptr.add(value_padding).cast::<Value>());
+        }
+    }
+
+    /// Pushes a Value into the current frame,
+    /// returning a mutable reference to the Value.
+    ///
+    /// This is the same as calling
+    /// [get_mut](crate::stack_ref::safe_ref::StackRef::get_mut) on the StackRef returned by
+    /// [push](crate::stack_frame_allocator::StackFrameAllocator::push),
+    /// for when the borrow doesn't need to be deferred.
+    ///
+    /// # Examples
+    ///
+    /// ```edition2020
+    /// # use stack_frame_allocators::stack_frame_allocator::StackFrameAllocator;
+    ///
+    /// struct Node<'a> {
+    ///     value: usize,
+    ///     children: Vec<&'a Node<'a>>
+    /// }
+    ///
+    /// let stack = StackFrameAllocator::<Node>::new();
+    ///
+    /// let leaf = stack.alloc(Node { value: 1, children: vec![] });
+    /// leaf.value += 1;
+    ///
+    /// let root = stack.alloc(Node { value: 0, children: vec![leaf] });
+    /// assert_eq!(root.children[0].value, 2);
+    /// ```
+    pub fn alloc(&self, value: Value) -> &mut Value {
+        self.push(value).get_mut()
+    }
 
-            // eprintln!("writing key of size {} at {:?} with {}",
-            //     Self::SIZE_KEY, key_ptr, &key
-            // );
-            // eprintln!("writing key of size {} at {:?} with {}",
-            //     Self::SIZE_VALUE, value_ptr, &value
-            // );
+    /// Swaps the Values two StackRefs point to,
+    /// even if the Values live in different blocks or frames.
+    ///
+    /// Only one StackRef can exist at a time for a given Value,
+    /// so taking both StackRefs mutably guarantees they don't alias.
+    ///
+    /// # Examples
+    ///
+    /// ```edition2020
+    /// # use stack_frame_allocators::stack_frame_allocator::StackFrameAllocator;
+    ///
+    /// let stack = StackFrameAllocator::<usize>::new();
+    /// let mut a = stack.push(1);
+    /// let mut b = stack.push(2);
+    ///
+    /// stack.swap(&mut a, &mut b);
+    /// assert_eq!((*a, *b), (2, 1));
+    /// ```
+    pub fn swap(&self, a: &mut StackRef<'_, 's, Value>, b: &mut StackRef<'_, 's, Value>) {
+        unsafe {core::ptr::swap(a.value, b.value)}
+    }
 
-            (value_ptr as *mut Value).write(value);
-            (*(*self.current_frame.get()).as_ptr()).current_frame_ptr =
-                next_block_addr_ptr.add(block_offset);
+    /// Returns the remaining spare capacity of the current block
+    /// as a slice of `MaybeUninit<Value>`.
+    ///
+    /// The returned slice starts at the next correctly aligned slot
+    /// after the top of the current frame and ends where the block's
+    /// tail begins.  Only the current block is considered,
+    /// so this never allocates or chains into a new block.
+    /// After writing values into the slice, they must be committed to
+    /// the current frame with
+    /// [set_frame_len_added](crate::stack_frame_allocator::StackFrameAllocator::set_frame_len_added),
+    /// otherwise they are not considered part of the frame and will not be dropped.
+    ///
+    /// # Examples
+    ///
+    /// ```edition2020
+    /// # use stack_frame_allocators::stack_frame_allocator::StackFrameAllocator;
+    ///
+    /// let mut stack = StackFrameAllocator::<usize>::new();
+    ///
+    /// let spare = stack.spare_capacity_mut();
+    /// spare[0].write(0);
+    /// spare[1].write(1);
+    /// spare[2].write(2);
+    ///
+    /// unsafe {
+    ///     stack.set_frame_len_added(3);
+    /// }
+    /// ```
+    pub fn spare_capacity_mut(&mut self) -> &mut [MaybeUninit<Value>] {
+        unsafe {
+            let current_frame_ptr = (*(*self.current_frame.get()).as_ptr())
+                .current_frame_ptr;
+            let value_padding = current_frame_ptr
+                .align_offset(Self::ALIGN_VALUE);
+            let bytes_used = *self.buffer_bytes_used.get() + value_padding;
 
-            return StackRef {
-                value: value_ptr as *mut Value,
-                phantom: PhantomData::default()
+            let slots = match Self::SIZE_VALUE {
+                0 => 0,
+                size => self.real_size().bytes().saturating_sub(bytes_used) / size
             };
-        }}
+
+            core::slice::from_raw_parts_mut(
+                current_frame_ptr.add(value_padding).cast::<MaybeUninit<Value>>(),
+                slots
+            )
+        }
+    }
+
+    /// Commits the first `added` slots of the
+    /// [spare capacity](crate::stack_frame_allocator::StackFrameAllocator::spare_capacity_mut)
+    /// to the current frame.
+    ///
+    /// The committed values become part of the current frame,
+    /// as if they had been pushed one by one,
+    /// and will be dropped when the frame is popped.
+    ///
+    /// # Safety
+    ///
+    /// `added` must be less than or equal to the length of the slice returned by
+    /// [spare_capacity_mut](crate::stack_frame_allocator::StackFrameAllocator::spare_capacity_mut),
+    /// and the first `added` slots of that slice must have been initialized.
+    ///
+    /// # Examples
+    ///
+    /// ```edition2020
+    /// # use stack_frame_allocators::stack_frame_allocator::StackFrameAllocator;
+    ///
+    /// let mut stack = StackFrameAllocator::<usize>::new();
+    ///
+    /// for (i, slot) in stack.spare_capacity_mut().iter_mut().take(10).enumerate() {
+    ///     slot.write(i);
+    /// }
+    ///
+    /// unsafe {
+    ///     stack.set_frame_len_added(10);
+    /// }
+    /// ```
+    pub unsafe fn set_frame_len_added(&mut self, added: usize) {
+        if added == 0 {
+            return;
+        }
+
+        let current_frame_ptr = (*(*self.current_frame.get()).as_ptr())
+            .current_frame_ptr;
+        let value_padding = current_frame_ptr
+            .align_offset(Self::ALIGN_VALUE);
+        let offset = value_padding + added * Self::SIZE_VALUE;
+
+        debug_assert!(
+            *self.buffer_bytes_used.get() + offset <= self.real_size().bytes(),
+            "committed more values than there is spare capacity in the current block"
+        );
+
+        (*(*self.current_frame.get()).as_ptr()).current_frame_ptr =
+            current_frame_ptr.add(offset);
+        *self.buffer_bytes_used.get() += offset;
+    }
+
+    /// Grabs a reference to the most recently pushed Value in the current frame,
+    /// returning None if the current frame is empty.
+    ///
+    /// Values pushed onto previous frames are never returned,
+    /// even if the current frame is empty.
+    /// Because StackRefs returned by
+    /// [push](crate::stack_frame_allocator::StackFrameAllocator::push)
+    /// can be turned into mutable references, peeking requires
+    /// a mutable borrow of the allocator, so the borrow checker can still
+    /// guarantee there's no outstanding mutable reference to the top value.
+    ///
+    /// # Examples
+    ///
+    /// ```edition2020
+    /// # use stack_frame_allocators::stack_frame_allocator::StackFrameAllocator;
+    ///
+    /// let mut stack = StackFrameAllocator::<usize>::new();
+    /// assert_eq!(stack.peek(), None);
+    ///
+    /// stack.push(1);
+    /// stack.push(2);
+    /// assert_eq!(stack.peek(), Some(&2));
+    ///
+    /// stack.new_scope(|mut stack| {
+    ///     assert_eq!(stack.peek(), None);
+    ///
+    ///     stack.push(3);
+    ///     assert_eq!(stack.peek(), Some(&3));
+    /// });
+    /// ```
+    pub fn peek(&mut self) -> Option<&Value> {
+        //the walker skips the padding between the header and the first value,
+        //which empty slices can move the top of the frame past
+        let mut walker = unsafe {self.walker()};
+        walker.next_in_frame().map(|value_ptr| unsafe {&*value_ptr.cast::<Value>()})
+    }
+
+    /// Grabs a StackRef to the `n`th most recently pushed Value in the current frame,
+    /// where 0 is the top of the frame.
+    ///
+    /// Returns None if the current frame holds `n` Values or less.
+    ///
+    /// This takes a mutable reference, so the StackRef can't alias
+    /// a StackRef returned by [push](crate::stack_frame_allocator::StackFrameAllocator::push),
+    /// since StackRefs hand out mutable references.
+    ///
+    /// # Examples
+    ///
+    /// ```edition2020
+    /// # use stack_frame_allocators::stack_frame_allocator::StackFrameAllocator;
+    ///
+    /// let mut stack = StackFrameAllocator::<usize>::new();
+    /// stack.push(1);
+    /// stack.push(2);
+    ///
+    /// *stack.nth_from_top(1).unwrap().get_mut() += 10;
+    /// assert_eq!(stack.nth_from_top(1).map(|value| *value.get()), Some(11));
+    /// assert!(stack.nth_from_top(2).is_none());
+    /// ```
+    pub fn nth_from_top(&mut self, n: usize) -> Option<StackRef<'_, 's, Value>> {
+        let mut walker = unsafe {self.walker()};
+        let value_ptr = core::iter::from_fn(|| walker.next_in_frame()).nth(n)?;
+
+        return Some(StackRef {
+            value: value_ptr.cast::<Value>(),
+            phantom: PhantomData
+        });
+    }
+
+    /// Grabs mutable references to two distinct Values in the current frame,
+    /// where `a` and `b` are indices from the first push (bottom) of the frame.
+    ///
+    /// Returns None if `a` and `b` are the same index,
+    /// or if either index is out of the current frame's range.
+    ///
+    /// # Examples
+    ///
+    /// ```edition2020
+    /// # use stack_frame_allocators::stack_frame_allocator::StackFrameAllocator;
+    ///
+    /// let mut stack = StackFrameAllocator::<usize>::new();
+    /// stack.push(1);
+    /// stack.push(2);
+    ///
+    /// let (a, b) = stack.get_two_mut(0, 1).unwrap();
+    /// core::mem::swap(a, b);
+    /// assert_eq!(stack.peek(), Some(&1));
+    ///
+    /// assert!(stack.get_two_mut(1, 1).is_none());
+    /// assert!(stack.get_two_mut(0, 2).is_none());
+    /// ```
+    pub fn get_two_mut(&mut self, a: usize, b: usize) -> Option<(&mut Value, &mut Value)> {
+        if a == b {
+            return None;
+        }
+
+        let mut walker = unsafe {self.walker()};
+        let frame: Vec<*mut u8> = core::iter::from_fn(|| walker.next_in_frame()).collect();
+
+        let len = frame.len();
+        if a >= len || b >= len {
+            return None;
+        }
+
+        //the walker goes from top to bottom,
+        //and a != b, so the two values never alias
+        unsafe {
+            Some((
+                &mut *frame[len - 1 - a].cast::<Value>(),
+                &mut *frame[len - 1 - b].cast::<Value>()
+            ))
+        }
+    }
+
+    /// Clones every Value in the current frame into a Vec,
+    /// from the last push (top) to the first push (bottom).
+    ///
+    /// The Vec doesn't borrow the StackFrameAllocator,
+    /// so it can be handed out after the frame is popped.
+    ///
+    /// Like [peek](crate::stack_frame_allocator::StackFrameAllocator::peek),
+    /// this takes a mutable reference, so no mutable references
+    /// from StackRefs can be alive while the Values are cloned.
+    ///
+    /// # Examples
+    ///
+    /// ```edition2020
+    /// # use stack_frame_allocators::stack_frame_allocator::StackFrameAllocator;
+    ///
+    /// let stack = StackFrameAllocator::<String>::new();
+    ///
+    /// let results = stack.new_scope(|mut stack| {
+    ///     for word in ["a", "b", "c"] {
+    ///         stack.push(word.repeat(2));
+    ///     }
+    ///     stack.to_vec()
+    /// });
+    ///
+    /// assert_eq!(results, vec!["cc", "bb", "aa"]);
+    /// ```
+    pub fn to_vec(&mut self) -> Vec<Value> where Value: Clone {
+        let mut walker = unsafe {self.walker()};
+
+        core::iter::from_fn(|| walker.next_in_frame())
+            .map(|value_ptr| unsafe {&*value_ptr.cast::<Value>()}.clone())
+            .collect()
+    }
+
+    /// Iterates over every Value in the current frame,
+    /// from the first push (bottom) to the last push (top).
+    ///
+    /// Frames can only be walked from the top down,
+    /// so the position of every Value in the frame is collected into a Vec first.
+    ///
+    /// Like [peek](crate::stack_frame_allocator::StackFrameAllocator::peek),
+    /// this takes a mutable reference, so no mutable references
+    /// from StackRefs can be alive while iterating.
+    ///
+    /// # Examples
+    ///
+    /// ```edition2020
+    /// # use stack_frame_allocators::stack_frame_allocator::StackFrameAllocator;
+    ///
+    /// let mut stack = StackFrameAllocator::<usize>::new();
+    /// stack.push(1);
+    /// stack.push(2);
+    /// stack.push(3);
+    ///
+    /// let values: Vec<_> = stack.iter_frame_rev().collect();
+    /// assert_eq!(values, vec![&1, &2, &3]);
+    /// ```
+    pub fn iter_frame_rev(&mut self) -> impl Iterator<Item = &Value> {
+        let mut walker = unsafe {self.walker()};
+        let positions: Vec<*mut u8> = core::iter::from_fn(|| walker.next_in_frame()).collect();
+
+        positions.into_iter().rev().map(|value_ptr| unsafe {&*value_ptr.cast::<Value>()})
+    }
+
+    /// Whether any Value in the current frame is equal to `value`.
+    ///
+    /// Values pushed onto previous frames aren't searched.
+    /// Like [peek](crate::stack_frame_allocator::StackFrameAllocator::peek),
+    /// this takes a mutable reference, so no mutable references
+    /// from StackRefs can be alive while comparing.
+    ///
+    /// # Examples
+    ///
+    /// ```edition2020
+    /// # use stack_frame_allocators::stack_frame_allocator::StackFrameAllocator;
+    ///
+    /// let mut stack = StackFrameAllocator::<usize>::new();
+    /// stack.push(1);
+    /// stack.push(2);
+    /// stack.push(3);
+    ///
+    /// assert!(stack.contains_in_frame(&2));
+    /// assert!(!stack.contains_in_frame(&4));
+    /// ```
+    pub fn contains_in_frame(&mut self, value: &Value) -> bool where Value: PartialEq {
+        let mut walker = unsafe {self.walker()};
+
+        while let Some(value_ptr) = walker.next_in_frame() {
+            if unsafe {&*value_ptr.cast::<Value>()} == value {
+                return true;
+            }
+        }
+
+        return false;
+    }
+
+    /// Views every Value in the current frame as a slice,
+    /// from the first push (bottom) to the last push (top),
+    /// so they can be processed in bulk without copying them.
+    ///
+    /// Values never span across blocks, but a frame can.  The Values of a frame
+    /// are only contiguous while the frame fits in the block holding its header,
+    /// so this returns None once the frame has spilled over into another block.
+    /// Zero sized Values take up no room in the frame, so the slice of them is always empty.
+    ///
+    /// # Safety
+    ///
+    /// No mutable references from StackRefs to the Values in the current frame
+    /// can be alive while the slice is alive.
+    ///
+    /// # Examples
+    ///
+    /// ```edition2020
+    /// # use stack_frame_allocators::stack_frame_allocator::StackFrameAllocator;
+    ///
+    /// let stack = StackFrameAllocator::<u64>::new();
+    /// for i in 1..=4 {
+    ///     stack.push(i);
+    /// }
+    ///
+    /// let values = unsafe {stack.as_slice()}.unwrap();
+    /// assert_eq!(values, [1, 2, 3, 4]);
+    /// assert_eq!(values.iter().sum::<u64>(), 10);
+    /// ```
+    pub unsafe fn as_slice(&self) -> Option<&[Value]> {
+        let stack_frame = (*self.current_frame.get()).as_ptr();
+        let current_frame_ptr = (*stack_frame).current_frame_ptr;
+        let block_start = current_frame_ptr.sub(*self.buffer_bytes_used.get());
+
+        let header_ptr = stack_frame.cast::<u8>();
+        if header_ptr < block_start || current_frame_ptr <= header_ptr {
+            return None;
+        }
+
+        if Self::SIZE_VALUE == 0 {
+            return Some(&[]);
+        }
+
+        let items_start = header_ptr.add(Self::SIZE_HEADER);
+        let items_start = items_start.add(items_start.align_offset(Self::ALIGN_VALUE));
+        let len = (current_frame_ptr as usize).saturating_sub(items_start as usize) / Self::SIZE_VALUE;
+
+        return Some(core::slice::from_raw_parts(items_start.cast::<Value>(), len));
+    }
+
+    unsafe fn walker(&self) -> StackWalker<'_> {
+        StackWalker::new(
+            (*self.current_frame.get()).as_ref(),
+            *self.buffer_bytes_used.get(),
+            self.real_size().bytes(),
+            Self::SIZE_VALUE,
+            Self::ALIGN_VALUE
+        )
     }
 
     /// prints out the current stack from last push (top) to first push (bottom)
@@ -578,264 +2121,2798 @@ impl<'s, Value> StackFrameAllocator<'s, Value> {
     ///     1
     /// header
     /// ```
+    #[cfg(feature = "std")]
     pub fn print(&self) where Value: Display {
-        let mut count_blocks = 1;
-
-        let mut curr_block_tail = unsafe {self.get_block_tail()};
-        let mut bytes_remaining = unsafe {*self.buffer_bytes_used.get()};
-
-        let mut stack_frame = unsafe {(*self.current_frame.get()).as_ref()};
-        let mut peek_ptr = stack_frame.current_frame_ptr;
-        
-        //for the first scope we're looking at, because it's the newest scope
-        //there should be no headers after the current frame,
-        //so we'll use key alignment
-        let mut just_jumped_block = false;
-        let mut expect_key_value_pair = true;
-        let mut stack_frame_ptr_after = {unsafe {
-            let offset_ptr = (stack_frame as *const StackFrameHeader as *mut u8)
-                .add(Self::SIZE_HEADER);
-            let padding = offset_ptr.align_offset(Self::ALIGN_VALUE);
-            offset_ptr.add(padding)
-        }};
-
-        println!("top of stack");
-
-        loop {unsafe {
-            if bytes_remaining == 0 {
-                if curr_block_tail.prev_block.is_null() {
-                    unreachable!("{}", concat!(
-                        "the previous block can only be null ",  
-                        "if the block currently being looked at is the first block.  ",  
-                        "In that case, the header logic would've ran first, ", 
-                        "thus this should never be reached"
-                    ))
-                }
-
-                count_blocks += 1;
-
-                bytes_remaining = curr_block_tail.prev_block_bytes_used;
-                peek_ptr = curr_block_tail.prev_block;
-
-                let offset = self.real_size().bytes() - bytes_remaining;
-        
-                curr_block_tail = peek_ptr
-                    .add(offset)
-                    .cast::<BlockTail>()
-                    .as_mut()
-                    .expect("Error grabbing mutable reference to BlockTail");
-
-                //we must check for the case, the first key value pair attached
-                //to this header was in the block we were just looking in
-                //in this case, there should be no padding
-                stack_frame_ptr_after = (
-                    stack_frame 
-                    as *const StackFrameHeader 
-                    as *mut u8
-                ).add(Self::SIZE_HEADER);
-
-                just_jumped_block = true;
-            }
-            
-            if peek_ptr < stack_frame_ptr_after {
-                unreachable!("unexpected operation caused peek_ptr to go past the stack_frame_ptr");
-            } else if peek_ptr == stack_frame_ptr_after {
-                println!("header");
-
-                let Some(new_frame) = stack_frame.previous_frame else {
-                    break;
-                };
-
-                stack_frame = new_frame;
-                peek_ptr = stack_frame.current_frame_ptr;
-
-                //this new header could have zero items
-                just_jumped_block = false;
-                expect_key_value_pair = false;
-                stack_frame_ptr_after = {
-                    let offset_ptr = (stack_frame as *const StackFrameHeader as *mut u8)
-                        .add(Self::SIZE_HEADER);
-                    let padding = offset_ptr.align_offset(Self::ALIGN_HEADER);
-                    offset_ptr.add(padding)
-                };
-
-                continue;
-            } else if !expect_key_value_pair || just_jumped_block {
-                just_jumped_block = false;
-                expect_key_value_pair = true;
-
-                stack_frame_ptr_after = {
-                    let offset_ptr = (stack_frame as *const StackFrameHeader as *mut u8)
-                        .add(Self::SIZE_HEADER);
-                    let padding = offset_ptr.align_offset(Self::ALIGN_VALUE);
-                    offset_ptr.add(padding)
-                };
-            }
-            
-            peek_ptr = peek_ptr.sub(Self::SIZE_VALUE);
-            let value = peek_ptr.cast::<Value>().as_ref().unwrap_unchecked();
-            println!("\t{}", value);
-        }}
-
-        println!("\n{} block(s) of size {} bytes have been allocated.\n", 
-            count_blocks, 
-            self.size.bytes()
-        );
+        self.print_to(&mut io::stdout().lock()).expect("failed printing to stdout");
     }
 
-    //TODO add allocated_blocks(&self) -> usize and using_blocks(&self) -> usize functions
-}
+    /// Writes out the current stack from last push (top) to first push (bottom)
+    /// to `w`, the same way
+    /// [print](crate::stack_frame_allocator::StackFrameAllocator::print) does.
+    ///
+    /// # Examples
+    ///
+    /// ```edition2020
+    /// # use stack_frame_allocators::stack_frame_allocator::StackFrameAllocator;
+    ///
+    /// let stack = StackFrameAllocator::<usize>::new();
+    /// stack.push(1);
+    /// stack.push(2);
+    ///
+    /// let mut out = Vec::new();
+    /// stack.print_to(&mut out).unwrap();
+    /// assert!(String::from_utf8(out).unwrap().starts_with("top of stack\n\t2\n\t1\nheader\n"));
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn print_to(&self, w: &mut impl Write) -> io::Result<()> where Value: Display {
+        writeln!(w, "{}\n", fmt::from_fn(|f| self.fmt_stack(f, false, |value, f| write!(f, "{}", value))))
+    }
 
-impl<'s, Value> Drop for StackFrameAllocator<'s, Value> {
-    fn drop(&mut self) {
-        //eprintln!("dropping stack frame");
-        unsafe {
-            let current_frame_ptr = (*self.current_frame.get()).as_ptr().cast::<u8>();
-            let mut bytes_remaining = *self.buffer_bytes_used.get();
-            let mut peek_ptr = (*current_frame_ptr.cast::<StackFrameHeader>()).current_frame_ptr;
-            let mut curr_block_tail = self.get_block_tail();
-    
-            //because we're only dropping the current scope,
-            //we can assume the padding after the header
-            //is key padding, because we shouldn't be expecting a header 
-            //after the header we're looking in
-            let stack_frame_ptr_after = {
-                let offset_ptr = current_frame_ptr.add(Self::SIZE_HEADER);
-                let padding = offset_ptr.align_offset(Self::ALIGN_VALUE);
-                offset_ptr.add(padding)
-            };
-    
-            //eprintln!("starting search at {:?} until {:?}", peek_ptr, stack_frame_ptr_after);
-            while peek_ptr > stack_frame_ptr_after {
-                // eprintln!("peeking at {:?} until {:?} with {} bytes remaining", 
-                //     peek_ptr, stack_frame_ptr_after, bytes_remaining
-                // );
-                if bytes_remaining == 0 {
-                    if curr_block_tail.prev_block.is_null() {
-                        unreachable!("{}", concat!(
-                            "the previous block can only be null ",  
-                            "if the block currently being looked at is the first block.  ",  
-                            "In that case, the header logic would've ran first, ", 
-                            "thus this should never be reached"
-                        ))
-                    }
-                    bytes_remaining = curr_block_tail.prev_block_bytes_used;
-                    peek_ptr = curr_block_tail.prev_block;
-    
-                    let offset = self.real_size().bytes() - bytes_remaining;
-            
-                    curr_block_tail = peek_ptr
-                        .add(offset)
-                        .cast::<BlockTail>()
-                        .as_mut()
-                        .expect("Error grabbing mutable reference to BlockTail");
-                }
-    
-                //dropping key and value pair
-                peek_ptr = peek_ptr.sub(Self::SIZE_VALUE);
-                bytes_remaining -= Self::SIZE_VALUE;
-                
-                std::ptr::drop_in_place(peek_ptr.cast::<Value>());
+    /// Prints out the current stack from last push (top) to first push (bottom),
+    /// the same way [print](crate::stack_frame_allocator::StackFrameAllocator::print) does,
+    /// but with every frame indented one level deeper than the frame before it.
+    ///
+    /// # Examples
+    ///
+    /// ```edition2020
+    /// # use stack_frame_allocators::stack_frame_allocator::StackFrameAllocator;
+    ///
+    /// let stack = StackFrameAllocator::<usize>::new();
+    /// stack.push(1);
+    /// stack.push(2);
+    ///
+    /// stack.new_scope(|stack| {
+    ///     stack.push(10);
+    ///
+    ///     stack.new_scope(|stack| {
+    ///         stack.push(100);
+    ///         stack.push(200);
+    ///         stack.print_indented();
+    ///     });
+    /// });
+    /// ```
+    ///
+    /// Will print out:
+    /// ```text
+    /// top of stack
+    ///             200
+    ///             100
+    ///         header
+    ///         10
+    ///     header
+    ///     2
+    ///     1
+    /// header
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn print_indented(&self) where Value: Display {
+        self.print_indented_to(&mut io::stdout().lock()).expect("failed printing to stdout");
+    }
+
+    /// Writes out the current stack from last push (top) to first push (bottom)
+    /// to `w`, the same way
+    /// [print_indented](crate::stack_frame_allocator::StackFrameAllocator::print_indented) does.
+    ///
+    /// # Examples
+    ///
+    /// ```edition2020
+    /// # use stack_frame_allocators::stack_frame_allocator::StackFrameAllocator;
+    ///
+    /// let stack = StackFrameAllocator::<usize>::new();
+    /// stack.push(1);
+    ///
+    /// stack.new_scope(|stack| {
+    ///     stack.push(10);
+    ///
+    ///     let mut out = Vec::new();
+    ///     stack.print_indented_to(&mut out).unwrap();
+    ///     assert!(String::from_utf8(out).unwrap().starts_with("top of stack\n\t\t10\n\theader\n\t1\nheader\n"));
+    /// });
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn print_indented_to(&self, w: &mut impl Write) -> io::Result<()> where Value: Display {
+        writeln!(w, "{}\n", fmt::from_fn(|f| self.fmt_stack(f, true, |value, f| write!(f, "{}", value))))
+    }
+
+    /// Writes the current stack from last push (top) to first push (bottom),
+    /// including where headers are, and how many blocks the stack spans.
+    ///
+    /// If `indent` is set, every frame is indented by its depth,
+    /// otherwise every frame is indented as if it were the root frame.
+    fn fmt_stack(
+        &self,
+        f: &mut Formatter<'_>,
+        indent: bool,
+        fmt_value: fn(&Value, &mut Formatter<'_>) -> fmt::Result
+    ) -> fmt::Result {
+        let mut walker = unsafe {self.walker()};
+        let mut depth = if indent { self.depth() } else { 1 };
+
+        writeln!(f, "top of stack")?;
+
+        loop {
+            while let Some(value_ptr) = walker.next_in_frame() {
+                let value = unsafe {&*value_ptr.cast::<Value>()};
+
+                write!(f, "{}", "\t".repeat(depth))?;
+                fmt_value(value, f)?;
+                writeln!(f)?;
+            }
+
+            writeln!(f, "{}header", "\t".repeat(depth - 1))?;
+
+            if !walker.next_frame() {
+                break;
+            }
+            if indent {
+                depth -= 1;
+            }
+        }
+
+        match self.growth {
+            GrowthPolicy::Fixed => write!(f, "\n{} block(s) of size {} bytes have been allocated.",
+                walker.blocks_visited(),
+                self.size.bytes()
+            ),
+            GrowthPolicy::Geometric { .. } => write!(f, "\n{} block(s) totalling {} bytes have been allocated.",
+                walker.blocks_visited(),
+                walker.block_bytes_visited()
+            )
+        }
+    }
+
+    /// Gets the amount of blocks the StackFrameAllocator has allocated,
+    /// including blocks kept around to be reused after their frames were popped.
+    ///
+    /// # Examples
+    ///
+    /// ```edition2020
+    /// # use stack_frame_allocators::stack_frame_allocator::StackFrameAllocator;
+    ///
+    /// let stack = StackFrameAllocator::<u64>::new();
+    /// assert_eq!(stack.allocated_blocks(), 1);
+    ///
+    /// stack.new_scope(|stack| {
+    ///     for i in 0..200 {
+    ///         stack.push(i);
+    ///     }
+    /// });
+    ///
+    /// assert_eq!(stack.allocated_blocks(), 2);
+    /// assert_eq!(stack.using_blocks(), 1);
+    /// ```
+    pub fn allocated_blocks(&self) -> usize {
+        self.blocks().len()
+    }
+
+    /// Gets the amount of blocks that currently hold frames or values.
+    ///
+    /// # Examples
+    ///
+    /// ```edition2020
+    /// # use stack_frame_allocators::stack_frame_allocator::StackFrameAllocator;
+    ///
+    /// let stack = StackFrameAllocator::<u64>::new();
+    /// for i in 0..200 {
+    ///     stack.push(i);
+    /// }
+    ///
+    /// assert_eq!(stack.using_blocks(), 2);
+    /// ```
+    pub fn using_blocks(&self) -> usize {
+        self.blocks().iter().filter(|(_, bytes_used, _)| *bytes_used != 0).count()
+    }
+
+    /// Breaks down what every byte of every allocated block is being used for.
+    ///
+    /// # Examples
+    ///
+    /// ```edition2020
+    /// # use stack_frame_allocators::stack_frame_allocator::StackFrameAllocator;
+    ///
+    /// let stack = StackFrameAllocator::<u64>::new();
+    /// stack.push(1);
+    /// stack.push(2);
+    ///
+    /// let usage = stack.mem_usage();
+    /// assert_eq!(usage.value_bytes, 16);
+    /// assert_eq!(usage.total_bytes(), 1024);
+    /// ```
+    pub fn mem_usage(&self) -> MemUsage {
+        let blocks = self.blocks();
+
+        let mut walker = unsafe {self.walker()};
+        let mut values = 0;
+        let mut headers = 1;
+        loop {
+            while walker.next_in_frame().is_some() {
+                values += 1;
+            }
+
+            if !walker.next_frame() {
+                break;
+            }
+            headers += 1;
+        }
+
+        let usable_bytes: usize = blocks.iter().map(|(_, _, size)| size.bytes() - Self::SIZE_TAIL).sum();
+        let bytes_used: usize = blocks.iter().map(|(_, bytes_used, _)| bytes_used).sum();
+
+        let value_bytes = values * Self::SIZE_VALUE;
+        let header_bytes = headers * Self::SIZE_HEADER;
+
+        MemUsage {
+            value_bytes,
+            header_bytes,
+            tail_bytes: blocks.len() * Self::SIZE_TAIL,
+            padding_bytes: bytes_used - value_bytes - header_bytes,
+            slack_bytes: usable_bytes - bytes_used
+        }
+    }
+
+    /// Gathers statistics about the blocks and frames of the stack,
+    /// walking every block and every value in the current frame once.
+    ///
+    /// This is cheaper than calling
+    /// [allocated_blocks](crate::stack_frame_allocator::StackFrameAllocator::allocated_blocks),
+    /// [using_blocks](crate::stack_frame_allocator::StackFrameAllocator::using_blocks),
+    /// and friends separately, since each of them walks the stack on its own.
+    ///
+    /// # Examples
+    ///
+    /// ```edition2020
+    /// # use stack_frame_allocators::stack_frame_allocator::StackFrameAllocator;
+    ///
+    /// let stack = StackFrameAllocator::<u64>::new();
+    /// stack.push(1);
+    ///
+    /// stack.new_scope(|stack| {
+    ///     for i in 0..200 {
+    ///         stack.push(i);
+    ///     }
+    ///
+    ///     let stats = stack.stats();
+    ///     assert_eq!(stats.allocated_blocks, 2);
+    ///     assert_eq!(stats.using_blocks, 2);
+    ///     assert_eq!(stats.frame_depth, 2);
+    ///     assert_eq!(stats.items_in_frame, 200);
+    /// });
+    /// ```
+    pub fn stats(&self) -> FrameStats {
+        let blocks = self.blocks();
+
+        let mut walker = unsafe {self.walker()};
+        let mut items_in_frame = 0;
+        while walker.next_in_frame().is_some() {
+            items_in_frame += 1;
+        }
+
+        FrameStats {
+            allocated_blocks: blocks.len(),
+            using_blocks: blocks.iter().filter(|(_, bytes_used, _)| *bytes_used != 0).count(),
+            bytes_used: blocks.iter().map(|(_, bytes_used, _)| bytes_used).sum(),
+            bytes_capacity: blocks.iter().map(|(_, _, size)| size.bytes() - Self::SIZE_TAIL).sum(),
+            frame_depth: self.depth(),
+            items_in_frame
+        }
+    }
+
+    /// Gets the most bytes the stack has used at once, across every block,
+    /// since the allocator was created or the peak was last reset.
+    ///
+    /// Popping frames lowers how many bytes are used, but not the peak,
+    /// so short spikes in usage are still seen.
+    /// The peak of a scope made with
+    /// [new_scope](crate::stack_frame_allocator::StackFrameAllocator::new_scope)
+    /// or a frame made with [new_frame](crate::stack_frame_allocator::StackFrameAllocator::new_frame)
+    /// is handed back to the allocator it was made from once the scope ends or the frame is dropped.
+    ///
+    /// # Examples
+    ///
+    /// ```edition2020
+    /// # use stack_frame_allocators::stack_frame_allocator::StackFrameAllocator;
+    ///
+    /// let stack = StackFrameAllocator::<u64>::new();
+    ///
+    /// let burst = stack.new_scope(|stack| {
+    ///     for i in 0..1000 {
+    ///         stack.push(i);
+    ///     }
+    ///     stack.stats().bytes_used
+    /// });
+    ///
+    /// assert!(stack.stats().bytes_used < burst);
+    /// assert_eq!(stack.peak_bytes(), burst);
+    /// ```
+    pub fn peak_bytes(&self) -> usize {
+        return unsafe {*self.peak_bytes.get()};
+    }
+
+    /// Lowers the peak to the bytes the stack currently uses.
+    ///
+    /// # Examples
+    ///
+    /// ```edition2020
+    /// # use stack_frame_allocators::stack_frame_allocator::StackFrameAllocator;
+    ///
+    /// let stack = StackFrameAllocator::<u64>::new();
+    /// stack.new_scope(|stack| {
+    ///     stack.push(1);
+    /// });
+    ///
+    /// stack.reset_peak();
+    /// assert_eq!(stack.peak_bytes(), stack.stats().bytes_used);
+    /// ```
+    pub fn reset_peak(&self) {
+        unsafe {
+            *self.peak_bytes.get() = 0;
+            let top = (*self.current_frame.get()).as_ref().current_frame_ptr;
+            self.track_peak(top, *self.buffer_bytes_used.get());
+        }
+    }
+}
+
+impl<'s, Value, A: Allocator> StackFrameAllocator<'s, Value, A, Root> {
+    /// Deallocates every block past the block the top of the root frame is in.
+    ///
+    /// Blocks are normally kept around after their frames are popped,
+    /// so they can be reused by later pushes without allocating again.
+    /// Only the root allocator can shrink, and only while no frame or StackRef borrows it,
+    /// so no frame is left pointing into a deallocated block.
+    ///
+    /// # Examples
+    ///
+    /// ```edition2020
+    /// # use stack_frame_allocators::stack_frame_allocator::StackFrameAllocator;
+    ///
+    /// let mut stack = StackFrameAllocator::<u64>::new();
+    /// stack.new_scope(|stack| {
+    ///     for i in 0..200 {
+    ///         stack.push(i);
+    ///     }
+    /// });
+    ///
+    /// assert_eq!(stack.allocated_blocks(), 2);
+    /// stack.shrink_to_fit();
+    /// assert_eq!(stack.allocated_blocks(), 1);
+    /// ```
+    ///
+    /// ```compile_fail
+    /// # use stack_frame_allocators::stack_frame_allocator::StackFrameAllocator;
+    ///
+    /// let mut stack = StackFrameAllocator::<u64>::new();
+    /// let frame = stack.new_frame();
+    /// for i in 0..200 {
+    ///     frame.push(i);
+    /// }
+    ///
+    /// //the frame still lives in the second block
+    /// stack.shrink_to_fit();
+    /// frame.push(200);
+    /// ```
+    pub fn shrink_to_fit(&mut self) {
+        unsafe {
+            let block_tail = self.get_block_tail();
+            let mut next_block = block_tail.next_block;
+            let mut next_block_size = StackSize(block_tail.next_block_size);
+            block_tail.next_block = core::ptr::null_mut();
+            block_tail.next_block_size = 0;
+
+            while !next_block.is_null() {
+                let (block, size) = (next_block, next_block_size);
+                let block_tail = Self::block_tail_at(block, size);
+                next_block = block_tail.next_block;
+                next_block_size = StackSize(block_tail.next_block_size);
+
+                self.deallocate_block(block, size);
+            }
+        }
+    }
+}
+
+impl<'s, Value, A: Allocator, Kind> StackFrameAllocator<'s, Value, A, Kind> {
+    /// Allocates enough blocks up front so at least `additional_bytes`
+    /// can be pushed onto the current frame without allocating.
+    ///
+    /// Blocks left behind by popped frames count towards the reserved bytes.
+    /// Values never span across blocks, so the bytes at the end of a block
+    /// too small to fit another value go unused.
+    ///
+    /// # Examples
+    ///
+    /// ```edition2020
+    /// # use stack_frame_allocators::stack_frame_allocator::StackFrameAllocator;
+    ///
+    /// let stack = StackFrameAllocator::<u64>::new();
+    /// stack.reserve(8 * 500);
+    ///
+    /// let allocated_blocks = stack.allocated_blocks();
+    /// for i in 0..500 {
+    ///     stack.push(i);
+    /// }
+    /// assert_eq!(stack.allocated_blocks(), allocated_blocks);
+    /// ```
+    pub fn reserve(&self, additional_bytes: usize) {
+        unsafe {
+            let mut last_block_tail = self.get_block_tail();
+            let mut last_block_top = (*self.current_frame.get()).as_ref().current_frame_ptr;
+            let mut last_block_bytes_used = *self.buffer_bytes_used.get();
+            let mut last_block_size = *self.block_size.get();
+            let mut bytes_reserved = self.real_size().bytes() - last_block_bytes_used;
+
+            loop {
+                if bytes_reserved >= additional_bytes {
+                    return;
+                }
+
+                let (next_block, next_block_size) = self.link_next_block(
+                    last_block_tail,
+                    last_block_top,
+                    last_block_bytes_used,
+                    last_block_size
+                );
+
+                //blocks after the current block are empty,
+                //pushing onto them rewrites their tails anyways
+                last_block_top = next_block;
+                last_block_bytes_used = 0;
+                last_block_size = next_block_size;
+                last_block_tail = Self::block_tail_at(next_block, next_block_size);
+                bytes_reserved += next_block_size.bytes() - Self::SIZE_TAIL;
+            }
+        }
+    }
+
+    /// Gets the start of every allocated block, from the first block to the last block,
+    /// alongside how many bytes of the block are being used, and the size of the block.
+    fn blocks(&self) -> Vec<(*mut u8, usize, StackSize)> {
+        let mut blocks = Vec::new();
+
+        unsafe {
+            let current_block_bytes_used = *self.buffer_bytes_used.get();
+            let current_block_size = *self.block_size.get();
+            let current_block = (*self.current_frame.get())
+                .as_ref()
+                .current_frame_ptr
+                .sub(current_block_bytes_used);
+
+            //the tail of every block in use points to the top of the block before it
+            let mut block = current_block;
+            let mut bytes_used = current_block_bytes_used;
+            let mut size = current_block_size;
+            loop {
+                blocks.push((block, bytes_used, size));
+
+                let block_tail = Self::block_tail_at(block, size);
+                if block_tail.prev_block.is_null() {
+                    break;
+                }
+
+                bytes_used = block_tail.prev_block_bytes_used;
+                size = StackSize(block_tail.prev_block_size);
+                block = block_tail.prev_block.sub(bytes_used);
+            }
+            blocks.reverse();
+
+            //blocks after the current block are kept around to be reused
+            let block_tail = Self::block_tail_at(current_block, current_block_size);
+            let mut next_block = block_tail.next_block;
+            let mut next_block_size = StackSize(block_tail.next_block_size);
+            while !next_block.is_null() {
+                blocks.push((next_block, 0, next_block_size));
+
+                let block_tail = Self::block_tail_at(next_block, next_block_size);
+                next_block = block_tail.next_block;
+                next_block_size = StackSize(block_tail.next_block_size);
+            }
+        }
+
+        return blocks;
+    }
+
+    /// Gets the block after the block `top` lives in, alongside its size,
+    /// allocating it according to the growth policy if it doesn't exist yet.
+    ///
+    /// The tail of the next block is pointed back to `top`.
+    unsafe fn try_link_next_block(
+        &self,
+        block_tail: &mut BlockTail,
+        top: *mut u8,
+        bytes_used: usize,
+        block_size: StackSize
+    ) -> Result<(*mut u8, StackSize), AllocError> {
+        //if there is no next block, create one
+        if block_tail.next_block.is_null() {
+            let next_block_size = self.growth.next_block_size(block_size);
+            let allocated_block = self.try_allocate_block(next_block_size)?;
+            if let Some(on_block_alloc) = &self.on_block_alloc {
+                on_block_alloc(next_block_size.bytes());
+            }
+
+            (Self::block_tail_at(allocated_block, next_block_size) as *mut BlockTail).write(BlockTail {
+                prev_block: top,
+                prev_block_bytes_used: bytes_used,
+                prev_block_size: block_size.bytes(),
+                next_block: core::ptr::null_mut(),
+                next_block_size: 0
+            });
+
+            block_tail.next_block = allocated_block;
+            block_tail.next_block_size = next_block_size.bytes();
+        } else {
+            //the next block was left behind by a frame that has since been popped,
+            //so its tail still points back into that frame
+            let next_block_tail = Self::block_tail_at(
+                block_tail.next_block,
+                StackSize(block_tail.next_block_size)
+            );
+            next_block_tail.prev_block = top;
+            next_block_tail.prev_block_bytes_used = bytes_used;
+        }
+
+        return Ok((block_tail.next_block, StackSize(block_tail.next_block_size)));
+    }
+
+    unsafe fn link_next_block(
+        &self,
+        block_tail: &mut BlockTail,
+        top: *mut u8,
+        bytes_used: usize,
+        block_size: StackSize
+    ) -> (*mut u8, StackSize) {
+        self.try_link_next_block(block_tail, top, bytes_used, block_size)
+            .unwrap_or_else(|_| handle_alloc_error(
+                Self::block_layout(self.growth.next_block_size(block_size))
+            ))
+    }
+
+    /// Panics with `error`, or reports it to the global alloc error handler
+    /// if a new block couldn't be allocated.
+    fn push_failed(&self, error: PushError) -> ! {
+        match error {
+            PushError::Alloc(_) => handle_alloc_error(self.next_block_layout()),
+            PushError::TooLarge { .. } => panic!("{}", error)
+        }
+    }
+
+    fn next_block_layout(&self) -> Layout {
+        Self::block_layout(self.growth.next_block_size(unsafe {*self.block_size.get()}))
+    }
+
+    fn block_layout(size: StackSize) -> Layout {
+        Layout::from_size_align(size.bytes(), Self::ALIGN_BLOCK)
+            .expect("block size should never exceed isize::MAX")
+    }
+
+    fn try_allocate_block(&self, size: StackSize) -> Result<*mut u8, AllocError> {
+        self.allocator
+            .allocate(Self::block_layout(size))
+            .map(|block| block.cast::<u8>().as_ptr())
+            .map_err(|_| AllocError)
+    }
+
+    unsafe fn deallocate_block(&self, block: *mut u8, size: StackSize) {
+        self.allocator.deallocate(NonNull::new_unchecked(block), Self::block_layout(size));
+    }
+
+    unsafe fn block_tail_at<'b>(block: *mut u8, size: StackSize) -> &'b mut BlockTail {
+        block.add(size.bytes() - Self::SIZE_TAIL)
+            .cast::<BlockTail>()
+            .as_mut()
+            .expect("Error grabbing mutable reference to BlockTail")
+    }
+}
+
+impl<'s, A: Allocator, Kind> StackFrameAllocator<'s, u8, A, Kind> {
+    /// Copies a string into the current frame,
+    /// returning a reference to the copied string.
+    ///
+    /// The string's bytes are pushed the same way
+    /// [push_slice](crate::stack_frame_allocator::StackFrameAllocator::push_slice) pushes them,
+    /// so if they don't fit in the current block, they're all placed in the next block.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the string is too large to fit in a single block.
+    ///
+    /// # Examples
+    ///
+    /// ```edition2020
+    /// # use stack_frame_allocators::stack_frame_allocator::StackFrameAllocator;
+    ///
+    /// let stack = StackFrameAllocator::<u8>::new();
+    ///
+    /// let hello = stack.push_str("hello");
+    /// let world = stack.push_str(&String::from("world"));
+    /// assert_eq!(format!("{} {}", hello, world), "hello world");
+    /// ```
+    pub fn push_str(&self, s: &str) -> &str {
+        let bytes = self.push_slice(s.as_bytes()).get();
+
+        //the bytes were copied from a str, so they're still valid UTF-8
+        unsafe {core::str::from_utf8_unchecked(bytes)}
+    }
+}
+
+/// A breakdown of what every byte of every block allocated by a
+/// [StackFrameAllocator](crate::stack_frame_allocator::StackFrameAllocator)
+/// is being used for, returned by
+/// [mem_usage](crate::stack_frame_allocator::StackFrameAllocator::mem_usage).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MemUsage {
+    /// Bytes holding values.
+    pub value_bytes: usize,
+    /// Bytes holding frame headers.
+    pub header_bytes: usize,
+    /// Bytes holding the tails at the end of every block.
+    pub tail_bytes: usize,
+    /// Bytes skipped over to keep values and headers aligned.
+    pub padding_bytes: usize,
+    /// Bytes at the end of blocks that haven't been pushed onto yet.
+    pub slack_bytes: usize
+}
+
+impl MemUsage {
+    /// Gets the total amount of bytes accounted for,
+    /// which is every byte of every allocated block.
+    pub fn total_bytes(&self) -> usize {
+        self.value_bytes
+            + self.header_bytes
+            + self.tail_bytes
+            + self.padding_bytes
+            + self.slack_bytes
+    }
+}
+
+/// Statistics about the blocks and frames of a
+/// [StackFrameAllocator](crate::stack_frame_allocator::StackFrameAllocator),
+/// returned by [stats](crate::stack_frame_allocator::StackFrameAllocator::stats).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameStats {
+    /// The amount of allocated blocks,
+    /// including blocks kept around to be reused after their frames were popped.
+    pub allocated_blocks: usize,
+    /// The amount of blocks that currently hold frames or values.
+    pub using_blocks: usize,
+    /// Bytes used by frame headers, values, and padding, across every block.
+    pub bytes_used: usize,
+    /// Bytes that can be used across every allocated block,
+    /// which excludes the tails at the end of every block.
+    pub bytes_capacity: usize,
+    /// The amount of frames on the stack, where the root frame has a depth of 1.
+    pub frame_depth: usize,
+    /// The amount of values in the current frame.
+    pub items_in_frame: usize
+}
+
+/// A mark of the top of a frame made by
+/// [checkpoint](crate::stack_frame_allocator::StackFrameAllocator::checkpoint),
+/// which the frame can be rewound to with
+/// [restore](crate::stack_frame_allocator::StackFrameAllocator::restore).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Checkpoint {
+    stack_frame: *mut u8,
+    current_frame_ptr: *mut u8,
+    buffer_bytes_used: usize,
+    block_size: StackSize
+}
+
+//SAFETY: the blocks, and every value in them, are owned by the allocator,
+//the raw pointers in it only ever point into those blocks,
+//so moving an allocator along with its Values to another thread
+//moves everything it can reach.  StackRefs borrow the allocator,
+//so none can be left behind on the previous thread.
+//Frames made with new_frame or new_scope push onto their parent's blocks,
+//so they're Child frames, which hold raw pointers and stay !Send.
+//Every Child frame borrows the allocator it was made from,
+//so a Root allocator can't be moved to another thread while one is alive.
+//The allocator isn't Sync, since pushing through &self mutates it without synchronization.
+unsafe impl<'s, Value: Send, A: Allocator + Send> Send for StackFrameAllocator<'s, Value, A, Root> {}
+
+/// Configures a [StackFrameAllocator](crate::stack_frame_allocator::StackFrameAllocator)
+/// before creating it, created by
+/// [builder](crate::stack_frame_allocator::StackFrameAllocator::builder).
+pub struct StackFrameAllocatorBuilder<Value, A: Allocator = Global> {
+    size: StackSize,
+    growth: GrowthPolicy,
+    on_block_alloc: Option<Arc<dyn Fn(usize) + Send + Sync>>,
+    allocator: A,
+    phantom: PhantomData<Value>
+}
+
+impl<Value, A: Allocator> StackFrameAllocatorBuilder<Value, A> {
+    /// Sets how large the first block is in bytes.
+    ///
+    /// Defaults to 1024 bytes.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `bytes` isn't a power of two,
+    /// [build](crate::stack_frame_allocator::StackFrameAllocatorBuilder::build) panics if `bytes`
+    /// is too small to hold a frame header and a block tail.
+    pub fn block_size(mut self, bytes: usize) -> Self {
+        //block sizes are kept as powers of two,
+        //so the block tail at the end of the block stays aligned
+        assert!(bytes.is_power_of_two(), "block size must be a power of two, got {} bytes", bytes);
+        self.size = StackSize::from_num_bytes(bytes);
+        self
+    }
+
+    /// Sets how large every block allocated after the first block is.
+    ///
+    /// Defaults to [GrowthPolicy::Fixed](crate::growth_policy::GrowthPolicy::Fixed).
+    pub fn growth(mut self, growth: GrowthPolicy) -> Self {
+        self.growth = growth;
+        self
+    }
+
+    /// Sets a callback called with the size in bytes of every block allocated after the first block,
+    /// so the allocator growing can be observed without polling it.
+    ///
+    /// Frames made from the allocator call the same callback.
+    /// Defaults to no callback.
+    pub fn on_block_alloc(mut self, on_block_alloc: impl Fn(usize) + Send + Sync + 'static) -> Self {
+        self.on_block_alloc = Some(Arc::new(on_block_alloc));
+        self
+    }
+
+    /// Sets the allocator blocks are allocated and deallocated with.
+    ///
+    /// Defaults to [Global](allocator_api2::alloc::Global).
+    pub fn allocator<B: Allocator>(self, allocator: B) -> StackFrameAllocatorBuilder<Value, B> {
+        StackFrameAllocatorBuilder {
+            size: self.size,
+            growth: self.growth,
+            on_block_alloc: self.on_block_alloc,
+            allocator,
+            phantom: PhantomData
+        }
+    }
+
+    /// Creates the configured StackFrameAllocator.
+    pub fn build<'s>(self) -> StackFrameAllocator<'s, Value, A> {
+        let mut stack = StackFrameAllocator::with_stack_size_in(self.size, self.growth, self.allocator);
+        stack.on_block_alloc = self.on_block_alloc;
+        stack
+    }
+
+    /// Creates the configured StackFrameAllocator,
+    /// returning an error instead of aborting if the first block can't be allocated.
+    pub fn try_build<'s>(self) -> Result<StackFrameAllocator<'s, Value, A>, AllocError> {
+        let mut stack = StackFrameAllocator::try_with_stack_size_in(self.size, self.growth, self.allocator)?;
+        stack.on_block_alloc = self.on_block_alloc;
+        Ok(stack)
+    }
+}
+
+/// Creates a new StackFrameAllocator, the same as
+/// [new](crate::stack_frame_allocator::StackFrameAllocator::new).
+impl<'s, Value> Default for StackFrameAllocator<'s, Value> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Creates a new StackFrameAllocator with every Value pushed onto the root frame,
+/// in iteration order.
+///
+/// If the iterator knows exactly how many Values it yields,
+/// the first block is made large enough to fit all of them, the same as
+/// [with_capacity](crate::stack_frame_allocator::StackFrameAllocator::with_capacity).
+/// Otherwise blocks have the default size.
+///
+/// # Examples
+///
+/// ```edition2020
+/// # use stack_frame_allocators::stack_frame_allocator::StackFrameAllocator;
+///
+/// let mut stack: StackFrameAllocator<u64> = (0..1000).collect();
+/// assert_eq!(stack.peek(), Some(&999));
+/// assert_eq!(stack.allocated_blocks(), 1);
+/// ```
+impl<'s, Value> FromIterator<Value> for StackFrameAllocator<'s, Value> {
+    fn from_iter<I: IntoIterator<Item = Value>>(iter: I) -> Self {
+        let iter = iter.into_iter();
+        let stack = match iter.size_hint() {
+            (lower, Some(upper)) if lower == upper => Self::with_capacity(lower),
+            _ => Self::new()
+        };
+
+        for value in iter {
+            stack.push(value);
+        }
+
+        stack
+    }
+}
+
+/// Pushes every Value into the current frame, in iteration order.
+///
+/// Implemented for a reference, since pushing only needs a shared reference,
+/// so `(&stack).extend(values)` works while StackRefs into the stack are held.
+///
+/// # Examples
+///
+/// ```edition2020
+/// # use stack_frame_allocators::stack_frame_allocator::StackFrameAllocator;
+///
+/// let mut stack = StackFrameAllocator::<u64>::new();
+/// (&stack).extend(0..100);
+/// assert_eq!(stack.peek(), Some(&99));
+/// ```
+impl<'a, 's, Value, A: Allocator, Kind> Extend<Value> for &'a StackFrameAllocator<'s, Value, A, Kind> {
+    fn extend<I: IntoIterator<Item = Value>>(&mut self, iter: I) {
+        for value in iter {
+            self.push(value);
+        }
+    }
+}
+
+impl<'s, Value, A: Allocator, Kind> Debug for StackFrameAllocator<'s, Value, A, Kind> where Value: Debug {
+    /// Formats the current stack the same way
+    /// [print](crate::stack_frame_allocator::StackFrameAllocator::print) does,
+    /// using each Value's Debug implementation.
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        self.fmt_stack(f, false, |value, f| write!(f, "{:?}", value))
+    }
+}
+
+impl<'s, Value, A: Allocator, Kind> Drop for StackFrameAllocator<'s, Value, A, Kind> {
+    fn drop(&mut self) {
+        //eprintln!("dropping stack frame");
+        unsafe {
+            //we're only dropping the current frame,
+            //previous frames are dropped by the allocators that created them
+            let mut walker = self.walker();
+            while let Some(value_ptr) = walker.next_in_frame() {
+                crate::drop_element(value_ptr.cast::<Value>());
+            }
+
+            if let Some(parent_peak) = self.parent_peak.as_ref() {
+                let parent_peak = &mut *parent_peak.get();
+                *parent_peak = (*parent_peak).max(*self.peak_bytes.get());
             }
             
-            if (*self.current_frame.get()).as_ref().previous_frame.is_none() {
+            if self.is_root() {
                 //eprintln!("dropping whole stack");
-                let mut prev_addr;
+                //the root header always sits at the start of the first block
                 let mut next_addr = (*self.current_frame.get()).as_ptr() as *mut u8;
+                let mut next_size = self.size;
+
+                while !next_addr.is_null() {
+                    //eprintln!("dropping block of size {} bytes at {:?}", next_size.bytes(), next_addr);
+                    let (block, size) = (next_addr, next_size);
+                    let block_tail = Self::block_tail_at(block, size);
+                    next_addr = block_tail.next_block;
+                    next_size = StackSize(block_tail.next_block_size);
+
+                    self.deallocate_block(block, size);
+                }
+            }
+        }
+    }
+}
+
+/// Pops the frame created by
+/// [enter_frame](crate::stack_frame_allocator::StackFrameAllocator::enter_frame)
+/// when dropped.
+///
+/// Dereferences to the allocator, so values can be pushed onto the guard's frame.
+pub struct FrameGuard<'a, 's, Value, A: Allocator = Global, Kind = Root> {
+    stack: &'a StackFrameAllocator<'s, Value, A, Kind>
+}
+
+impl<'a, 's, Value, A: Allocator, Kind> Deref for FrameGuard<'a, 's, Value, A, Kind> {
+    type Target = StackFrameAllocator<'s, Value, A, Kind>;
+
+    fn deref(&self) -> &Self::Target {
+        self.stack
+    }
+}
+
+impl<'a, 's, Value, A: Allocator, Kind> Drop for FrameGuard<'a, 's, Value, A, Kind> {
+    fn drop(&mut self) {
+        //enter_frame's caller guarantees the guard's frame is the current frame
+        unsafe {self.stack.pop_frame()}
+    }
+}
+
+/// Moves the Values out of a frame, created by
+/// [drain_frame](crate::stack_frame_allocator::StackFrameAllocator::drain_frame).
+///
+/// Dropping the iterator drops the Values it hasn't yielded yet.
+pub struct DrainFrame<'a, 's, Value, A: Allocator = Global, Kind = Root> {
+    stack: &'a StackFrameAllocator<'s, Value, A, Kind>,
+    walker: StackWalker<'a>
+}
+
+impl<'a, 's, Value, A: Allocator, Kind> DrainFrame<'a, 's, Value, A, Kind> {
+    /// Cuts the frame down to the Values the walker hasn't reached yet.
+    unsafe fn cut_frame(&self) {
+        let stack_frame = (*self.stack.current_frame.get()).as_ptr();
+        let mut below = self.walker.clone();
+        let (top, bytes_used) = match below.next_in_frame() {
+            //the top of the frame sits right after the next Value,
+            //which can be in a previous block if the walker is at the start of a block
+            Some(value_ptr) => {
+                let (_, bytes_before) = below.position();
+                (value_ptr.add(StackFrameAllocator::<Value, A, Kind>::SIZE_VALUE),
+                    bytes_before + StackFrameAllocator::<Value, A, Kind>::SIZE_VALUE)
+            },
+            //the walker stops at the first Value's place after the header,
+            //but an empty frame's top sits right after the header
+            None => {
+                let (items_start, bytes_before) = below.position();
+                let header_end = stack_frame.cast::<u8>().add(StackFrameAllocator::<Value, A, Kind>::SIZE_HEADER);
+                (header_end, bytes_before - (items_start as usize - header_end as usize))
+            }
+        };
+
+        (*stack_frame).current_frame_ptr = top;
+        *self.stack.buffer_bytes_used.get() = bytes_used;
+        *self.stack.block_size.get() = StackSize(below.block_size());
+    }
+}
+
+impl<'a, 's, Value, A: Allocator, Kind> Iterator for DrainFrame<'a, 's, Value, A, Kind> {
+    type Item = Value;
+
+    fn next(&mut self) -> Option<Value> {
+        let value_ptr = self.walker.next_in_frame()?;
+
+        unsafe {
+            //the frame is cut down to the Values not yet yielded,
+            //so a leaked iterator never leaves a moved out Value on the frame
+            self.cut_frame();
+
+            return Some(value_ptr.cast::<Value>().read());
+        }
+    }
+}
+
+impl<'a, 's, Value, A: Allocator, Kind> Drop for DrainFrame<'a, 's, Value, A, Kind> {
+    fn drop(&mut self) {
+        unsafe {
+            while let Some(value_ptr) = self.walker.next_in_frame() {
+                crate::drop_element(value_ptr.cast::<Value>());
+            }
+
+            self.cut_frame();
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::cell::RefCell;
+
+    #[doc(hidden)]
+    #[derive(Clone)]
+    pub struct DropTest<'d>(&'d str, &'d RefCell<Vec<&'d str>>);
+
+    impl<'d> Drop for DropTest<'d> {
+        fn drop(&mut self) {
+            let (value, dropped) = (self.0, self.1);
+
+            dropped.borrow_mut().push(value);
+        }
+    }
+
+    #[doc(hidden)]
+    #[derive(PartialEq, Eq, Hash)]
+    pub struct DropPrint<T>(T) where T : Display;
+
+    impl<T> Drop for DropPrint<T> where T : Display {
+        fn drop(&mut self) {
+            println!("{}", self.0);
+        }
+    }
+
+    #[test]
+    pub fn drop_scope_test() {
+        let dropped = RefCell::new(vec![]);
+        {
+            let stack = StackFrameAllocator::<DropTest>::new();
+            stack.push(DropTest("value1scope1", &dropped));
+            stack.push(DropTest("value2scope1", &dropped));
+            stack.push(DropTest("value3scope1", &dropped));
+            stack.new_scope(|stack| {
+                stack.push(DropTest("value1scope2", &dropped));
+                stack.push(DropTest("value2scope2", &dropped));
+                stack.push(DropTest("value3scope2", &dropped));
+            });
+        }
+
+        let compare = vec![
+            "value3scope2", 
+            "value2scope2", 
+            "value1scope2", 
+            "value3scope1", 
+            "value2scope1", 
+            "value1scope1"
+        ];
+
+        assert_eq!(*dropped.borrow(), compare);
+    }
+
+    #[test]
+    pub fn drop_frame_test() {
+        let dropped = RefCell::new(vec![]);
+        {
+            let stack = StackFrameAllocator::<DropTest>::new();
+            stack.push(DropTest("value1scope1", &dropped));
+            stack.push(DropTest("value2scope1", &dropped));
+            stack.push(DropTest("value3scope1", &dropped));
+            let stack = stack.new_frame();
+            stack.push(DropTest("value1scope2", &dropped));
+            stack.push(DropTest("value2scope2", &dropped));
+            stack.push(DropTest("value3scope2", &dropped));
+        }
+    }
+
+    #[test]
+    pub fn spare_capacity_test() {
+        const NAMES: [&str; 4] = ["spare0", "spare1", "spare2", "spare3"];
+
+        let dropped = RefCell::new(vec![]);
+        {
+            let mut stack = StackFrameAllocator::<DropTest>::new();
+            stack.push(DropTest("pushed", &dropped));
+
+            let spare = stack.spare_capacity_mut();
+            let spare_before = spare.len();
+            assert!(spare_before >= NAMES.len());
+            for (slot, name) in spare.iter_mut().zip(NAMES) {
+                slot.write(DropTest(name, &dropped));
+            }
+            unsafe { stack.set_frame_len_added(NAMES.len()); }
+
+            let spare_after = stack.spare_capacity_mut().len();
+            assert_eq!(spare_after + NAMES.len(), spare_before);
+
+            stack.push(DropTest("after", &dropped));
+        }
+
+        let compare = vec!["after", "spare3", "spare2", "spare1", "spare0", "pushed"];
+
+        assert_eq!(*dropped.borrow(), compare);
+    }
+
+    #[test]
+    pub fn spare_capacity_scope_test() {
+        let stack = StackFrameAllocator::<usize>::new();
+        stack.push(1);
+
+        stack.new_scope(|mut stack| {
+            let spare = stack.spare_capacity_mut();
+            let slots = spare.len();
+            for (i, slot) in spare.iter_mut().enumerate() {
+                slot.write(i);
+            }
+            unsafe { stack.set_frame_len_added(slots); }
+
+            assert!(stack.spare_capacity_mut().is_empty());
+
+            //the block is full, so this spills into a new block
+            let spilled = stack.push(usize::MAX).get();
+            assert_eq!(*spilled, usize::MAX);
+        });
+
+        assert_eq!(*stack.push(2).get(), 2);
+    }
+
+    #[test]
+    pub fn peek_test() {
+        let mut stack = StackFrameAllocator::<u64>::new();
+        assert_eq!(stack.peek(), None);
+
+        for i in 0..500 {
+            stack.push(i);
+            assert_eq!(stack.peek(), Some(&i));
+        }
+
+        stack.new_scope(|mut stack| {
+            assert_eq!(stack.peek(), None);
+            stack.push(1000);
+            assert_eq!(stack.peek(), Some(&1000));
+        });
+
+        assert_eq!(stack.peek(), Some(&499));
+
+        //empty slices only move the top of the frame by the padding before the first value
+        #[derive(Debug, Clone, Copy, PartialEq)]
+        #[repr(align(64))]
+        struct Aligned(u64);
+
+        let mut stack = StackFrameAllocator::<Aligned>::new();
+        stack.push(Aligned(0));
+        stack.new_scope(|mut stack| {
+            stack.push_slice(&[]);
+            assert_eq!(stack.peek(), None);
+            stack.push(Aligned(1));
+            assert_eq!(stack.peek(), Some(&Aligned(1)));
+        });
+        stack.new_scope(|mut stack| {
+            stack.push_all(vec![]);
+            stack.alloc_slice_fill_with(0, |i| Aligned(i as u64));
+            assert_eq!(stack.peek(), None);
+        });
+        assert_eq!(stack.peek(), Some(&Aligned(0)));
+    }
+
+    #[test]
+    pub fn nth_from_top_test() {
+        let mut stack = StackFrameAllocator::<u64>::new();
+        stack.push(1000);
+
+        stack.new_scope(|mut stack| {
+            for i in 0..3 {
+                stack.push(i);
+            }
+
+            for n in 0..3 {
+                assert_eq!(stack.nth_from_top(n).map(|value| *value.get()), Some(2 - n as u64));
+            }
+            //the parent frame isn't reachable
+            assert!(stack.nth_from_top(3).is_none());
+
+            //crossing blocks
+            for i in 3..300 {
+                stack.push(i);
+            }
+            assert_eq!(stack.nth_from_top(299).map(|value| *value.get()), Some(0));
+            *stack.nth_from_top(0).unwrap().get_mut() = 5000;
+            assert_eq!(stack.peek(), Some(&5000));
+        });
+
+        assert_eq!(stack.nth_from_top(0).map(|value| *value.get()), Some(1000));
+    }
+
+    #[test]
+    pub fn get_two_mut_test() {
+        let mut stack = StackFrameAllocator::<u64>::new();
+        stack.push(100);
+
+        stack.new_scope(|mut stack| {
+            for i in 0..300 {
+                stack.push(i);
+            }
+
+            let (a, b) = stack.get_two_mut(0, 2).unwrap();
+            *a += 10;
+            *b += 20;
+            let (a, b) = stack.get_two_mut(2, 0).unwrap();
+            assert_eq!((*a, *b), (22, 10));
+
+            //slots spread across blocks
+            let (a, b) = stack.get_two_mut(1, 299).unwrap();
+            std::mem::swap(a, b);
+            assert_eq!(stack.peek(), Some(&1));
+
+            assert!(stack.get_two_mut(3, 3).is_none());
+            assert!(stack.get_two_mut(0, 300).is_none());
+        });
+
+        assert!(stack.get_two_mut(0, 1).is_none());
+    }
+
+    #[test]
+    pub fn debug_test() {
+        let stack = StackFrameAllocator::<u64>::new();
+        stack.push(1);
+        stack.push(2);
+
+        stack.new_scope(|stack| {
+            stack.push(10);
+
+            assert_eq!(format!("{:?}", stack), concat!(
+                "top of stack\n",
+                "\t10\n",
+                "header\n",
+                "\t2\n",
+                "\t1\n",
+                "header\n",
+                "\n1 block(s) of size 1024 bytes have been allocated."
+            ));
+
+            for i in 0..200 {
+                stack.push(i);
+            }
+
+            let debug = format!("{:?}", stack);
+            assert_eq!(debug.lines().filter(|line| *line == "header").count(), 2);
+            assert!(debug.ends_with("\n2 block(s) of size 1024 bytes have been allocated."));
+        });
+    }
+
+    #[test]
+    pub fn from_fn_test() {
+        let stack = StackFrameAllocator::from_fn(5, |i| i * i);
+        assert_eq!(format!("{:?}", stack), concat!(
+            "top of stack\n",
+            "\t16\n",
+            "\t9\n",
+            "\t4\n",
+            "\t1\n",
+            "\t0\n",
+            "header\n",
+            "\n1 block(s) of size 1024 bytes have been allocated."
+        ));
+
+        //too many values for a default sized block
+        let mut stack = StackFrameAllocator::from_fn(1000, |i| i as u64);
+        assert!(format!("{:?}", stack).ends_with("\n1 block(s) of size 8192 bytes have been allocated."));
+
+        let (first, last) = stack.get_two_mut(0, 999).unwrap();
+        assert_eq!((*first, *last), (0, 999));
+        stack.new_scope(|stack| {
+            stack.push(1000);
+        });
+    }
+
+    #[test]
+    pub fn pop_frame_test() {
+        const NAMES: [&str; 3] = ["frame0", "frame1", "frame2"];
+
+        let dropped = RefCell::new(vec![]);
+        {
+            let mut stack = StackFrameAllocator::<DropTest>::new();
+            stack.push(DropTest("root", &dropped));
+
+            //frames made in place, the way scopes make them
+            for name in NAMES {
+                unsafe {stack.generate_frame();}
+                //spill the frames over multiple blocks
+                for _ in 0..50 {
+                    stack.push(DropTest(name, &dropped));
+                }
+            }
+
+            for name in NAMES.into_iter().rev() {
+                unsafe {stack.pop_frame();}
+
+                let popped = std::mem::take(&mut *dropped.borrow_mut());
+                assert_eq!(popped, vec![name; 50]);
+            }
+
+            assert_eq!(stack.peek().map(|value| value.0), Some("root"));
+            stack.push(DropTest("root2", &dropped));
+        }
+
+        assert_eq!(*dropped.borrow(), vec!["root2", "root"]);
+    }
+
+    #[test]
+    #[cfg(feature = "zeroize")]
+    pub fn zeroize_test() {
+        let dropped = RefCell::new(vec![]);
+        let stack = StackFrameAllocator::<(u64, DropTest)>::new();
+
+        unsafe {stack.generate_frame();}
+        let secret = stack.push((0xdead_beef, DropTest("secret", &dropped)));
+        let secret_ptr = secret.get() as *const (u64, DropTest) as *const u8;
+        unsafe {stack.pop_frame();}
+
+        //the destructor still saw the value before it was scrubbed
+        assert_eq!(*dropped.borrow(), vec!["secret"]);
+
+        //the block is still owned by the allocator, so its bytes can be read
+        let bytes = unsafe {
+            core::slice::from_raw_parts(secret_ptr, core::mem::size_of::<(u64, DropTest)>())
+        };
+        assert!(bytes.iter().all(|byte| *byte == 0));
+    }
+
+    #[test]
+    #[should_panic(expected = "cannot pop the root frame")]
+    pub fn pop_root_frame_test() {
+        let stack = StackFrameAllocator::<u64>::new();
+        unsafe {stack.pop_frame();}
+    }
+
+    #[test]
+    pub fn mem_usage_test() {
+        let stack = StackFrameAllocator::<u8>::new();
+        let usage = stack.mem_usage();
+        assert_eq!(usage.value_bytes, 0);
+        assert_eq!(usage.padding_bytes, 0);
+        assert_eq!(usage.total_bytes(), 1024);
+
+        stack.push(1);
+
+        stack.new_scope(|stack| {
+            //the header is padded after the single u8
+            let usage = stack.mem_usage();
+            assert_eq!(usage.value_bytes, 1);
+            assert_eq!(usage.header_bytes, 2 * 16);
+            assert_eq!(usage.padding_bytes, 7);
+
+            stack.new_scope(|stack| {
+                for i in 0..2000 {
+                    stack.push(i as u8);
+                }
+
+                assert_eq!(stack.using_blocks(), 3);
+                let usage = stack.mem_usage();
+                assert_eq!(usage.value_bytes, 2001);
+                assert_eq!(usage.tail_bytes, 3 * 40);
+                assert_eq!(usage.total_bytes(), stack.allocated_blocks() * 1024);
+            });
+
+            assert_eq!(stack.using_blocks(), 1);
+            assert_eq!(stack.allocated_blocks(), 3);
+            let usage = stack.mem_usage();
+            assert_eq!(usage.value_bytes, 1);
+            assert_eq!(usage.slack_bytes, 3 * (1024 - 40) - (16 + 1 + 7 + 16));
+            assert_eq!(usage.total_bytes(), stack.allocated_blocks() * 1024);
+        });
+    }
+
+    #[test]
+    pub fn shrink_to_fit_test() {
+        let mut stack = StackFrameAllocator::<u64>::new();
+        stack.push(0);
+
+        stack.new_scope(|stack| {
+            for i in 0..300 {
+                stack.push(i);
+            }
+            assert_eq!(stack.allocated_blocks(), 3);
+        });
+
+        assert_eq!(stack.using_blocks(), 1);
+        assert_eq!(stack.allocated_blocks(), 3);
+
+        stack.shrink_to_fit();
+        assert_eq!(stack.allocated_blocks(), 1);
+
+        //new blocks get allocated again once needed
+        stack.new_scope(|mut stack| {
+            for i in 0..300 {
+                stack.push(i);
+            }
+            assert_eq!(stack.allocated_blocks(), 3);
+            assert_eq!(stack.peek(), Some(&299));
+        });
+
+        //a frame spanning the freed blocks pushes into freshly allocated ones
+        stack.shrink_to_fit();
+        {
+            let frame = stack.new_frame();
+            let refs: Vec<_> = (0..300).map(|i| frame.push(i)).collect();
+            assert_eq!(frame.allocated_blocks(), 3);
+            assert_eq!(frame.using_blocks(), 3);
+            assert!(refs.iter().enumerate().all(|(i, value)| *value.get() == i as u64));
+        }
+        stack.shrink_to_fit();
+        assert_eq!(stack.allocated_blocks(), 1);
+        assert_eq!(stack.stats().items_in_frame, 1);
+    }
+
+    #[test]
+    pub fn reserve_test() {
+        let stack = StackFrameAllocator::<u64>::new();
+        stack.push(0);
+
+        stack.reserve(8 * 10);
+        assert_eq!(stack.allocated_blocks(), 1);
+
+        stack.new_scope(|mut stack| {
+            stack.reserve(8 * 1000);
+            let allocated_blocks = stack.allocated_blocks();
+            assert!(allocated_blocks > 1);
+
+            for i in 1..=1000 {
+                stack.push(i);
+            }
+            assert_eq!(stack.allocated_blocks(), allocated_blocks);
+            assert_eq!(stack.peek(), Some(&1000));
+            assert_eq!(stack.mem_usage().value_bytes, 8 * 1001);
+        });
+
+        //reserved blocks left behind count towards the next reserve
+        let allocated_blocks = stack.allocated_blocks();
+        stack.reserve(8 * 1000);
+        assert_eq!(stack.allocated_blocks(), allocated_blocks);
+    }
+
+    #[test]
+    pub fn new_in_test() {
+        use std::{cell::Cell, rc::Rc};
+        use allocator_api2::alloc::AllocError;
+
+        #[derive(Clone)]
+        struct CountingAllocator(Rc<Cell<isize>>);
+
+        unsafe impl Allocator for CountingAllocator {
+            fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+                self.0.set(self.0.get() + 1);
+                Global.allocate(layout)
+            }
+
+            unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+                self.0.set(self.0.get() - 1);
+                Global.deallocate(ptr, layout)
+            }
+        }
+
+        let live_blocks = Rc::new(Cell::new(0));
+        {
+            let mut stack = StackFrameAllocator::<u64, _>::new_in(CountingAllocator(live_blocks.clone()));
+            assert_eq!(live_blocks.get(), 1);
+
+            stack.new_scope(|stack| {
+                for i in 0..300 {
+                    stack.push(i);
+                }
+                assert_eq!(live_blocks.get(), 3);
+            });
+
+            stack.shrink_to_fit();
+            assert_eq!(live_blocks.get(), 1);
+
+            stack.reserve(8 * 300);
+            assert_eq!(live_blocks.get() as usize, stack.allocated_blocks());
+        }
+
+        assert_eq!(live_blocks.get(), 0);
+    }
+
+    #[test]
+    pub fn allocator_api2_test() {
+        use allocator_api2::vec::Vec as Api2Vec;
+
+        use crate::{
+            fixed_stack_frame_allocator::FixedStackFrameAllocator,
+            stack_frame_dict_allocator::StackFrameDictAllocator,
+            stack_frame_general_allocator::StackFrameGeneralAllocator,
+            stack_pool::StackPool
+        };
+
+        //every allocator takes allocator_api2's Global shim on stable
+        let stack = StackFrameAllocator::<u64, Global>::new_in(Global);
+        assert_eq!(*stack.push(1).get(), 1);
+        let dict = StackFrameDictAllocator::<u64, u64, Global>::new_in(Global);
+        assert_eq!(*dict.push(1u64, 2).get(), 2);
+        let general = StackFrameGeneralAllocator::<Global>::new_in(Global);
+        assert_eq!(*general.push(3u32).get(), 3);
+        let fixed = FixedStackFrameAllocator::<u64, 4096, Global>::new_in(Global);
+        assert_eq!(*fixed.push(4).get(), 4);
+
+        //and the StackPool is an allocator_api2 Allocator itself
+        let pool = StackPool::new_in(Global);
+        let mut values = Api2Vec::new_in(&pool);
+        values.extend([1u64, 2, 3]);
+        let mut stack = StackFrameAllocator::<u64, _>::new_in(&pool);
+        stack.push(values.iter().sum());
+        assert_eq!(stack.to_vec(), [6]);
+    }
+
+    #[test]
+    pub fn try_push_test() {
+        use std::cell::Cell;
+
+        use crate::test_alloc::LimitedAllocator;
+
+        let limited = LimitedAllocator(Cell::new(2));
+        let mut stack = StackFrameAllocator::<[u64; 16], _>::new_in(&limited);
+
+        let mut pushed = 0;
+        let error = loop {
+            match stack.try_push([pushed; 16]) {
+                Ok(_) => pushed += 1,
+                Err(error) => break error
+            }
+        };
+
+        assert_eq!(error, PushError::Alloc(AllocError));
+        assert_eq!(stack.allocated_blocks(), 2);
+
+        //a failed push leaves the stack untouched
+        assert!(stack.try_push([0; 16]).is_err());
+        assert_eq!(stack.mem_usage().value_bytes as u64, pushed * 128);
+        assert_eq!(stack.get_two_mut(0, pushed as usize - 1).unwrap().1[0], pushed - 1);
+
+        let limited = LimitedAllocator(Cell::new(1));
+        let dropped = RefCell::new(vec![]);
+        {
+            let stack = StackFrameAllocator::<DropTest, _>::new_in(&limited);
+            while stack.try_push(DropTest("value", &dropped)).is_ok() {}
+
+            //the value that failed to push is dropped right away
+            assert_eq!(dropped.borrow().len(), 1);
+        }
+
+        assert!(dropped.borrow().len() > 1);
+    }
+
+    #[test]
+    pub fn try_new_test() {
+        use std::cell::Cell;
+
+        use crate::test_alloc::LimitedAllocator;
+
+        let limited = LimitedAllocator(Cell::new(0));
+        assert!(matches!(StackFrameAllocator::<[u64; 16], _>::try_new_in(&limited), Err(AllocError)));
+        assert!(matches!(StackFrameAllocator::<[u64; 16]>::builder().allocator(&limited).try_build(), Err(AllocError)));
+
+        limited.0.set(1);
+        let stack = StackFrameAllocator::<[u64; 16], _>::try_new_in(&limited).unwrap();
+        stack.push([1; 16]);
+
+        //the allocator is out of blocks, so the stack can't grow past its first block
+        assert!((0..100).any(|_| stack.try_push([2; 16]).is_err()));
+    }
+
+    #[test]
+    pub fn drain_frame_test() {
+        let dropped = RefCell::new(vec![]);
+        {
+            let mut stack = StackFrameAllocator::<DropTest>::new();
+            stack.push(DropTest("root", &dropped));
+
+            let mut frame = stack.new_frame();
+            for name in ["a", "b", "c", "d", "e"] {
+                frame.push(DropTest(name, &dropped));
+            }
+
+            let mut drain = frame.drain_frame();
+            let drained: Vec<_> = drain.by_ref().take(3).collect();
+            assert_eq!(drained.iter().map(|value| value.0).collect::<Vec<_>>(), ["e", "d", "c"]);
+            assert!(dropped.borrow().is_empty());
+
+            drop(drain);
+            assert_eq!(*dropped.borrow(), ["b", "a"]);
+
+            drop(drained);
+            assert_eq!(*dropped.borrow(), ["b", "a", "e", "d", "c"]);
+
+            //the frame is empty but still usable
+            assert!(frame.peek().is_none());
+            frame.push(DropTest("f", &dropped));
+            assert_eq!(frame.peek().unwrap().0, "f");
+            drop(frame);
+
+            assert_eq!(stack.drain_frame().count(), 1);
+            dropped.borrow_mut().clear();
+        }
+        assert!(dropped.borrow().is_empty());
+
+        //draining values spread over several blocks
+        let mut stack = StackFrameAllocator::<u64>::new();
+        for i in 0..300 {
+            stack.push(i);
+        }
+        assert!(stack.drain_frame().eq((0..300).rev()));
+        assert_eq!(stack.frame_bytes(), StackFrameAllocator::<u64>::new().frame_bytes());
+        stack.push(0);
+        assert_eq!(stack.using_blocks(), 1);
+
+        //leaking the iterator leaves the frame holding the Values it hasn't yielded,
+        //even when the last one yielded was the first Value in its block
+        for drained in 0..300 {
+            let mut stack = StackFrameAllocator::<u64>::new();
+            for i in 0..300 {
+                stack.push(i);
+            }
+
+            let mut drain = stack.drain_frame();
+            assert!(drain.by_ref().take(drained).eq((300 - drained as u64..300).rev()));
+            core::mem::forget(drain);
+
+            assert_eq!(stack.peek().copied(), (300 - drained as u64).checked_sub(1));
+            assert_eq!(stack.mem_usage().value_bytes, 8 * (300 - drained));
+
+            //the top of the frame is where it was before the drained Values were pushed
+            let expected = StackFrameAllocator::<u64>::new();
+            for i in 0..300 - drained as u64 {
+                expected.push(i);
+            }
+            assert_eq!(stack.using_blocks(), expected.using_blocks());
+            assert_eq!(stack.frame_bytes(), expected.frame_bytes());
+            assert_eq!(stack.capacity(), expected.capacity());
+            assert_eq!(unsafe {stack.as_slice()}.is_some(), unsafe {expected.as_slice()}.is_some());
+            stack.push(1000);
+            assert_eq!(stack.peek(), Some(&1000));
+            assert_eq!(stack.iter_frame_rev().count(), 300 - drained + 1);
+        }
+    }
+
+    #[test]
+    pub fn alloc_slice_fill_with_test() {
+        let mut stack = StackFrameAllocator::<u64>::new();
+        let first = stack.push(u64::MAX).as_ptr();
+
+        let squares = stack.alloc_slice_fill_with(100, |i| (i * i) as u64).get();
+        assert_eq!(squares.iter().sum::<u64>(), (0..100).map(|i| i * i).sum::<u64>());
+        assert_eq!(squares[99], 99 * 99);
+
+        //the slice doesn't fit after the first 100 values, so it's placed in the next block
+        let more = stack.alloc_slice_fill_with(100, |i| i as u64).get();
+        assert_eq!(more.iter().sum::<u64>(), 4950);
+        assert_eq!(stack.using_blocks(), 2);
+        assert_eq!(unsafe {*first}, u64::MAX);
+
+        let dropped = RefCell::new(vec![]);
+        {
+            let mut stack = StackFrameAllocator::<DropTest>::new();
+            let names = ["a", "b", "c", "d"];
+            stack.alloc_slice_fill_with(4, |i| DropTest(names[i], &dropped));
+
+            //a panicking closure drops the values it already made
+            let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+                stack.alloc_slice_fill_with(4, |i| {
+                    if i == 2 {
+                        panic!("closure panicked");
+                    }
+                    DropTest(names[i], &dropped)
+                });
+            }));
+            assert!(result.is_err());
+            assert_eq!(*dropped.borrow(), ["a", "b"]);
+            dropped.borrow_mut().clear();
+
+            //the slice's place was handed back
+            assert_eq!(stack.mem_usage().value_bytes, 4 * core::mem::size_of::<DropTest>());
+        }
+
+        //every value of the slice is dropped with the frame
+        assert_eq!(*dropped.borrow(), ["d", "c", "b", "a"]);
+    }
+
+    #[test]
+    pub fn too_large_test() {
+        //a 1024 byte block holds at most 1024 - 40 - 1 bytes, the 40 being the block tail
+        let stack = StackFrameAllocator::<[u8; 983]>::new();
+        stack.try_push([1; 983]).unwrap();
+        stack.try_push([2; 983]).unwrap();
+        assert_eq!(stack.allocated_blocks(), 3);
+
+        let stack = StackFrameAllocator::<[u8; 984]>::new();
+        assert_eq!(
+            stack.try_push([1; 984]).err(),
+            Some(PushError::TooLarge { required: 984, block_capacity: 983 })
+        );
+        assert_eq!(stack.mem_usage().value_bytes, 0);
+
+        let result = std::panic::catch_unwind(|| {
+            StackFrameAllocator::<[u8; 984]>::new().push([1; 984]);
+        });
+        assert!(result.is_err());
+    }
+
+    #[test]
+    pub fn alloc_test() {
+        let dropped = RefCell::new(vec![]);
+        {
+            let stack = StackFrameAllocator::<u64>::new();
+            let a = stack.alloc(1);
+            let b = stack.alloc(2);
+
+            stack.new_scope(|stack| {
+                //values from previous frames stay borrowed across frames
+                for i in 0..500 {
+                    *stack.alloc(i) += *a;
+                }
+                *b += 1;
+            });
+
+            *a += 10;
+            assert_eq!((*a, *b), (11, 3));
+
+            let stack = StackFrameAllocator::<DropTest>::new();
+            stack.alloc(DropTest("a", &dropped)).0 = "b";
+        }
+
+        assert_eq!(*dropped.borrow(), vec!["b"]);
+    }
+
+    #[test]
+    pub fn deref_test() {
+        let stack = StackFrameAllocator::<Vec<u64>>::new();
+        let mut list = stack.push(vec![1, 2]);
+
+        list.push(3);
+        list.extend([4, 5]);
+        assert_eq!(list.len(), 5);
+        assert_eq!(list.iter().sum::<u64>(), 15);
+
+        stack.push(vec![]).push(6);
+
+        let numbers = StackFrameAllocator::<u64>::new();
+        *numbers.push(5) += 1;
+        let mut a = numbers.push(1);
+        *a += 1;
+        assert_eq!(*a, 2);
+    }
+
+    #[test]
+    pub fn new_scope_result_test() {
+        let stack = StackFrameAllocator::<u64>::new();
+        stack.push(1000);
+
+        let total = stack.new_scope(|stack| {
+            let mut total = 0;
+            for i in 0..500 {
+                total += *stack.push(i).get();
+            }
+
+            total
+        });
+        assert_eq!(total, (0..500).sum::<u64>());
+
+        //the scope's frame was popped before the result was handed back
+        let dropped = RefCell::new(vec![]);
+        let stack = StackFrameAllocator::<DropTest>::new();
+        let len = stack.new_scope(|stack| {
+            stack.push(DropTest("a", &dropped));
+            dropped.borrow().len()
+        });
+        assert_eq!(len, 0);
+        assert_eq!(*dropped.borrow(), vec!["a"]);
+    }
+
+    #[test]
+    pub fn with_reused_frame_test() {
+        let dropped = RefCell::new(vec![]);
+        let mut stack = StackFrameAllocator::<DropTest>::new();
+        stack.push(DropTest("root", &dropped));
+
+        let mut iterations = 0;
+        let mut allocated_blocks = 0;
+        let result = stack.with_reused_frame(|stack| {
+            //values from the previous iteration were dropped before this one
+            assert_eq!(stack.stats().items_in_frame, 0);
+            assert_eq!(dropped.borrow().len(), iterations * 100);
+
+            //enough values to spill into another block
+            for _ in 0..100 {
+                stack.push(DropTest("body", &dropped));
+            }
+
+            //the blocks used by the first iteration are reused by the rest
+            if iterations == 0 {
+                allocated_blocks = stack.allocated_blocks();
+            }
+            assert_eq!(stack.allocated_blocks(), allocated_blocks);
+
+            iterations += 1;
+            if iterations < 5 {ControlFlow::Continue(())} else {ControlFlow::Break(iterations)}
+        });
+        assert_eq!(result, 5);
+        assert_eq!(dropped.borrow().len(), 500);
+        assert!(dropped.borrow().iter().all(|value| *value == "body"));
+        assert_eq!(stack.peek().map(|value| value.0), Some("root"));
+    }
+
+    #[test]
+    pub fn enter_frame_test() {
+        let dropped = RefCell::new(vec![]);
+        {
+            let stack = StackFrameAllocator::<DropTest>::new();
+            stack.push(DropTest("root", &dropped));
+
+            let outer = unsafe {stack.enter_frame()};
+            outer.push(DropTest("outer", &dropped));
+
+            for _ in 0..3 {
+                let inner = unsafe {outer.enter_frame()};
+                for _ in 0..100 {
+                    inner.push(DropTest("inner", &dropped));
+                }
+
+                let innermost = unsafe {inner.enter_frame()};
+                innermost.push(DropTest("innermost", &dropped));
+                drop(innermost);
+
+                assert_eq!(dropped.borrow().last(), Some(&"innermost"));
+            }
+            assert_eq!(dropped.borrow().len(), 303);
+
+            outer.push(DropTest("outer", &dropped));
+            drop(outer);
+            assert_eq!(dropped.borrow()[303..], ["outer", "outer"]);
+
+            stack.push(DropTest("root", &dropped));
+        }
+
+        assert_eq!(dropped.borrow()[305..], ["root", "root"]);
+    }
+
+    #[test]
+    pub fn clear_test() {
+        let dropped = RefCell::new(vec![]);
+        let mut stack = StackFrameAllocator::<DropTest>::new();
+        for _ in 0..100 {
+            stack.push(DropTest("a", &dropped));
+        }
+        let allocated_blocks = stack.allocated_blocks();
+
+        stack.clear();
+        assert_eq!(dropped.borrow().len(), 100);
+        assert_eq!(stack.using_blocks(), 1);
+        assert_eq!(stack.mem_usage().value_bytes, 0);
+
+        for _ in 0..100 {
+            stack.push(DropTest("b", &dropped));
+        }
+        assert_eq!(stack.allocated_blocks(), allocated_blocks);
+
+        let mut frame = stack.new_frame();
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| frame.clear()));
+        assert!(result.is_err());
+        drop(frame);
+
+        drop(stack);
+        assert_eq!(dropped.borrow()[100..], ["b"; 100]);
+    }
+
+    #[test]
+    pub fn panic_scope_test() {
+        use std::panic::{self, AssertUnwindSafe};
+
+        let dropped = RefCell::new(vec![]);
+        {
+            let stack = StackFrameAllocator::<DropTest>::new();
+            stack.push(DropTest("root", &dropped));
+
+            let result = panic::catch_unwind(AssertUnwindSafe(|| {
+                stack.new_scope(|mut stack| {
+                    for _ in 0..100 {
+                        stack.push(DropTest("scope", &dropped));
+                    }
+
+                    stack.alloc_with(|| -> DropTest { panic!("exploded") });
+                });
+            }));
+            assert!(result.is_err());
+            assert_eq!(*dropped.borrow(), ["scope"; 100]);
+
+            for _ in 0..100 {
+                stack.push(DropTest("after", &dropped));
+            }
+        }
+
+        assert_eq!(dropped.borrow().len(), 201);
+        assert_eq!(dropped.borrow().last(), Some(&"root"));
+    }
+
+    #[test]
+    pub fn send_test() {
+        let stack = StackFrameAllocator::<String>::new();
+        stack.push("main".to_string());
+
+        let stack = std::thread::spawn(move || {
+            stack.new_scope(|stack| {
+                for i in 0..200 {
+                    stack.push(i.to_string());
+                }
+            });
+            stack.push("thread".to_string());
+
+            stack
+        }).join().unwrap();
+
+        assert_eq!(stack.using_blocks(), 1);
+    }
+
+    #[test]
+    pub fn map_test() {
+        let stack = StackFrameAllocator::<(u64, Vec<u64>)>::new();
+        let pair = stack.push((1, vec![2, 3]));
+        let mut list = pair.map(|(_, list)| list);
+        list.push(4);
+
+        let slice = list.map(|list| list.as_mut_slice());
+        assert_eq!(slice.get(), &[2, 3, 4]);
+
+        stack.new_scope(|stack| {
+            let mut first = stack.push((5, vec![])).map(|(first, _)| first);
+            *first += 1;
+            assert_eq!(*first, 6);
+        });
+        assert_eq!(slice.get(), &[2, 3, 4]);
+    }
+
+    #[test]
+    pub fn push_default_test() {
+        let stack = StackFrameAllocator::<Vec<u8>>::new();
+        stack.push(vec![1, 2, 3]);
+
+        let empty = stack.push_default();
+        assert!(empty.get().is_empty());
+        assert_eq!(stack.mem_usage().value_bytes, 2 * core::mem::size_of::<Vec<u8>>());
+    }
+
+    #[test]
+    pub fn from_iter_test() {
+        let mut stack: StackFrameAllocator<u64> = (0..1000).collect();
+        assert_eq!(stack.allocated_blocks(), 1);
+        assert_eq!(stack.to_vec(), (0..1000).rev().collect::<Vec<_>>());
+
+        //without an exact size, blocks keep the default size
+        let mut stack: StackFrameAllocator<u64> = (0..1000).filter(|i| i % 2 == 0).collect();
+        assert_eq!(stack.allocated_blocks(), 5);
+        assert_eq!(stack.peek(), Some(&998));
+    }
+
+    #[test]
+    pub fn extend_test() {
+        let mut stack = StackFrameAllocator::<u64>::new();
+
+        let first = stack.push(0);
+        (&stack).extend(1..=1000);
+        assert_eq!(*first.get(), 0);
+
+        assert_eq!(stack.to_vec(), (0..=1000).rev().collect::<Vec<_>>());
+    }
+
+    #[test]
+    pub fn push_slice_test() {
+        let stack = StackFrameAllocator::<i32>::new();
+        let empty = stack.push_slice(&[]).get();
+        let slice = stack.push_slice(&[1, 2, 3, 4]).get();
+        assert!(empty.is_empty());
+        assert_eq!(slice, &[1, 2, 3, 4]);
+
+        stack.new_scope(|mut stack| {
+            let values: Vec<i32> = (0..100).collect();
+
+            //slices that don't fit in a block are moved onto the next block
+            let slices: Vec<&[i32]> = (0..10).map(|_| stack.push_slice(&values).get()).collect();
+            assert!(stack.using_blocks() > 1);
+            for slice in slices {
+                assert_eq!(slice, &values[..]);
+            }
+
+            assert_eq!(stack.mem_usage().value_bytes, 4 * (4 + 1000));
+            assert_eq!(stack.peek(), Some(&99));
+            assert_eq!(*stack.get_two_mut(0, 999).unwrap().0, 0);
+        });
+
+        assert_eq!(slice, &[1, 2, 3, 4]);
+    }
+
+    #[test]
+    pub fn push_all_test() {
+        let dropped = RefCell::new(vec![]);
+        {
+            let stack = StackFrameAllocator::<DropTest>::new();
+            stack.push(DropTest("root", &dropped));
+
+            stack.new_scope(|stack| {
+                let names = ["a", "b", "c", "d"];
+                let values = names.iter().map(|name| DropTest(name, &dropped)).collect();
+                let slice = stack.push_all(values).get();
+                assert_eq!(slice.iter().map(|value| value.0).collect::<Vec<_>>(), names);
+                assert!(dropped.borrow().is_empty());
+
+                //Values that don't fit in the current block are moved onto the next block
+                for _ in 0..10 {
+                    stack.push_all(vec![DropTest("many", &dropped); 30]);
+                }
+                assert!(stack.using_blocks() > 1);
+                assert!(stack.push_all(vec![]).get().is_empty());
+                assert!(dropped.borrow().is_empty());
+            });
+
+            //every Value moved in is dropped exactly once, when its frame pops
+            let mut dropped = dropped.borrow().clone();
+            dropped.sort();
+            let mut expected = vec!["a", "b", "c", "d"];
+            expected.extend(["many"; 300]);
+            assert_eq!(dropped, expected);
+        }
+
+        assert_eq!(dropped.borrow().len(), 305);
+        assert_eq!(dropped.borrow().last(), Some(&"root"));
+    }
+
+    #[test]
+    pub fn push_str_test() {
+        let stack = StackFrameAllocator::<u8>::new();
+        let root = stack.push_str("root");
+
+        stack.new_scope(|stack| {
+            let strings: Vec<String> = (0..1000).map(|i| format!("string {}", i)).collect();
+            let pushed: Vec<&str> = strings.iter().map(|s| stack.push_str(s)).collect();
+
+            //strings are never split across blocks
+            assert!(stack.using_blocks() > 1);
+            assert_eq!(pushed, strings);
+            assert_eq!(stack.push_str(""), "");
+        });
+
+        assert_eq!(root, "root");
+        assert_eq!(stack.using_blocks(), 1);
+    }
+
+    #[test]
+    pub fn alloc_with_test() {
+        use std::panic::{self, AssertUnwindSafe};
+
+        let mut stack = StackFrameAllocator::<[u64; 100]>::new();
+        let first = stack.alloc_with(|| [1; 100]).as_ptr();
+
+        //only one Value fits in a block, so every Value spills onto a new block
+        let values: Vec<*const [u64; 100]> = (0..10)
+            .map(|i| stack.alloc_with(|| [i; 100]).as_ptr())
+            .collect();
+        assert_eq!(stack.using_blocks(), 11);
+        for (i, value) in values.into_iter().enumerate() {
+            assert!(unsafe {&*value}.iter().all(|v| *v == i as u64));
+        }
+        assert_eq!(unsafe {&*first}, &[1; 100]);
+
+        let dropped = RefCell::new(vec![]);
+        {
+            let mut stack = StackFrameAllocator::<DropTest>::new();
+            stack.push(DropTest("a", &dropped));
+
+            let result = panic::catch_unwind(AssertUnwindSafe(|| {
+                stack.alloc_with(|| panic!("failed constructing value"));
+            }));
+            assert!(result.is_err());
+
+            //the Value's place was handed back, so it's reused by the next push
+            assert_eq!(stack.mem_usage().value_bytes, core::mem::size_of::<DropTest>());
+            let b = stack.alloc_with(|| DropTest("b", &dropped)).as_ptr();
+            assert_eq!(stack.mem_usage().value_bytes, 2 * core::mem::size_of::<DropTest>());
+            assert_ne!(b, stack.push(DropTest("c", &dropped)).as_ptr());
+            assert!(dropped.borrow().is_empty());
+        }
+
+        assert_eq!(*dropped.borrow(), vec!["c", "b", "a"]);
+    }
+
+    #[test]
+    pub fn on_block_alloc_test() {
+        use std::sync::{Arc, Mutex};
+
+        let sizes = Arc::new(Mutex::new(vec![]));
+        let recorded = sizes.clone();
+        let stack = StackFrameAllocator::<u64>::builder()
+            .growth(GrowthPolicy::Geometric { factor: 2, cap: 4096 })
+            .on_block_alloc(move |size| recorded.lock().unwrap().push(size))
+            .build();
+
+        //the first block comes with the allocator
+        assert!(sizes.lock().unwrap().is_empty());
+
+        stack.new_scope(|stack| {
+            for i in 0..1000u64 {
+                stack.push(i);
+            }
+        });
+        assert_eq!(*sizes.lock().unwrap(), [2048, 4096, 4096]);
+        assert_eq!(sizes.lock().unwrap().len(), stack.allocated_blocks() - 1);
+
+        //blocks left behind by popped frames are reused, so they aren't reported again
+        stack.new_scope(|stack| {
+            for i in 0..1000u64 {
+                stack.push(i);
+            }
+        });
+        assert_eq!(sizes.lock().unwrap().len(), stack.allocated_blocks() - 1);
+    }
+
+    #[test]
+    pub fn block_size_test() {
+        let mut stack = StackFrameAllocator::<u64>::builder()
+            .block_size(64)
+            .build();
+        for i in 0..100u64 {
+            stack.push(i);
+        }
+        assert_eq!(stack.to_vec(), (0..100).rev().collect::<Vec<_>>());
+        assert!(stack.usable_block_size() < 64);
+    }
+
+    #[test]
+    #[should_panic(expected = "a block of 8 bytes is too small, blocks must be at least")]
+    pub fn block_size_too_small_test() {
+        StackFrameAllocator::<u64>::builder()
+            .block_size(8)
+            .build();
+    }
+
+    #[test]
+    pub fn growth_test() {
+        use std::cell::Cell;
+        use allocator_api2::alloc::AllocError;
+
+        //tracks live bytes, so deallocating with the wrong layout shows up
+        struct SizeAllocator(Cell<usize>);
+
+        unsafe impl Allocator for &SizeAllocator {
+            fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+                self.0.set(self.0.get() + layout.size());
+                Global.allocate(layout)
+            }
+
+            unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+                self.0.set(self.0.get() - layout.size());
+                Global.deallocate(ptr, layout)
+            }
+        }
 
-                while !next_addr.is_null() {
-                    //eprintln!("dropping block of size {} bytes at {:?}", self.size.bytes(), next_addr);
-                    
-                    prev_addr = next_addr;
-                    //eprintln!("grabbing tail at {:?}", next_addr.add(self.real_size().bytes()));
-                    let block_tail = next_addr.add(self.real_size().bytes())
-                        .cast::<BlockTail>().as_ref().unwrap_unchecked();
-                    //eprintln!("successfully grabbed tail");
-                    next_addr = block_tail.next_block;
+        let live_bytes = SizeAllocator(Cell::new(0));
+        let dropped = RefCell::new(vec![]);
+        {
+            let mut stack = StackFrameAllocator::<u64, _>::builder()
+                .growth(GrowthPolicy::Geometric { factor: 2, cap: 5000 })
+                .allocator(&live_bytes)
+                .build();
+            stack.push(0);
+
+            stack.new_scope(|mut stack| {
+                for i in 1..=2000 {
+                    stack.push(i);
+                }
+
+                //the cap is rounded down to 4096 bytes
+                let sizes: Vec<usize> = stack.blocks().iter().map(|(_, _, size)| size.bytes()).collect();
+                assert_eq!(sizes, vec![1024, 2048, 4096, 4096, 4096, 4096]);
+                assert_eq!(live_bytes.0.get(), sizes.iter().sum::<usize>());
 
-                    std::alloc::dealloc(prev_addr, Layout::array::<u8>(self.size.bytes()).expect("fuck"));
+                let usage = stack.mem_usage();
+                assert_eq!(usage.value_bytes, 8 * 2001);
+                assert_eq!(usage.total_bytes(), live_bytes.0.get());
+                assert!(format!("{:?}", stack).ends_with("6 block(s) totalling 19456 bytes have been allocated."));
+
+                assert_eq!(stack.peek(), Some(&2000));
+                assert_eq!(*stack.get_two_mut(0, 1999).unwrap().1, 2000);
+            });
+
+            //blocks left behind are reused at their own sizes
+            let allocated_bytes = live_bytes.0.get();
+            stack.new_scope(|stack| {
+                for i in 0..2000 {
+                    stack.push(i);
                 }
+                assert_eq!(live_bytes.0.get(), allocated_bytes);
+
+                //new blocks stay at the cap
+                stack.new_scope(|stack| {
+                    for i in 0..1000 {
+                        stack.push(i);
+                    }
+                    assert!(live_bytes.0.get() > allocated_bytes);
+                    assert_eq!((live_bytes.0.get() - allocated_bytes) % 4096, 0);
+                });
+            });
+
+            stack.shrink_to_fit();
+            assert_eq!(live_bytes.0.get(), 1024);
+
+            let stack = StackFrameAllocator::<DropTest, _>::builder()
+                .growth(GrowthPolicy::Geometric { factor: 4, cap: 1 << 16 })
+                .allocator(&live_bytes)
+                .build();
+            for _ in 0..1000 {
+                stack.push(DropTest("value", &dropped));
             }
         }
+
+        assert_eq!(dropped.borrow().len(), 1000);
+        assert_eq!(live_bytes.0.get(), 0);
     }
-}
 
-#[cfg(test)]
-mod test {
-    use super::*;
+    #[test]
+    pub fn print_to_test() {
+        struct FailingWriter;
 
-    use std::cell::RefCell;
+        impl std::io::Write for FailingWriter {
+            fn write(&mut self, _: &[u8]) -> std::io::Result<usize> {
+                Err(std::io::Error::other("writer is closed"))
+            }
 
-    #[doc(hidden)]
-    pub struct DropTest<'d>(&'d str, &'d RefCell<Vec<&'d str>>);
+            fn flush(&mut self) -> std::io::Result<()> {
+                Ok(())
+            }
+        }
 
-    impl<'d> Drop for DropTest<'d> {
-        fn drop(&mut self) {
-            let (value, dropped) = (self.0, self.1);
+        let stack = StackFrameAllocator::<u64>::new();
+        stack.push(1);
 
-            dropped.borrow_mut().push(value);
+        stack.new_scope(|stack| {
+            stack.push(2);
+
+            let mut out = Vec::new();
+            stack.print_to(&mut out).unwrap();
+            assert_eq!(String::from_utf8(out).unwrap(), concat!(
+                "top of stack\n",
+                "\t2\n",
+                "header\n",
+                "\t1\n",
+                "header\n",
+                "\n1 block(s) of size 1024 bytes have been allocated.\n\n"
+            ));
+
+            assert!(stack.print_to(&mut FailingWriter).is_err());
+        });
+    }
+
+    #[test]
+    #[allow(unused_variables)]
+    pub fn empty_drop_test() {
+        let stack_u8 = StackFrameAllocator::<DropPrint<u8>>::new();
+        let stack_u16 = StackFrameAllocator::<DropPrint<u16>>::new();
+        let stack_u32 = StackFrameAllocator::<DropPrint<u32>>::new();
+        let stack_u64 = StackFrameAllocator::<DropPrint<u64>>::new();
+        let stack_u128 = StackFrameAllocator::<DropPrint<u128>>::new();
+    }
+
+    #[test]
+    pub fn to_vec_test() {
+        let dropped = RefCell::new(vec![]);
+        let stack = StackFrameAllocator::<DropTest>::new();
+        stack.push(DropTest("root", &dropped));
+
+        let names = stack.new_scope(|mut stack| {
+            for name in ["a", "b", "c"] {
+                stack.push(DropTest(name, &dropped));
+            }
+
+            stack.to_vec().iter().map(|value| value.0).collect::<Vec<_>>()
+        });
+
+        //the clones are dropped once they're mapped,
+        //and the frame still drops its own values
+        assert_eq!(names, vec!["c", "b", "a"]);
+        assert_eq!(*dropped.borrow(), vec!["c", "b", "a", "c", "b", "a"]);
+    }
+
+    #[test]
+    pub fn stats_test() {
+        let stack = StackFrameAllocator::<u64>::new();
+
+        let stats = stack.stats();
+        assert_eq!(stats, FrameStats {
+            allocated_blocks: 1,
+            using_blocks: 1,
+            bytes_used: 16,
+            bytes_capacity: 1024 - 40,
+            frame_depth: 1,
+            items_in_frame: 0
+        });
+
+        stack.new_scope(|stack| {
+            stack.new_scope(|stack| {
+                for i in 0..300 {
+                    stack.push(i);
+                }
+
+                let stats = stack.stats();
+                assert_eq!(stats.allocated_blocks, stack.allocated_blocks());
+                assert_eq!(stats.using_blocks, stack.using_blocks());
+                assert_eq!(stats.frame_depth, 3);
+                assert_eq!(stats.items_in_frame, 300);
+                assert_eq!(stats.bytes_capacity, stats.allocated_blocks * (1024 - 40));
+
+                let usage = stack.mem_usage();
+                assert_eq!(stats.bytes_used, usage.value_bytes + usage.header_bytes + usage.padding_bytes);
+            });
+        });
+
+        let stats = stack.stats();
+        assert_eq!(stats.allocated_blocks, 3);
+        assert_eq!(stats.using_blocks, 1);
+        assert_eq!(stats.frame_depth, 1);
+    }
+
+    #[test]
+    pub fn capacity_per_block_test() {
+        let stack = StackFrameAllocator::<u64>::new();
+
+        //1024 bytes, minus the 40 byte tail and the 16 byte root header
+        assert_eq!(stack.usable_block_size(), 1024 - 40 - 16);
+        //minus at most 7 bytes of padding before the first u64,
+        //and the byte the top of a block never reaches
+        assert_eq!(stack.capacity_per_block(), (968 - 7 - 1) / 8);
+
+        for i in 0..stack.capacity_per_block() {
+            stack.push(i as u64);
+        }
+        assert_eq!(stack.stats().using_blocks, 1);
+
+        //bytes never need padding, so only the last byte is lost
+        let stack = StackFrameAllocator::<u8>::new();
+        assert_eq!(stack.capacity_per_block(), 967);
+        for i in 0..stack.capacity_per_block() {
+            stack.push(i as u8);
         }
+        assert_eq!(stack.allocated_blocks(), 1);
+        assert_eq!(stack.capacity(), 0);
+
+        assert_eq!(StackFrameAllocator::<()>::new().capacity_per_block(), usize::MAX);
     }
 
-    #[doc(hidden)]
-    #[derive(PartialEq, Eq, Hash)]
-    pub struct DropPrint<T>(T) where T : Display;
+    #[test]
+    pub fn capacity_test() {
+        fn check<Value>(mut value: impl FnMut() -> Value) {
+            let stack = StackFrameAllocator::<Value>::new();
+            stack.push(value());
 
-    impl<T> Drop for DropPrint<T> where T : Display {
-        fn drop(&mut self) {
-            println!("{}", self.0);
+            stack.new_scope(|stack| {
+                for _ in 0..3 {
+                    let capacity = stack.capacity();
+                    let allocated_blocks = stack.allocated_blocks();
+                    for _ in 0..capacity {
+                        stack.push(value());
+                    }
+                    assert_eq!(stack.capacity(), 0);
+                    assert_eq!(stack.allocated_blocks(), allocated_blocks);
+
+                    //the next push spills into a new block
+                    stack.push(value());
+                    assert_eq!(stack.allocated_blocks(), allocated_blocks + 1);
+                }
+            });
         }
+
+        check(|| 0u8);
+        check(|| 0u64);
+        check(|| [0u8; 24]);
+        check(String::new);
+        check(|| 0u128);
+
+        assert_eq!(StackFrameAllocator::<()>::new().capacity(), usize::MAX);
     }
 
     #[test]
-    pub fn drop_scope_test() {
+    pub fn frame_bytes_test() {
+        let stack = StackFrameAllocator::<u64>::new();
+        for i in 0..10 {
+            stack.push(i);
+        }
+        let parent_bytes = stack.frame_bytes();
+        assert_eq!(parent_bytes, 16 + 10 * 8);
+
+        stack.new_scope(|stack| {
+            assert_eq!(stack.frame_bytes(), 16);
+            for i in 0..5 {
+                stack.push(i);
+            }
+            //the parent's values share the block, but aren't counted
+            assert_eq!(stack.frame_bytes(), 16 + 5 * 8);
+            assert!(stack.stats().bytes_used > stack.frame_bytes());
+
+            //spilling over 3 blocks doesn't count the space left at the end of a block
+            for i in 5..300 {
+                stack.push(i);
+            }
+            assert_eq!(stack.frame_bytes(), 16 + 300 * 8);
+        });
+
+        assert_eq!(stack.frame_bytes(), parent_bytes);
+    }
+
+    #[test]
+    pub fn peak_bytes_test() {
+        let stack = StackFrameAllocator::<u64>::new();
+        stack.push(0);
+        assert_eq!(stack.peak_bytes(), 16 + 8);
+
+        let burst = stack.new_scope(|stack| {
+            stack.new_scope(|stack| {
+                stack.new_scope(|stack| {
+                    for i in 0..500 {
+                        stack.push(i);
+                    }
+                    assert_eq!(stack.peak_bytes(), stack.stats().bytes_used);
+                    stack.stats().bytes_used
+                })
+            })
+        });
+
+        //the burst spanned several blocks, and is long gone
+        assert!(burst > 1024);
+        assert_eq!(stack.stats().bytes_used, 16 + 8);
+        assert_eq!(stack.peak_bytes(), burst);
+
+        //a smaller burst leaves the peak alone
+        stack.new_scope(|stack| {
+            for i in 0..10 {
+                stack.push(i);
+            }
+        });
+        assert_eq!(stack.peak_bytes(), burst);
+
+        stack.reset_peak();
+        assert_eq!(stack.peak_bytes(), 16 + 8);
+
+        //popping frames in place keeps the peak too
+        unsafe {stack.generate_frame();}
+        for i in 0..200 {
+            stack.push(i);
+        }
+        let burst = stack.stats().bytes_used;
+        unsafe {stack.pop_frame();}
+        assert_eq!(stack.peak_bytes(), burst);
+
+        //frames made with new_frame hand their peak back once they're dropped,
+        //through every frame in between
+        stack.reset_peak();
+        let frame = stack.new_frame();
+        let inner = frame.new_frame();
+        for i in 0..500 {
+            inner.push(i);
+        }
+        let burst = inner.stats().bytes_used;
+        assert!(burst > 1024);
+        assert_eq!(stack.peak_bytes(), 16 + 8);
+        drop(inner);
+        assert_eq!(frame.peak_bytes(), burst);
+        drop(frame);
+        assert_eq!(stack.stats().bytes_used, 16 + 8);
+        assert_eq!(stack.peak_bytes(), burst);
+    }
+
+    #[test]
+    pub fn depth_test() {
+        fn recurse<Kind>(stack: &StackFrameAllocator<u64, Global, Kind>, n: usize) {
+            assert_eq!(stack.depth(), n);
+            if n < 50 {
+                stack.new_scope(|stack| {
+                    stack.push(n as u64);
+                    recurse(&stack, n + 1);
+                });
+            }
+            assert_eq!(stack.depth(), n);
+        }
+
+        let stack = StackFrameAllocator::<u64>::new();
+        recurse(&stack, 1);
+
+        let frame = stack.new_frame();
+        assert_eq!(frame.depth(), 2);
+        drop(frame);
+
+        unsafe {
+            let guard = stack.enter_frame();
+            assert_eq!(guard.depth(), 2);
+        }
+        assert_eq!(stack.depth(), 1);
+    }
+
+    #[test]
+    pub fn is_root_test() {
+        let stack = StackFrameAllocator::<u64>::new();
+        stack.push(0);
+        assert!(stack.is_root());
+
+        stack.new_scope(|stack| {
+            assert!(!stack.is_root());
+            stack.new_scope(|stack| assert!(!stack.is_root()));
+        });
+
+        let guard = unsafe {stack.enter_frame()};
+        assert!(!guard.is_root());
+        drop(guard);
+        assert!(stack.is_root());
+    }
+
+    #[test]
+    pub fn truncate_frame_test() {
         let dropped = RefCell::new(vec![]);
-        {
-            let stack = StackFrameAllocator::<DropTest>::new();
-            stack.push(DropTest("value1scope1", &dropped));
-            stack.push(DropTest("value2scope1", &dropped));
-            stack.push(DropTest("value3scope1", &dropped));
+        let stack = StackFrameAllocator::<DropTest>::new();
+        stack.push(DropTest("root", &dropped));
+
+        stack.new_scope(|mut stack| {
+            for name in ["a", "b", "c", "d", "e"] {
+                stack.push(DropTest(name, &dropped));
+            }
+
+            unsafe {stack.truncate_frame(2)};
+            assert_eq!(*dropped.borrow(), vec!["e", "d", "c"]);
+            assert_eq!(stack.peek().map(|value| value.0), Some("b"));
+
+            unsafe {stack.truncate_frame(5)};
+            assert_eq!(dropped.borrow().len(), 3);
+
+            stack.push(DropTest("f", &dropped));
+            assert_eq!(stack.peek().map(|value| value.0), Some("f"));
+
+            unsafe {stack.truncate_frame(0)};
+            assert_eq!(*dropped.borrow(), vec!["e", "d", "c", "f", "b", "a"]);
+            assert!(stack.peek().is_none());
+        });
+        assert_eq!(dropped.borrow().len(), 6);
+
+        //truncating across blocks
+        let stack = StackFrameAllocator::<u64>::new();
+        stack.new_scope(|mut stack| {
+            for i in 0..300 {
+                stack.push(i);
+            }
+            assert_eq!(stack.using_blocks(), 3);
+
+            unsafe {stack.truncate_frame(50)};
+            assert_eq!(stack.peek(), Some(&49));
+            assert_eq!(stack.using_blocks(), 1);
+
+            for i in 50..300 {
+                stack.push(i * 2);
+            }
+            assert_eq!(stack.allocated_blocks(), 3);
+
+            let mut expected: Vec<u64> = (0..50).collect();
+            expected.extend((50..300).map(|i| i * 2));
+            expected.reverse();
+            assert_eq!(stack.to_vec(), expected);
+        });
+    }
+
+    #[test]
+    pub fn checkpoint_test() {
+        let dropped = RefCell::new(vec![]);
+        let stack = StackFrameAllocator::<DropTest>::new();
+
+        stack.new_scope(|mut stack| {
+            stack.push(DropTest("a", &dropped));
+            stack.push(DropTest("b", &dropped));
+            let before = stack.stats();
+
+            let checkpoint = stack.checkpoint();
+            for _ in 0..100 {
+                stack.push(DropTest("speculative", &dropped));
+            }
+            assert!(stack.using_blocks() > 1);
+
+            unsafe {stack.restore(checkpoint)};
+            assert_eq!(dropped.borrow().len(), 100);
+            assert!(dropped.borrow().iter().all(|name| *name == "speculative"));
+            let after = stack.stats();
+            assert_eq!(after.bytes_used, before.bytes_used);
+            assert_eq!(after.using_blocks, 1);
+            assert_eq!(stack.peek().map(|value| value.0), Some("b"));
+
+            //restoring again without pushing drops nothing
+            unsafe {stack.restore(checkpoint)};
+            assert_eq!(dropped.borrow().len(), 100);
+
+            stack.push(DropTest("c", &dropped));
+            assert_eq!(stack.peek().map(|value| value.0), Some("c"));
+        });
+
+        assert_eq!(dropped.borrow()[100..], ["c", "b", "a"]);
+    }
+
+    #[test]
+    #[should_panic(expected = "cannot restore a checkpoint made in a different frame")]
+    pub fn checkpoint_frame_test() {
+        let stack = StackFrameAllocator::<u64>::new();
+        let checkpoint = stack.checkpoint();
+
+        stack.new_scope(|stack| {
+            unsafe {stack.restore(checkpoint)};
+        });
+    }
+
+    #[test]
+    pub fn default_test() {
+        #[derive(Default)]
+        struct Machine<'s> {
+            stack: StackFrameAllocator<'s, u64>,
+            steps: usize
+        }
+
+        let machine = Machine::default();
+        machine.stack.push(1);
+        assert_eq!(machine.steps, 0);
+        assert_eq!(machine.stack.stats().items_in_frame, 1);
+    }
+
+    #[test]
+    pub fn with_capacity_test() {
+        #[repr(align(32))]
+        struct Aligned(#[allow(dead_code)] [u8; 40]);
+
+        for elements in [0, 1, 100, 123, 124, 1000, 4096] {
+            let stack = StackFrameAllocator::<u64>::with_capacity(elements);
+            for i in 0..elements {
+                stack.push(i as u64);
+            }
+            assert_eq!(stack.allocated_blocks(), 1);
+
+            let stack = StackFrameAllocator::<Aligned>::with_capacity(elements);
+            for _ in 0..elements {
+                stack.push(Aligned([0; 40]));
+            }
+            assert_eq!(stack.allocated_blocks(), 1);
+        }
+    }
+
+    #[test]
+    pub fn over_aligned_test() {
+        #[repr(align(128))]
+        #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+        struct Big(u8);
+
+        let stack = StackFrameAllocator::<Big>::new();
+        stack.push(Big(0));
+
+        stack.new_scope(|mut stack| {
+            let refs: Vec<_> = (1..=40).map(|i| stack.push(Big(i)).get()).collect();
+            for (i, value) in refs.iter().enumerate() {
+                assert_eq!(**value, Big(i as u8 + 1));
+                assert_eq!(*value as *const Big as usize % 128, 0);
+            }
+            assert!(stack.using_blocks() > 1);
+
+            let expected: Vec<_> = (1..=40).rev().map(Big).collect();
+            assert_eq!(stack.to_vec(), expected);
+        });
+
+        assert_eq!(stack.stats().items_in_frame, 1);
+    }
+
+    #[test]
+    pub fn swap_test() {
+        let stack = StackFrameAllocator::<u64>::new();
+        let mut first = stack.push(1);
+        let mut second = stack.push(2);
+
+        stack.swap(&mut first, &mut second);
+        assert_eq!((*first, *second), (2, 1));
+
+        stack.new_scope(|stack| {
+            let mut refs: Vec<_> = (0..300).map(|i| stack.push(i)).collect();
+            let mut last = refs.pop().unwrap();
+
+            //the first and last values live in different blocks
+            stack.swap(&mut refs[0], &mut last);
+            assert_eq!(*refs[0], 299);
+            assert_eq!(*last, 0);
+
+            stack.swap(&mut first, &mut last);
+            assert_eq!((*first, *last), (0, 2));
+        });
+
+        assert_eq!(*first, 0);
+    }
+
+    #[test]
+    pub fn contains_in_frame_test() {
+        let mut stack = StackFrameAllocator::<u64>::new();
+        for i in 1..=3 {
+            stack.push(i);
+        }
+        assert!(stack.contains_in_frame(&2));
+        assert!(!stack.contains_in_frame(&4));
+
+        stack.new_scope(|mut stack| {
+            //only the current frame is searched
+            assert!(!stack.contains_in_frame(&2));
+
+            //enough values to span multiple blocks
+            for i in 100..400 {
+                stack.push(i);
+            }
+            assert!(stack.contains_in_frame(&100));
+            assert!(stack.contains_in_frame(&399));
+            assert!(!stack.contains_in_frame(&1));
+        });
+    }
+
+    #[test]
+    pub fn as_slice_test() {
+        let stack = StackFrameAllocator::<u64>::new();
+        assert_eq!(unsafe {stack.as_slice()}, Some(&[][..]));
+        stack.push(1000);
+
+        stack.new_scope(|stack| {
+            assert_eq!(unsafe {stack.as_slice()}, Some(&[][..]));
+
+            for i in 0..50 {
+                stack.push(i);
+            }
+            let values = unsafe {stack.as_slice()}.unwrap();
+            assert_eq!(values, (0..50).collect::<Vec<_>>());
+
+            //once the frame spans multiple blocks, its Values aren't contiguous
+            for i in 50..300 {
+                stack.push(i);
+            }
+            assert!(unsafe {stack.as_slice()}.is_none());
+
+            //a new frame starts out in the current block, so it is contiguous again
             stack.new_scope(|stack| {
-                stack.push(DropTest("value1scope2", &dropped));
-                stack.push(DropTest("value2scope2", &dropped));
-                stack.push(DropTest("value3scope2", &dropped));
+                for i in 0..10 {
+                    stack.push(i);
+                }
+                assert_eq!(unsafe {stack.as_slice()}.unwrap(), (0..10).collect::<Vec<_>>());
             });
-        }
+        });
 
-        let compare = vec![
-            "value3scope2", 
-            "value2scope2", 
-            "value1scope2", 
-            "value3scope1", 
-            "value2scope1", 
-            "value1scope1"
-        ];
+        assert_eq!(unsafe {stack.as_slice()}, Some(&[1000][..]));
 
-        assert_eq!(*dropped.borrow(), compare);
+        let stack = StackFrameAllocator::<u8>::new();
+        stack.push_str("unaligned");
+        assert_eq!(unsafe {stack.as_slice()}, Some(&b"unaligned"[..]));
+
+        let stack = StackFrameAllocator::<()>::new();
+        stack.push(());
+        assert_eq!(unsafe {stack.as_slice()}, Some(&[][..]));
     }
 
     #[test]
-    pub fn drop_frame_test() {
+    pub fn leak_test() {
         let dropped = RefCell::new(vec![]);
-        {
-            let stack = StackFrameAllocator::<DropTest>::new();
-            stack.push(DropTest("value1scope1", &dropped));
-            stack.push(DropTest("value2scope1", &dropped));
-            stack.push(DropTest("value3scope1", &dropped));
-            let stack = stack.new_frame();
-            stack.push(DropTest("value1scope2", &dropped));
-            stack.push(DropTest("value2scope2", &dropped));
-            stack.push(DropTest("value3scope2", &dropped));
+        let stack = StackFrameAllocator::<DropTest>::new();
+        //the leaked reference outlives the borrow of the allocator it was pushed with
+        fn push_root<'s, 'd>(stack: &StackFrameAllocator<'s, DropTest<'d>>, value: DropTest<'d>) -> &'s mut DropTest<'d> {
+            unsafe {stack.push(value).leak()}
         }
+        let leaked = push_root(&stack, DropTest("root", &dropped));
+
+        //frames pushed and popped above the root frame don't touch it
+        stack.new_scope(|stack| {
+            for _ in 0..100 {
+                stack.push(DropTest("scope", &dropped));
+            }
+        });
+        assert_eq!(dropped.borrow().len(), 100);
+
+        leaked.0 = "leaked";
+        assert_eq!(leaked.0, "leaked");
+
+        //the value is still owned by the allocator
+        drop(stack);
+        assert_eq!(dropped.borrow().last(), Some(&"leaked"));
+        assert_eq!(dropped.borrow().len(), 101);
     }
 
     #[test]
-    #[allow(unused_variables)]
-    pub fn empty_drop_test() {
-        let stack_u8 = StackFrameAllocator::<DropPrint<u8>>::new();
-        let stack_u16 = StackFrameAllocator::<DropPrint<u16>>::new();
-        let stack_u32 = StackFrameAllocator::<DropPrint<u32>>::new();
-        let stack_u64 = StackFrameAllocator::<DropPrint<u64>>::new();
-        let stack_u128 = StackFrameAllocator::<DropPrint<u128>>::new();
+    pub fn iter_frame_rev_test() {
+        let stack = StackFrameAllocator::<u64>::new();
+        stack.push(1000);
+
+        stack.new_scope(|mut stack| {
+            for i in 1..=3 {
+                stack.push(i);
+            }
+            assert_eq!(stack.iter_frame_rev().copied().collect::<Vec<_>>(), vec![1, 2, 3]);
+
+            for i in 4..=300 {
+                stack.push(i);
+            }
+            let values: Vec<_> = stack.iter_frame_rev().copied().collect();
+            assert_eq!(values, (1..=300).collect::<Vec<_>>());
+        });
+
+        stack.new_scope(|mut stack| {
+            assert_eq!(stack.iter_frame_rev().count(), 0);
+        });
+    }
+
+    #[test]
+    pub fn push_while_holding_test() {
+        let stack = StackFrameAllocator::<u64>::new();
+        let root = stack.push(0).get_mut();
+
+        stack.new_scope(|stack| {
+            //every push reads a value pushed before it,
+            //and the pushes spill over to new blocks
+            let mut held = vec![stack.push(*root + 1).get()];
+            for _ in 1..300 {
+                let previous = *held.last().unwrap();
+                held.push(stack.push(*previous + 1).get());
+            }
+
+            stack.new_scope(|stack| {
+                let doubled = stack.push(*held[149] * 2).get();
+                *root = *doubled;
+            });
+
+            for (i, value) in held.iter().enumerate() {
+                assert_eq!(**value, i as u64 + 1);
+            }
+        });
+
+        assert_eq!(*root, 300);
     }
 }
\ No newline at end of file
@@ -7,9 +7,23 @@
 //! onto the previous frame.  Key Value pairs can be grabbed by 
 //! searching for the last entry with that key.
 
-use std::{alloc::Layout, cell::UnsafeCell, fmt::Display, hash::Hash, marker::PhantomData, ptr::NonNull};
+use core::{borrow::Borrow, cell::UnsafeCell, fmt::{self, Debug, Formatter}, hash::Hash, marker::PhantomData, ops::{Deref, Index}, ptr::NonNull};
+#[cfg(feature = "std")]
+use core::hash::Hasher;
+#[cfg(feature = "std")]
+use std::{collections::{HashMap, HashSet}, fmt::Display, io::{self, Write}};
 
-use crate::{block_tail::BlockTail, stack_frame_header::StackFrameHeader, stack_ref::unsafe_ref::StackRef, stack_size::StackSize};
+use alloc::{alloc::{handle_alloc_error, Layout}, collections::BTreeMap, sync::Arc, vec::Vec};
+#[cfg(feature = "borrow_check")]
+use alloc::rc::Rc;
+
+use allocator_api2::alloc::{Allocator, Global};
+#[cfg(feature = "serde")]
+use serde::{de::{DeserializeSeed, SeqAccess, Visitor}, Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::{block_tail::BlockTail, max_align, error::{AllocError, PushError}, growth_policy::GrowthPolicy, frame_kind::{Child, Root}, stack_frame_header::StackFrameHeader, stack_ref::unsafe_ref::StackRef, stack_size::StackSize, stack_walker::StackWalker};
+#[cfg(feature = "borrow_check")]
+use crate::stack_ref::unsafe_ref::{BorrowFlag, BorrowTable};
 
 /// The StackFrameDictAllocator allows the creation of "Frames"
 /// where key value pairs can be pushed onto this frame.
@@ -53,31 +67,184 @@ use crate::{block_tail::BlockTail, stack_frame_header::StackFrameHeader, stack_r
 ///     //are not reachable past this point
 /// });
 /// ```
-pub struct StackFrameDictAllocator<'s, Key, Value> 
+///
+/// # Pushing while holding StackRefs
+///
+/// Pushing only ever writes above the top of the current Frame,
+/// or into a new block chained after the current block,
+/// so Key Value pairs never move once they're pushed.
+/// The bookkeeping a push updates lives in the allocator, not next to the pairs,
+/// so StackRefs stay valid while pushing onto the same Frame or a new Frame.
+///
+/// ```edition2020
+/// # use stack_frame_allocators::stack_frame_dict_allocator::StackFrameDictAllocator;
+///
+/// let stack = StackFrameDictAllocator::<&str, usize>::new();
+/// stack.push("x", 1);
+///
+/// stack.new_scope(|stack| {
+///     let x = stack.get_in_stack("x").unwrap().get();
+///     stack.push("y", *x + 1);
+///     assert_eq!(*stack.get_in_frame("y").unwrap().get(), 2);
+/// });
+/// ```
+///
+/// StackRefs can't outlive the Frame they were grabbed from though.
+///
+/// ```compile_fail
+/// # use stack_frame_allocators::stack_frame_dict_allocator::StackFrameDictAllocator;
+///
+/// let stack = StackFrameDictAllocator::<&str, usize>::new();
+///
+/// let y = stack.new_scope(|stack| {
+///     stack.push("y", 2);
+///     stack.get_in_frame("y").unwrap()
+/// });
+/// ```
+pub struct StackFrameDictAllocator<'s, Key, Value, A: Allocator = Global, Kind = Root> 
 where 
     Key: Eq + Hash
 {
     pub(crate) size: StackSize,
+    pub(crate) growth: GrowthPolicy,
+    /// Called with the size of every block allocated after the first block.
+    pub(crate) on_block_alloc: Option<Arc<dyn Fn(usize) + Send + Sync>>,
+    pub(crate) allocator: A,
     pub(crate) current_frame: UnsafeCell<NonNull<StackFrameHeader<'s>>>,
     pub(crate) buffer_bytes_used: UnsafeCell<usize>,
-    pub(crate) phantom: PhantomData<(Key, Value)>
+    pub(crate) block_size: UnsafeCell<StackSize>,
+    pub(crate) entered_frames: UnsafeCell<usize>,
+    #[cfg(feature = "std")]
+    pub(crate) index: Option<UnsafeCell<FrameIndex<Key, Value>>>,
+    /// Shared with every Frame made from this allocator,
+    /// since their StackRefs can point to the same Values.
+    #[cfg(feature = "borrow_check")]
+    pub(crate) borrows: Option<Rc<BorrowTable>>,
+    pub(crate) phantom: PhantomData<(Key, Value)>,
+    pub(crate) kind: PhantomData<Kind>
+}
+
+/// A Frame made with
+/// [new_frame](crate::stack_frame_dict_allocator::StackFrameDictAllocator::new_frame) or
+/// [new_scope](crate::stack_frame_dict_allocator::StackFrameDictAllocator::new_scope).
+///
+/// A Frame pushes onto the same blocks as the allocator it was made from,
+/// so unlike a StackFrameDictAllocator made by a constructor, it can't be sent to another thread.
+///
+/// ```edition2020
+/// # use stack_frame_allocators::stack_frame_dict_allocator::{StackDictFrame, StackFrameDictAllocator};
+///
+/// fn push_pair(stack: StackDictFrame<&str, u64>) {
+///     stack.push("a", 1);
+///     stack.push("b", 2);
+/// }
+///
+/// let stack = StackFrameDictAllocator::<&str, u64>::new();
+/// push_pair(stack.new_frame());
+///
+/// let stack = std::thread::spawn(move || {
+///     stack.push("c", 3);
+///     stack
+/// }).join().unwrap();
+/// assert_eq!(*stack.get_in_frame("c").unwrap().get(), 3);
+/// ```
+///
+/// ```compile_fail
+/// # use stack_frame_allocators::stack_frame_dict_allocator::StackFrameDictAllocator;
+///
+/// let stack = StackFrameDictAllocator::<&str, u64>::new();
+/// let frame = stack.new_frame();
+///
+/// std::thread::spawn(move || {
+///     frame.push("a", 1);
+/// });
+/// stack.push("b", 2);
+/// ```
+///
+/// A Frame borrows the allocator it was made from,
+/// so the allocator can't be sent away while the Frame is still pushing onto its blocks.
+///
+/// ```compile_fail
+/// # use stack_frame_allocators::stack_frame_dict_allocator::StackFrameDictAllocator;
+///
+/// let stack = StackFrameDictAllocator::<&str, u64>::new();
+/// let frame = stack.new_frame();
+///
+/// std::thread::spawn(move || {
+///     stack.push("a", 1);
+/// });
+/// frame.push("b", 2);
+/// ```
+pub type StackDictFrame<'s, Key, Value, A = Global> = StackFrameDictAllocator<'s, Key, Value, A, Child>;
+
+/// Maps every Key in a Frame to the latest Value pushed with it.
+#[cfg(feature = "std")]
+pub(crate) type FrameIndex<Key, Value> = HashMap<FrameKey<Key>, *mut Value>;
+
+/// A Key living in a Frame, hashed and compared by the Key it points to,
+/// so an index doesn't need its own copy of every Key.
+#[cfg(feature = "std")]
+pub(crate) struct FrameKey<Key>(*const Key);
+
+#[cfg(feature = "std")]
+impl<Key: Hash> Hash for FrameKey<Key> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        unsafe {(*self.0).hash(state)}
+    }
+}
+
+#[cfg(feature = "std")]
+impl<Key: PartialEq> PartialEq for FrameKey<Key> {
+    fn eq(&self, other: &Self) -> bool {
+        unsafe {*self.0 == *other.0}
+    }
+}
+
+#[cfg(feature = "std")]
+impl<Key: Eq> Eq for FrameKey<Key> {}
+
+#[cfg(feature = "std")]
+impl<Key, Q: ?Sized> Borrow<KeyQuery<Q>> for FrameKey<Key> where Key: Borrow<Q> {
+    fn borrow(&self) -> &KeyQuery<Q> {
+        KeyQuery::new(unsafe {&*self.0}.borrow())
+    }
+}
+
+/// A borrowed form of a Key, which an index is searched with,
+/// so the index can be searched with anything the Key can be borrowed as.
+#[cfg(feature = "std")]
+#[repr(transparent)]
+pub(crate) struct KeyQuery<Q: ?Sized>(Q);
+
+#[cfg(feature = "std")]
+impl<Q: ?Sized> KeyQuery<Q> {
+    fn new(query: &Q) -> &Self {
+        //KeyQuery is transparent, so it has the same layout as Q
+        unsafe {&*(query as *const Q as *const KeyQuery<Q>)}
+    }
+}
+
+#[cfg(feature = "std")]
+impl<Q: ?Sized + Hash> Hash for KeyQuery<Q> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.hash(state)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<Q: ?Sized + PartialEq> PartialEq for KeyQuery<Q> {
+    fn eq(&self, other: &Self) -> bool {
+        self.0 == other.0
+    }
 }
 
+#[cfg(feature = "std")]
+impl<Q: ?Sized + Eq> Eq for KeyQuery<Q> {}
+
 impl<'s, Key, Value> StackFrameDictAllocator<'s, Key, Value> 
 where 
     Key: Eq + Hash
 {
-    const SIZE_HEADER:   usize = std::mem::size_of::<StackFrameHeader>();
-    const SIZE_KEY:      usize = std::mem::size_of::<Key>();
-    const SIZE_VALUE:    usize = std::mem::size_of::<Value>();
-    const SIZE_TAIL:     usize = std::mem::size_of::<BlockTail>();
-
-    const ALIGN_HEADER:     usize = std::mem::align_of::<StackFrameHeader>();
-    const ALIGN_KEY:        usize = std::mem::align_of::<Key>();
-    const ALIGN_VALUE:      usize = std::mem::align_of::<Value>();
-    #[allow(dead_code)]
-    const ALIGN_TAIL:       usize = std::mem::align_of::<BlockTail>();
-
     /// Creates a new StackFrameDictAllocator
     /// 
     /// The StackFrameDictAllocator allows the creation of "Frames"
@@ -123,14 +290,255 @@ where
     /// });
     /// ```
     pub fn new() -> Self {
-        let size = StackSize::default();
+        Self::new_in(Global)
+    }
+
+    /// Creates a new StackFrameDictAllocator like
+    /// [new](crate::stack_frame_dict_allocator::StackFrameDictAllocator::new),
+    /// but returns an error instead of aborting if the first block can't be allocated.
+    ///
+    /// # Examples
+    ///
+    /// ```edition2020
+    /// # use stack_frame_allocators::stack_frame_dict_allocator::StackFrameDictAllocator;
+    ///
+    /// let stack = StackFrameDictAllocator::<&str, usize>::try_new().unwrap();
+    /// stack.push("a", 1);
+    /// ```
+    pub fn try_new() -> Result<Self, AllocError> {
+        Self::try_new_in(Global)
+    }
+
+    /// Creates a new StackFrameDictAllocator that keeps an index
+    /// of the current Frame's Keys, so
+    /// [get_in_frame](crate::stack_frame_dict_allocator::StackFrameDictAllocator::get_in_frame)
+    /// is a hash lookup instead of a search through the whole Frame.
+    ///
+    /// The index is a HashMap living outside of the stack, holding a pointer
+    /// to every distinct Key and its latest Value in the current Frame,
+    /// so every push also costs a hash and may grow the index.
+    /// Frames made from this StackFrameDictAllocator get their own index.
+    /// [get_in_stack](crate::stack_frame_dict_allocator::StackFrameDictAllocator::get_in_stack)
+    /// still searches the stack.
+    ///
+    /// # Examples
+    ///
+    /// ```edition2020
+    /// # use stack_frame_allocators::stack_frame_dict_allocator::StackFrameDictAllocator;
+    ///
+    /// let stack = StackFrameDictAllocator::<usize, usize>::new_indexed();
+    /// for i in 0..1000 {
+    ///     stack.push(i, i);
+    /// }
+    ///
+    /// //shadow 500
+    /// stack.push(500usize, 0);
+    ///
+    /// assert_eq!(*stack.get_in_frame(999usize).unwrap().get(), 999);
+    /// assert_eq!(*stack.get_in_frame(500usize).unwrap().get(), 0);
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn new_indexed() -> Self {
+        let mut stack = Self::new();
+        stack.index = Some(UnsafeCell::new(HashMap::new()));
+
+        return stack;
+    }
+
+    /// Creates a new StackFrameDictAllocator whose StackRefs check at runtime
+    /// that they follow the borrowing rules, the same way a RefCell does.
+    ///
+    /// [get](crate::stack_ref::unsafe_ref::StackRef::get) panics if another StackRef
+    /// to the same Value has called [get_mut](crate::stack_ref::unsafe_ref::StackRef::get_mut),
+    /// and [get_mut](crate::stack_ref::unsafe_ref::StackRef::get_mut) panics if another StackRef
+    /// to the same Value has called either.  A StackRef holds on to its borrow until it's dropped.
+    /// Frames made from this StackFrameDictAllocator are checked along with it.
+    ///
+    /// This is meant for catching aliasing bugs during development,
+    /// every borrow is recorded in a map living outside of the stack.
+    /// Only available with the `borrow_check` feature,
+    /// which also makes every StackRef hold on to its borrow of the allocator until it's dropped.
+    ///
+    /// # Examples
+    ///
+    /// ```edition2020,should_panic
+    /// # use stack_frame_allocators::stack_frame_dict_allocator::StackFrameDictAllocator;
+    ///
+    /// let stack = StackFrameDictAllocator::<&str, usize>::new_checked();
+    /// stack.push("a", 0);
+    ///
+    /// let mut a = stack.get_in_frame("a").unwrap();
+    /// let mut also_a = stack.get_in_frame("a").unwrap();
+    ///
+    /// let a = unsafe {a.get_mut()};
+    /// //panics, a is already mutably borrowed
+    /// let also_a = unsafe {also_a.get_mut()};
+    /// ```
+    #[cfg(feature = "borrow_check")]
+    pub fn new_checked() -> Self {
+        let mut stack = Self::new();
+        stack.borrows = Some(Rc::new(BorrowTable::default()));
+
+        return stack;
+    }
+
+    /// Creates a new StackFrameDictAllocator whose first block is large enough
+    /// to fit `elements` Key Value pairs in the root Frame.
+    ///
+    /// Blocks are never made smaller than the default block size,
+    /// and blocks allocated after the first block are just as large.
+    ///
+    /// # Examples
+    ///
+    /// ```edition2020
+    /// # use stack_frame_allocators::stack_frame_dict_allocator::StackFrameDictAllocator;
+    ///
+    /// let stack = StackFrameDictAllocator::<u8, u64>::with_capacity(1000);
+    /// for i in 0..1000 {
+    ///     stack.push((i % 256) as u8, i);
+    /// }
+    ///
+    /// assert_eq!(stack.to_vec().len(), 1000);
+    /// ```
+    pub fn with_capacity(elements: usize) -> Self {
+        Self::with_stack_size_in(Self::capacity_block_size(elements), GrowthPolicy::Fixed, Global)
+    }
+
+    /// Creates a builder to configure a StackFrameDictAllocator before creating it.
+    ///
+    /// # Examples
+    ///
+    /// ```edition2020
+    /// # use stack_frame_allocators::stack_frame_dict_allocator::StackFrameDictAllocator;
+    /// use stack_frame_allocators::growth_policy::GrowthPolicy;
+    ///
+    /// let stack = StackFrameDictAllocator::<&str, usize>::builder()
+    ///     .growth(GrowthPolicy::Geometric { factor: 2, cap: 1 << 20 })
+    ///     .build();
+    /// stack.push("a", 1);
+    /// ```
+    pub fn builder() -> StackFrameDictAllocatorBuilder<Key, Value> {
+        StackFrameDictAllocatorBuilder {
+            size: StackSize::default(),
+            growth: GrowthPolicy::default(),
+            on_block_alloc: None,
+            allocator: Global,
+            phantom: PhantomData
+        }
+    }
+}
+
+impl<'s, Key, Value, A: Allocator, Kind> StackFrameDictAllocator<'s, Key, Value, A, Kind> 
+where 
+    Key: Eq + Hash
+{
+    const SIZE_HEADER:   usize = core::mem::size_of::<StackFrameHeader>();
+    const SIZE_KEY:      usize = core::mem::size_of::<Key>();
+    const SIZE_VALUE:    usize = core::mem::size_of::<Value>();
+    const SIZE_TAIL:     usize = core::mem::size_of::<BlockTail>();
+
+    const ALIGN_HEADER:     usize = core::mem::align_of::<StackFrameHeader>();
+    const ALIGN_KEY:        usize = core::mem::align_of::<Key>();
+    const ALIGN_VALUE:      usize = core::mem::align_of::<Value>();
+    const ALIGN_TAIL:       usize = core::mem::align_of::<BlockTail>();
+
+    //every key value pair is aligned to the stricter alignment of Key and Value,
+    //that way the padding between a key and its value, as well as the padding
+    //after the value, is the same for every pair, and pairs can be walked
+    //with a fixed stride
+    const ALIGN_KEY_VALUE:  usize = if Self::ALIGN_KEY > Self::ALIGN_VALUE {
+        Self::ALIGN_KEY
+    } else {
+        Self::ALIGN_VALUE
+    };
+    //blocks are aligned to everything placed at a fixed spot in them,
+    //that way no padding is needed at the start of a block,
+    //and the block tail at the end of the block stays aligned
+    const ALIGN_BLOCK:      usize = max_align(max_align(Self::ALIGN_HEADER, Self::ALIGN_TAIL), Self::ALIGN_KEY_VALUE);
+    const OFFSET_VALUE:     usize = Self::SIZE_KEY.next_multiple_of(Self::ALIGN_VALUE);
+    const SIZE_KEY_VALUE:   usize = (Self::OFFSET_VALUE + Self::SIZE_VALUE)
+        .next_multiple_of(Self::ALIGN_KEY_VALUE);
+
+}
+
+impl<'s, Key, Value, A: Allocator> StackFrameDictAllocator<'s, Key, Value, A, Root>
+where
+    Key: Eq + Hash
+{
+    /// Creates a new StackFrameDictAllocator,
+    /// whose blocks are allocated and deallocated with `allocator`.
+    ///
+    /// Frames made from this StackFrameDictAllocator use clones of `allocator`.
+    ///
+    /// # Examples
+    ///
+    /// ```edition2020
+    /// # #![cfg_attr(feature = "nightly", feature(allocator_api))]
+    /// # use stack_frame_allocators::stack_frame_dict_allocator::StackFrameDictAllocator;
+    /// use allocator_api2::alloc::Global;
+    ///
+    /// let stack = StackFrameDictAllocator::<&str, usize>::new_in(Global);
+    /// stack.push("a", 1);
+    /// ```
+    pub fn new_in(allocator: A) -> Self {
+        Self::with_stack_size_in(StackSize::default(), GrowthPolicy::default(), allocator)
+    }
+
+    /// Creates a new StackFrameDictAllocator like
+    /// [new_in](crate::stack_frame_dict_allocator::StackFrameDictAllocator::new_in),
+    /// but returns an error instead of aborting if the first block can't be allocated.
+    pub fn try_new_in(allocator: A) -> Result<Self, AllocError> {
+        Self::try_with_stack_size_in(StackSize::default(), GrowthPolicy::default(), allocator)
+    }
+
+}
+
+impl<'s, Key, Value, A: Allocator, Kind> StackFrameDictAllocator<'s, Key, Value, A, Kind>
+where
+    Key: Eq + Hash
+{
+    /// Gets the size of a block large enough to fit a header and `elements` Key Value pairs.
+    fn capacity_block_size(elements: usize) -> StackSize {
+        //the padding between a Key and its Value, and after the Value,
+        //is already part of SIZE_KEY_VALUE,
+        //so only the first pair after the header can need more padding
+        let bytes_needed = Self::SIZE_HEADER
+            + Self::ALIGN_KEY_VALUE
+            + elements * Self::SIZE_KEY_VALUE
+            + Self::SIZE_TAIL;
+
+        //block sizes are kept as powers of two,
+        //so the block tail at the end of the block stays aligned
+        StackSize::from_num_bytes(
+            bytes_needed.next_power_of_two().max(StackSize::default().bytes())
+        )
+    }
+
+    fn with_stack_size_in(size: StackSize, growth: GrowthPolicy, allocator: A) -> Self {
+        match Self::try_with_stack_size_in(size, growth, allocator) {
+            Ok(stack) => stack,
+            Err(AllocError) => handle_alloc_error(Self::block_layout(size))
+        }
+    }
+
+    fn try_with_stack_size_in(size: StackSize, growth: GrowthPolicy, allocator: A) -> Result<Self, AllocError> {
+        //the root frame's header has to fit in the first block,
+        //and the top of a block never reaches the block's tail
+        let min_bytes = Self::SIZE_HEADER + Self::SIZE_TAIL + 1;
+        assert!(
+            size.bytes() >= min_bytes,
+            "a block of {} bytes is too small, blocks must be at least {} bytes",
+            size.bytes(), min_bytes
+        );
 
         let allocated_block;
         let current_frame_pointer;
         unsafe {
-            allocated_block = std::alloc::alloc(
-                Layout::array::<u8>(size.bytes()).expect("could not allocate memory")
-            );
+            let layout = Self::block_layout(size);
+            allocated_block = match allocator.allocate(layout) {
+                Ok(block) => block.cast::<u8>().as_ptr(),
+                Err(_) => return Err(AllocError)
+            };
             
             //size.bytes() should be a multiple of a large power of two,
             //therefore size.bytes() should be aligned to BlockTail already,
@@ -138,9 +546,11 @@ where
             //at the end of the block
             let block_tail = allocated_block.add(size.bytes() - Self::SIZE_TAIL);
             (block_tail as *mut BlockTail).write(BlockTail {
-                prev_block: std::ptr::null_mut(),
+                prev_block: core::ptr::null_mut(),
                 prev_block_bytes_used: 0 /* we'll never read this value if prev_block is null */,
-                next_block: std::ptr::null_mut()
+                prev_block_size: 0,
+                next_block: core::ptr::null_mut(),
+                next_block_size: 0
             });
 
             current_frame_pointer = allocated_block.add(Self::SIZE_HEADER);
@@ -155,14 +565,24 @@ where
             (allocated_block as *mut StackFrameHeader).write(init_frame) 
         };
         
-        StackFrameDictAllocator {
+        Ok(StackFrameDictAllocator {
             size,
+            growth,
+            on_block_alloc: None,
+            allocator,
             current_frame: UnsafeCell::new(unsafe {
                 NonNull::new_unchecked(allocated_block as *mut StackFrameHeader)
             }),
             buffer_bytes_used: UnsafeCell::new(Self::SIZE_HEADER),
-            phantom: PhantomData::default()
-        }
+            block_size: UnsafeCell::new(size),
+            entered_frames: UnsafeCell::new(0),
+            #[cfg(feature = "std")]
+            index: None,
+            #[cfg(feature = "borrow_check")]
+            borrows: None,
+            phantom: PhantomData,
+            kind: PhantomData
+        })
     }
 
     /// Creates a new frame to push elements onto in a new scope.
@@ -191,16 +611,19 @@ where
     /// It is still memory safe to pass references to the stack, 
     /// it is just not preferred.
     /// 
+    /// Whatever `scope` returns is handed back once the new frame has been popped,
+    /// so the result can't borrow from the new frame.
+    /// 
     /// # Examples
     /// 
     /// ```edition2020
-    /// # use stack_frame_allocators::stack_frame_dict_allocator::StackFrameDictAllocator;
+    /// # use stack_frame_allocators::stack_frame_dict_allocator::{StackDictFrame, StackFrameDictAllocator};
     /// 
     /// pub fn bad_foo(stack: &StackFrameDictAllocator<&str, usize>) {
     ///     //do stuff here
     /// }
     /// 
-    /// pub fn good_foo(stack: StackFrameDictAllocator<&str, usize>) {
+    /// pub fn good_foo(stack: StackDictFrame<&str, usize>) {
     ///     //do stuff here
     /// }
     /// 
@@ -224,26 +647,36 @@ where
     /// });
     /// # }
     /// ``` 
-    pub fn new_scope<'n, F>(&self, mut scope: F) 
+    pub fn new_scope<'n, R, F>(&self, scope: F) -> R
     where 
         's : 'n,
         Key : 'n, 
-        F : FnMut(StackFrameDictAllocator<'n, Key, Value>)
+        A : Clone,
+        F : FnOnce(StackFrameDictAllocator<'n, Key, Value, A, Child>) -> R
     {
         unsafe {
             let new_frame = StackFrameDictAllocator {
                 size: self.size,
-                current_frame: UnsafeCell::new((*self.current_frame.get()).clone()),
-                buffer_bytes_used: UnsafeCell::new(
-                    (*self.buffer_bytes_used.get()).clone()
-                ),
-                phantom: self.phantom
+                growth: self.growth,
+                on_block_alloc: self.on_block_alloc.clone(),
+                allocator: self.allocator.clone(),
+                current_frame: UnsafeCell::new(*self.current_frame.get()),
+                buffer_bytes_used: UnsafeCell::new(*self.buffer_bytes_used.get()),
+                block_size: UnsafeCell::new(*self.block_size.get()),
+                entered_frames: UnsafeCell::new(0),
+                #[cfg(feature = "std")]
+                index: self.index.as_ref().map(|_| UnsafeCell::new(HashMap::new())),
+                #[cfg(feature = "borrow_check")]
+                borrows: self.borrows.clone(),
+                phantom: self.phantom,
+                kind: PhantomData
             };
 
             new_frame.generate_frame();
 
-            //scope will automatically pop the new frame
-            scope(new_frame);
+            //scope will automatically pop the new frame,
+            //before its result is handed back
+            scope(new_frame)
         }
     }
 
@@ -267,14 +700,14 @@ where
     /// # Examples
     /// 
     /// ```edition2020
-    /// # use stack_frame_allocators::stack_frame_dict_allocator::StackFrameDictAllocator;
+    /// # use stack_frame_allocators::stack_frame_dict_allocator::{StackDictFrame, StackFrameDictAllocator};
     /// 
     /// pub struct Chainable {
     ///     //input fields here
     /// }
     /// 
     /// impl Chainable {
-    ///     pub fn chain(&self, stack: StackFrameDictAllocator<&str, usize>, input: usize) -> Chainable {
+    ///     pub fn chain(&self, stack: StackDictFrame<&str, usize>, input: usize) -> Chainable {
     ///         //do stuff
     ///         # return Chainable {};
     ///     }
@@ -290,16 +723,24 @@ where
     ///      .chain(stack.new_frame(), 3);
     /// # }
     /// ```
-    pub fn new_frame(&self) -> StackFrameDictAllocator<'s, Key, Value> {
+    pub fn new_frame<'a>(&'a self) -> StackFrameDictAllocator<'a, Key, Value, A, Child> where A: Clone {
         let stack;
         unsafe {
             stack = StackFrameDictAllocator {
                 size: self.size,
-                current_frame: UnsafeCell::new((*self.current_frame.get()).clone()),
-                buffer_bytes_used: UnsafeCell::new(
-                    (*self.buffer_bytes_used.get()).clone()
-                ),
-                phantom: self.phantom
+                growth: self.growth,
+                on_block_alloc: self.on_block_alloc.clone(),
+                allocator: self.allocator.clone(),
+                current_frame: UnsafeCell::new(*self.current_frame.get()),
+                buffer_bytes_used: UnsafeCell::new(*self.buffer_bytes_used.get()),
+                block_size: UnsafeCell::new(*self.block_size.get()),
+                entered_frames: UnsafeCell::new(0),
+                #[cfg(feature = "std")]
+                index: self.index.as_ref().map(|_| UnsafeCell::new(HashMap::new())),
+                #[cfg(feature = "borrow_check")]
+                borrows: self.borrows.clone(),
+                phantom: self.phantom,
+                kind: PhantomData
             };
 
             stack.generate_frame();
@@ -308,115 +749,499 @@ where
         return stack;
     }
 
-    unsafe fn generate_frame<'n>(&self) {
-        let header_padding = (*(*self.current_frame.get()).as_ptr())
-            .current_frame_ptr
-            .align_offset(Self::ALIGN_HEADER);
-        let can_push_to_block = *self.buffer_bytes_used.get() + 
-            header_padding + Self::SIZE_HEADER < 
-            self.real_size().bytes();
-        
-        let mem = if can_push_to_block {
-            *self.buffer_bytes_used.get() += header_padding + Self::SIZE_HEADER;
-
-            (*(*self.current_frame.get()).as_ptr())
-                .current_frame_ptr
-                .add(header_padding + Self::SIZE_HEADER)
-        } else {
-            let curr_block_tail = self.get_block_tail();
-            
-            if curr_block_tail.next_block.is_null() {
-                let allocated_block = unsafe {std::alloc::alloc(
-                    Layout::array::<u8>(self.size.bytes())
-                        .expect("could not allocate memory")
-                )};
-
-                let next_block_tail = allocated_block.add(
-                    self.size.bytes() - Self::SIZE_TAIL
-                );
-                //eprintln!("writing block tail at {:?}", next_block_tail);
-                (next_block_tail as *mut BlockTail).write(BlockTail {
-                    prev_block: (*self.current_frame.get()).as_ptr().cast(),
-                    prev_block_bytes_used: (*self.buffer_bytes_used.get()),
-                    next_block: std::ptr::null_mut()
-                });
-
-                curr_block_tail.next_block = allocated_block;
-            }
-
-            curr_block_tail.next_block
-        };
-
-        let current_frame_ptr = mem.add(Self::SIZE_HEADER);
-        
-        let new_frame = StackFrameHeader {
-            previous_frame: Some((*self.current_frame.get()).as_ref()),
-            current_frame_ptr
-        };
+    /// Counts how many Frames are on the stack, by following the headers
+    /// from the current Frame down to the root Frame.
+    ///
+    /// The root Frame has a depth of 1, and every nested Frame adds 1.
+    ///
+    /// # Examples
+    ///
+    /// ```edition2020
+    /// # use stack_frame_allocators::stack_frame_dict_allocator::StackFrameDictAllocator;
+    ///
+    /// let stack = StackFrameDictAllocator::<&str, usize>::new();
+    /// assert_eq!(stack.depth(), 1);
+    ///
+    /// stack.new_scope(|stack| {
+    ///     stack.new_scope(|stack| {
+    ///         assert_eq!(stack.depth(), 3);
+    ///     });
+    /// });
+    /// ```
+    pub fn depth(&self) -> usize {
+        let mut stack_frame = unsafe {(*self.current_frame.get()).as_ref()};
+        let mut depth = 1;
 
-        (mem as *mut StackFrameHeader).write(new_frame);
+        while let Some(previous_frame) = stack_frame.previous_frame {
+            stack_frame = previous_frame;
+            depth += 1;
+        }
 
-        *self.current_frame.get() = NonNull::new_unchecked(mem as *mut StackFrameHeader);
+        return depth;
     }
 
-    /// The Tail End of a Memory Block is reserved for storing
-    /// the address to the previous block, 
-    /// how many bytes of the previous block is used,
-    /// and the address to the next block.
-    /// This Tail effectively reduces the usable size of the block
-    /// 
+    /// Whether the current frame is the root frame, which is the only frame
+    /// owning the whole stack, like the allocator made by
+    /// [new](crate::stack_frame_dict_allocator::StackFrameDictAllocator::new).
+    ///
     /// # Examples
-    /// A memory block with layout
-    /// ```text
-    ///   0x0000_0000_0000_0001
-    ///   0x0000_0000_0000_0002
-    ///   0x0000_0000_0000_0003
-    ///   0x0000_0000_0000_0004
-    ///   0x0000_0000_0000_0005
-    ///   0x0000_aaaa_aaaa_aaa0 <- address to prev block
-    ///   0x0000_0000_0000_0400 <- bytes used of prev block
-    ///   0x0000_ffff_ffff_fff0 <- address to next block
+    ///
+    /// ```edition2020
+    /// # use stack_frame_allocators::stack_frame_dict_allocator::StackFrameDictAllocator;
+    ///
+    /// let stack = StackFrameDictAllocator::<&str, usize>::new();
+    /// assert!(stack.is_root());
+    ///
+    /// stack.new_scope(|stack| {
+    ///     assert!(!stack.is_root());
+    /// });
     /// ```
-    /// has size 8 words, however 3 words are reserved
-    /// so `real_size(&self)` will return 5 words worth of space
-    #[inline]
-    fn real_size(&self) -> StackSize {
-        StackSize(self.size.bytes() - Self::SIZE_TAIL)
+    pub fn is_root(&self) -> bool {
+        unsafe {(*self.current_frame.get()).as_ref().previous_frame.is_none()}
     }
 
-    unsafe fn get_block_tail(&self) -> &mut BlockTail {
-        let offset = self.real_size().bytes() - *self.buffer_bytes_used.get();
-        
-        return (*self.current_frame.get())
-            .as_ref()
-            .current_frame_ptr
-            .add(offset)
-            .cast::<BlockTail>()
-            .as_mut()
-            .expect("Error grabbing mutable reference to BlockTail");
+    /// Gets the amount of bytes in the current block that can hold Frames and Key Value pairs,
+    /// which is the block size minus the block tail and the root header.
+    ///
+    /// # Examples
+    ///
+    /// ```edition2020
+    /// # use stack_frame_allocators::stack_frame_dict_allocator::StackFrameDictAllocator;
+    ///
+    /// let stack = StackFrameDictAllocator::<&str, usize>::new();
+    /// assert!(stack.usable_block_size() < 1024);
+    /// ```
+    pub fn usable_block_size(&self) -> usize {
+        return self.real_size().bytes() - Self::SIZE_HEADER;
     }
 
-    /// Pushes a Key Value pair into the current frame,
-    /// returning a StackRef to the Value.
-    /// 
-    /// Multiple Values can have the same key, even within the same frame.
-    /// This allows for shadowing, such that when using 
-    /// [get_in_frame](crate::stack_frame_dict_allocator::StackFrameDictAllocator::get_in_frame)
-    /// you grab the last value that was pushed with that key.
-    /// For example, I add the pair ("key", "first") and then the pair ("key", "second").
-    /// calling `stack.get_in_frame("key")` will grab ("key", "second"), not ("key", "first"),
-    /// because ("key", "second") shadows ("key", "first")
-    /// 
+    /// Gets how many Key Value pairs always fit in a single block of the current block size,
+    /// assuming the worst case padding between the header and the first pair.
+    ///
     /// # Examples
-    /// 
+    ///
     /// ```edition2020
-    /// use std::cell::RefCell;
     /// # use stack_frame_allocators::stack_frame_dict_allocator::StackFrameDictAllocator;
-    /// 
-    /// let stack = StackFrameDictAllocator::<&str, RefCell<usize>>::new();
-    /// 
-    /// {
-    ///     let mut a = stack.push("a", RefCell::new(0)).get().borrow_mut();
+    ///
+    /// let stack = StackFrameDictAllocator::<u32, u64>::new();
+    /// // a u32 padded to 8 bytes, then a u64
+    /// assert_eq!(stack.capacity_per_block(), (stack.usable_block_size() - 8) / 16);
+    /// ```
+    pub fn capacity_per_block(&self) -> usize {
+        if Self::SIZE_KEY_VALUE == 0 {
+            return usize::MAX;
+        }
+
+        //the top of a block never reaches its tail,
+        //so the last byte before it can't hold a pair either
+        return (self.usable_block_size() - (Self::ALIGN_KEY_VALUE - 1) - 1) / Self::SIZE_KEY_VALUE;
+    }
+
+    /// Gets the amount of bytes the current Frame takes up, including its header,
+    /// summed over every block the Frame spans.
+    ///
+    /// Previous Frames sharing the current block aren't counted.
+    ///
+    /// # Examples
+    ///
+    /// ```edition2020
+    /// # use stack_frame_allocators::stack_frame_dict_allocator::StackFrameDictAllocator;
+    ///
+    /// let stack = StackFrameDictAllocator::<u64, u64>::new();
+    /// stack.push(1u64, 1);
+    ///
+    /// stack.new_scope(|stack| {
+    ///     stack.push(2u64, 2);
+    ///     //the header, and a u64 Key and Value
+    ///     assert_eq!(stack.frame_bytes(), 16 + 16);
+    /// });
+    /// ```
+    pub fn frame_bytes(&self) -> usize {
+        return unsafe {self.walker()}.frame_bytes();
+    }
+
+    /// Pops the current Frame without having to wait for a scope to end,
+    /// dropping every Key Value pair in it, and continues pushing onto the previous Frame.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the current Frame is the root Frame.
+    ///
+    /// # Safety
+    ///
+    /// All StackRefs into the current Frame must have been dropped,
+    /// since they would otherwise point to dropped Values.
+    ///
+    /// The previous Frame must belong to this allocator.
+    /// Popping the Frame of an allocator created by
+    /// [new_frame](crate::stack_frame_dict_allocator::StackFrameDictAllocator::new_frame) or
+    /// [new_scope](crate::stack_frame_dict_allocator::StackFrameDictAllocator::new_scope)
+    /// moves it onto its parent's Frame, which it would then drop
+    /// a second time when it goes out of scope.
+    pub unsafe fn pop_frame(&self) {
+        let stack_frame = (*self.current_frame.get()).as_ref();
+        let Some(previous_frame) = stack_frame.previous_frame else {
+            panic!("cannot pop the root frame");
+        };
+
+        let mut walker = self.walker();
+        while let Some(key_ptr) = walker.next_in_frame() {
+            crate::drop_element(key_ptr.cast::<Key>());
+            crate::drop_element(key_ptr.add(Self::OFFSET_VALUE).cast::<Value>());
+        }
+
+        //moving onto the previous frame leaves the walker
+        //at the top of the previous frame
+        walker.next_frame();
+        let (_, bytes_used) = walker.position();
+        let block_size = StackSize(walker.block_size());
+
+        *self.current_frame.get() = NonNull::from(previous_frame);
+        *self.buffer_bytes_used.get() = bytes_used;
+        *self.block_size.get() = block_size;
+        *self.entered_frames.get() = (*self.entered_frames.get()).saturating_sub(1);
+
+        self.rebuild_index();
+    }
+
+    /// Creates a new Frame on top of the current Frame, returning a guard
+    /// which pops the new Frame and drops all of its items when it's dropped.
+    ///
+    /// Unlike [new_scope](crate::stack_frame_dict_allocator::StackFrameDictAllocator::new_scope),
+    /// the new Frame lives as long as the guard does, instead of as long as a closure,
+    /// so entering and exiting Frames doesn't have to follow the shape of the code.
+    /// The guard dereferences to this allocator, so items pushed through it
+    /// are pushed onto the new Frame, and can't outlive the guard.
+    ///
+    /// # Safety
+    ///
+    /// Guards must be dropped in the reverse order they were created in.
+    /// Entering a Frame through an existing guard borrows that guard,
+    /// so the borrow checker enforces this, however two guards made
+    /// directly from this allocator can be dropped out of order,
+    /// which pops the wrong Frame while the other guard is still alive.
+    ///
+    /// This allocator must not be used directly while a guard is alive,
+    /// since items pushed that way aren't tied to the guard
+    /// and are dropped along with the guard's Frame.
+    ///
+    /// Guards must not be forgotten, the guard's Frame would never be popped,
+    /// so every later push would land on it.
+    ///
+    /// # Examples
+    ///
+    /// ```edition2020
+    /// # use stack_frame_allocators::stack_frame_dict_allocator::StackFrameDictAllocator;
+    ///
+    /// let stack = StackFrameDictAllocator::<&str, u64>::new();
+    /// stack.push("a", 0);
+    ///
+    /// {
+    ///     let guard = unsafe {stack.enter_frame()};
+    ///     guard.push("a", 1);
+    ///
+    ///     let inner = unsafe {guard.enter_frame()};
+    ///     inner.push("a", 2);
+    ///     assert_eq!(inner.iter_stack().count(), 3);
+    /// }
+    ///
+    /// assert_eq!(stack.iter_stack().count(), 1);
+    /// ```
+    pub unsafe fn enter_frame(&self) -> FrameGuard<'_, 's, Key, Value, A, Kind> {
+        self.generate_frame();
+        *self.entered_frames.get() += 1;
+
+        FrameGuard { stack: self }
+    }
+
+    /// Drops every Key Value pair on the stack, keeping every allocated block
+    /// around to be reused by later pushes.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the current Frame is not the root Frame,
+    /// since only the allocator owning the root Frame owns the whole stack.
+    ///
+    /// Frames made with [new_frame](crate::stack_frame_dict_allocator::StackFrameDictAllocator::new_frame)
+    /// must already have been dropped, the same as when dropping the allocator.
+    ///
+    /// # Examples
+    ///
+    /// ```edition2020
+    /// # use stack_frame_allocators::stack_frame_dict_allocator::StackFrameDictAllocator;
+    ///
+    /// let mut stack = StackFrameDictAllocator::<u64, u64>::new();
+    /// for i in 0..200u64 {
+    ///     stack.push(i, i);
+    /// }
+    ///
+    /// stack.clear();
+    /// assert!(stack.get_in_stack(0u64).is_none());
+    ///
+    /// stack.push(0u64, 1);
+    /// assert_eq!(stack[0], 1);
+    /// ```
+    pub fn clear(&mut self) {
+        unsafe {
+            if !self.is_root() {
+                panic!("cannot clear a frame that isn't the root frame");
+            }
+            let stack_frame = (*self.current_frame.get()).as_ptr();
+
+            //the root frame holds every pair on the stack
+            let mut walker = self.walker();
+            while let Some(key_ptr) = walker.next_in_frame() {
+                crate::drop_element(key_ptr.cast::<Key>());
+                crate::drop_element(key_ptr.add(Self::OFFSET_VALUE).cast::<Value>());
+            }
+
+            //the root header always sits at the start of the first block
+            (*stack_frame).current_frame_ptr = stack_frame.cast::<u8>().add(Self::SIZE_HEADER);
+            *self.buffer_bytes_used.get() = Self::SIZE_HEADER;
+            *self.block_size.get() = self.size;
+
+            self.clear_index();
+        }
+    }
+
+    /// Drops every Key Value pair in the current Frame that doesn't satisfy `pred`,
+    /// moving the remaining pairs down to close the gaps, so they keep their order.
+    ///
+    /// Later pushes reuse the space freed at the top of the Frame,
+    /// including any blocks the Frame no longer reaches into.
+    /// `pred` is called on every pair from the first push (bottom) to the last push (top),
+    /// before anything is dropped or moved.
+    ///
+    /// # Safety
+    ///
+    /// All StackRefs into the current Frame must have been dropped,
+    /// since the pairs they point to are either dropped or moved.
+    ///
+    /// # Examples
+    ///
+    /// ```edition2020
+    /// # use stack_frame_allocators::stack_frame_dict_allocator::StackFrameDictAllocator;
+    ///
+    /// let stack = StackFrameDictAllocator::<&str, usize>::new();
+    /// stack.push("a", 1);
+    /// stack.push("b", 2);
+    /// stack.push("c", 3);
+    ///
+    /// unsafe {stack.retain_frame(|_, value| value % 2 == 1)};
+    /// assert!(stack.get_in_frame("b").is_none());
+    /// assert_eq!(stack.peek(), Some((&"c", &3)));
+    /// ```
+    pub unsafe fn retain_frame<P: FnMut(&Key, &Value) -> bool>(&self, mut pred: P) {
+        //every slot the frame uses, from the bottom up, alongside where its block
+        //stands, since survivors are moved into the lowest slots
+        let mut walker = self.walker();
+        let mut slots = Vec::new();
+        while let Some(key_ptr) = walker.next_in_frame() {
+            let (_, bytes_before) = walker.position();
+            slots.push((key_ptr, bytes_before, walker.block_size()));
+        }
+        slots.reverse();
+
+        //the predicate can panic, so it's called before the frame is touched
+        let keep: Vec<bool> = slots.iter()
+            .map(|(key_ptr, _, _)| {
+                let (key, value) = Self::key_value(*key_ptr);
+                pred(key, value)
+            })
+            .collect();
+
+        let mut kept = 0;
+        for (&(key_ptr, _, _), keep) in slots.iter().zip(keep) {
+            if !keep {
+                crate::drop_element(key_ptr.cast::<Key>());
+                crate::drop_element(key_ptr.add(Self::OFFSET_VALUE).cast::<Value>());
+                continue;
+            }
+
+            let (slot_ptr, _, _) = slots[kept];
+            if slot_ptr != key_ptr {
+                core::ptr::copy_nonoverlapping(key_ptr, slot_ptr, Self::SIZE_KEY_VALUE);
+            }
+            kept += 1;
+        }
+
+        let stack_frame = (*self.current_frame.get()).as_ptr();
+        let (top, bytes_used, block_size) = match kept.checked_sub(1).map(|last| slots[last]) {
+            //the top of the frame sits right after the last pair kept
+            Some((key_ptr, bytes_before, block_size)) => (
+                key_ptr.add(Self::SIZE_KEY_VALUE),
+                bytes_before + Self::SIZE_KEY_VALUE,
+                block_size
+            ),
+            //the walker stops at the first pair's place after the header,
+            //but an empty frame's top sits right after the header
+            None => {
+                let (items_start, bytes_before) = walker.position();
+                let header_end = stack_frame.cast::<u8>().add(Self::SIZE_HEADER);
+                (
+                    header_end,
+                    bytes_before - (items_start as usize - header_end as usize),
+                    walker.block_size()
+                )
+            }
+        };
+
+        (*stack_frame).current_frame_ptr = top;
+        *self.buffer_bytes_used.get() = bytes_used;
+        *self.block_size.get() = StackSize(block_size);
+
+        self.rebuild_index();
+    }
+
+    unsafe fn generate_frame(&self) {
+        let header_padding = (*(*self.current_frame.get()).as_ptr())
+            .current_frame_ptr
+            .align_offset(Self::ALIGN_HEADER);
+        let can_push_to_block = *self.buffer_bytes_used.get() + 
+            header_padding + Self::SIZE_HEADER < 
+            self.real_size().bytes();
+        
+        let mem = if can_push_to_block {
+            *self.buffer_bytes_used.get() += header_padding + Self::SIZE_HEADER;
+
+            (*(*self.current_frame.get()).as_ptr())
+                .current_frame_ptr
+                .add(header_padding)
+        } else {
+            let (next_block, next_block_size) = self.link_next_block(
+                self.get_block_tail(),
+                (*self.current_frame.get()).as_ref().current_frame_ptr,
+                *self.buffer_bytes_used.get(),
+                *self.block_size.get()
+            );
+
+            //the header sits at the start of the next block,
+            //so the only bytes used in that block are the header's
+            *self.buffer_bytes_used.get() = Self::SIZE_HEADER;
+            *self.block_size.get() = next_block_size;
+
+            next_block
+        };
+
+        let current_frame_ptr = mem.add(Self::SIZE_HEADER);
+        
+        let new_frame = StackFrameHeader {
+            previous_frame: Some((*self.current_frame.get()).as_ref()),
+            current_frame_ptr
+        };
+
+        (mem as *mut StackFrameHeader).write(new_frame);
+
+        *self.current_frame.get() = NonNull::new_unchecked(mem as *mut StackFrameHeader);
+
+        self.clear_index();
+    }
+
+    /// Records `key_ptr` and `value_ptr` as the latest pair with its Key,
+    /// if the current Frame is indexed.
+    #[inline]
+    #[allow(unused_variables)]
+    unsafe fn index_pair(&self, key_ptr: *mut u8, value_ptr: *mut u8) {
+        #[cfg(feature = "std")]
+        if let Some(index) = &self.index {
+            (*index.get()).insert(FrameKey(key_ptr.cast::<Key>()), value_ptr.cast::<Value>());
+        }
+    }
+
+    /// Empties the index, if the current Frame is indexed.
+    unsafe fn clear_index(&self) {
+        #[cfg(feature = "std")]
+        if let Some(index) = &self.index {
+            (*index.get()).clear();
+        }
+    }
+
+    /// Refills the index with the current Frame's pairs,
+    /// if the current Frame is indexed.
+    unsafe fn rebuild_index(&self) {
+        #[cfg(feature = "std")]
+        if let Some(index) = &self.index {
+            let index = &mut *index.get();
+            index.clear();
+
+            //the frame is walked from the top,
+            //so the first time a key is seen is its latest push
+            let mut walker = self.walker();
+            while let Some(key_ptr) = walker.next_in_frame() {
+                index.entry(FrameKey(key_ptr.cast::<Key>()))
+                    .or_insert(key_ptr.add(Self::OFFSET_VALUE).cast::<Value>());
+            }
+        }
+    }
+
+    /// The Tail End of a Memory Block is reserved for storing
+    /// the address to the previous block, 
+    /// how many bytes of the previous block is used,
+    /// the size of the previous block,
+    /// the address to the next block, and the size of the next block.
+    /// This Tail effectively reduces the usable size of the block
+    /// 
+    /// # Examples
+    /// A memory block with layout
+    /// ```text
+    ///   0x0000_0000_0000_0001
+    ///   0x0000_0000_0000_0002
+    ///   0x0000_0000_0000_0003
+    ///   0x0000_0000_0000_0004
+    ///   0x0000_0000_0000_0005
+    ///   0x0000_aaaa_aaaa_aaa0 <- address to prev block
+    ///   0x0000_0000_0000_0400 <- bytes used of prev block
+    ///   0x0000_0000_0000_0400 <- size of prev block
+    ///   0x0000_ffff_ffff_fff0 <- address to next block
+    ///   0x0000_0000_0000_0800 <- size of next block
+    /// ```
+    /// has size 10 words, however 5 words are reserved
+    /// so `real_size(&self)` will return 5 words worth of space
+    /// for the block the top of the current Frame lives in
+    #[inline]
+    fn real_size(&self) -> StackSize {
+        StackSize(unsafe {(*self.block_size.get()).bytes()} - Self::SIZE_TAIL)
+    }
+
+    //the tail lives in a block the allocator points to, not in the allocator itself
+    #[allow(clippy::mut_from_ref)]
+    unsafe fn get_block_tail(&self) -> &mut BlockTail {
+        let offset = self.real_size().bytes() - *self.buffer_bytes_used.get();
+        
+        return (*self.current_frame.get())
+            .as_ref()
+            .current_frame_ptr
+            .add(offset)
+            .cast::<BlockTail>()
+            .as_mut()
+            .expect("Error grabbing mutable reference to BlockTail");
+    }
+
+    /// Pushes a Key Value pair into the current frame,
+    /// returning a StackRef to the Value.
+    /// 
+    /// Multiple Values can have the same key, even within the same frame.
+    /// This allows for shadowing, such that when using 
+    /// [get_in_frame](crate::stack_frame_dict_allocator::StackFrameDictAllocator::get_in_frame)
+    /// you grab the last value that was pushed with that key.
+    /// For example, I add the pair ("key", "first") and then the pair ("key", "second").
+    /// calling `stack.get_in_frame("key")` will grab ("key", "second"), not ("key", "first"),
+    /// because ("key", "second") shadows ("key", "first")
+    ///
+    /// # Panics
+    ///
+    /// Panics if the pair can't fit in an empty block.
+    /// 
+    /// # Examples
+    /// 
+    /// ```edition2020
+    /// use std::cell::RefCell;
+    /// # use stack_frame_allocators::stack_frame_dict_allocator::StackFrameDictAllocator;
+    /// 
+    /// let stack = StackFrameDictAllocator::<&str, RefCell<usize>>::new();
+    /// 
+    /// {
+    ///     let mut a = stack.push("a", RefCell::new(0)).get().borrow_mut();
     ///     
     ///     assert_eq!(*a, 0);
     ///     *a += 2;
@@ -446,26 +1271,64 @@ where
         key: impl Into<Key>, 
         value: Value
     ) -> StackRef<'a, Value> {
-        let (key_padding, value_padding, can_push_to_block, current_frame_ptr);
+        self.try_push(key, value).unwrap_or_else(|error| self.push_failed(error))
+    }
+
+    /// Pushes a Key with the default Value into the current Frame,
+    /// returning a StackRef to the Value.
+    ///
+    /// # Examples
+    ///
+    /// ```edition2020
+    /// # use stack_frame_allocators::stack_frame_dict_allocator::StackFrameDictAllocator;
+    ///
+    /// let stack = StackFrameDictAllocator::<&str, Vec<u8>>::new();
+    ///
+    /// stack.push_default("a");
+    /// assert!(stack["a"].is_empty());
+    /// ```
+    pub fn push_default(&self, key: impl Into<Key>) -> StackRef<'_, Value> where Value: Default {
+        self.push(key, Value::default())
+    }
+
+    /// Pushes a Key Value pair into the current Frame,
+    /// returning a StackRef to the Value,
+    /// or a PushError if a new block was needed but couldn't be allocated,
+    /// or if the pair can't fit in an empty block.
+    /// 
+    /// # Examples
+    /// 
+    /// ```edition2020
+    /// # use stack_frame_allocators::stack_frame_dict_allocator::StackFrameDictAllocator;
+    /// 
+    /// let stack = StackFrameDictAllocator::<&str, usize>::new();
+    /// 
+    /// let a = stack.try_push("a", 1).unwrap().get();
+    /// assert_eq!(*a, 1);
+    /// ```
+    pub fn try_push<'a>(
+        &'a self, 
+        key: impl Into<Key>, 
+        value: Value
+    ) -> Result<StackRef<'a, Value>, PushError> {
+        //converting the key can panic, so it's done before the frame is touched,
+        //otherwise unwinding could drop a frame whose bookkeeping is half updated
+        let key = key.into();
+        let (key_padding, can_push_to_block, current_frame_ptr);
         let (key_ptr, value_ptr): (*mut u8, *mut u8);
         
         unsafe {
             current_frame_ptr = (*(*self.current_frame.get()).as_ptr())
                 .current_frame_ptr;
-            key_padding = current_frame_ptr.align_offset(Self::ALIGN_KEY);
+            key_padding = current_frame_ptr.align_offset(Self::ALIGN_KEY_VALUE);
             key_ptr = current_frame_ptr.add(key_padding);
-            value_padding = key_ptr
-                .add(Self::SIZE_KEY)
-                .align_offset(Self::ALIGN_VALUE);
-            value_ptr = key_ptr.add(Self::SIZE_KEY + value_padding);
+            value_ptr = key_ptr.add(Self::OFFSET_VALUE);
             can_push_to_block = *self.buffer_bytes_used.get() + 
-                key_padding + Self::SIZE_KEY + 
-                value_padding + Self::SIZE_VALUE < 
+                key_padding + Self::SIZE_KEY_VALUE < 
                 self.real_size().bytes();
         }
         
         if can_push_to_block { unsafe {
-            let key = key.into();
             // eprintln!("writing key of size {} at {:?} with {}",
             //     Self::SIZE_KEY, key_ptr, &key
             // );
@@ -474,58 +1337,43 @@ where
             // );
             (key_ptr as *mut Key).write(key);
             (value_ptr as *mut Value).write(value);
-            let offset = key_padding + Self::SIZE_KEY + 
-                value_padding + Self::SIZE_VALUE;
+            let offset = key_padding + Self::SIZE_KEY_VALUE;
             (*(*self.current_frame.get()).as_ptr()).current_frame_ptr = {
                 current_frame_ptr.add(offset)
             };
 
             *self.buffer_bytes_used.get() += offset;
 
-            return StackRef {
-                value: value_ptr as *mut Value,
-                phantom: PhantomData::default()
-            };
-        }} else { unsafe {
-            let curr_block_tail = self.get_block_tail();
-            
-            //if there is no next block, create one
-            if curr_block_tail.next_block.is_null() {
-                let allocated_block = std::alloc::alloc(
-                    Layout::array::<u8>(self.size.bytes())
-                        .expect("could not allocate memory")
-                );
-
-                let next_block_tail = allocated_block
-                    .add(self.size.bytes() - Self::SIZE_TAIL);
-                //eprintln!("writing block tail at {:?}", next_block_tail);
-                (next_block_tail as *mut BlockTail).write(BlockTail {
-                    prev_block: (*self.current_frame.get()).as_ref().current_frame_ptr,
-                    prev_block_bytes_used: (*self.buffer_bytes_used.get()),
-                    next_block: std::ptr::null_mut()
-                });
+            self.index_pair(key_ptr, value_ptr);
 
-                curr_block_tail.next_block = allocated_block;
-            }
+            return Ok(self.stack_ref(value_ptr as *mut Value));
+        }} else { unsafe {
+            let (next_block_addr_ptr, next_block_size) = self.try_link_next_block(
+                self.get_block_tail(),
+                current_frame_ptr,
+                *self.buffer_bytes_used.get(),
+                *self.block_size.get()
+            )?;
 
-            let next_block_addr_ptr = curr_block_tail.next_block;
-            //key_padding is not needed, 
-            //because the block should already be aligned to Key, 
+            //key_padding is not needed,
+            //because blocks are aligned to Key and Value,
             //but its added for consistency
             let key_padding = next_block_addr_ptr
-                .align_offset(Self::ALIGN_KEY);
+                .align_offset(Self::ALIGN_KEY_VALUE);
             let key_ptr = next_block_addr_ptr.add(key_padding);
-            let value_padding = key_ptr
-                .add(Self::SIZE_KEY)
-                .align_offset(Self::ALIGN_VALUE);
-            let value_ptr = key_ptr.add(Self::SIZE_KEY + value_padding);
+            let value_ptr = key_ptr.add(Self::OFFSET_VALUE);
 
-            let block_offset = key_padding + Self::SIZE_KEY +
-                value_padding + Self::SIZE_VALUE;
+            let block_offset = key_padding + Self::SIZE_KEY_VALUE;
+
+            //the top of a block never reaches its tail
+            let block_capacity = next_block_size.bytes() - Self::SIZE_TAIL - 1;
+            if block_offset > block_capacity {
+                return Err(PushError::TooLarge { required: block_offset, block_capacity });
+            }
 
+            *self.block_size.get() = next_block_size;
             *self.buffer_bytes_used.get() = block_offset;
 
-            let key = key.into();
             // eprintln!("writing key of size {} at {:?} with {}",
             //     Self::SIZE_KEY, key_ptr, &key
             // );
@@ -533,15 +1381,14 @@ where
             //     Self::SIZE_VALUE, value_ptr, &value
             // );
 
-            (key_ptr as *mut Key).write(key.into());
+            (key_ptr as *mut Key).write(key);
             (value_ptr as *mut Value).write(value);
             (*(*self.current_frame.get()).as_ptr()).current_frame_ptr =
                 next_block_addr_ptr.add(block_offset);
 
-            return StackRef {
-                value: value_ptr as *mut Value,
-                phantom: PhantomData::default()
-            };
+            self.index_pair(key_ptr, value_ptr);
+
+            return Ok(self.stack_ref(value_ptr as *mut Value));
         }}
     }
 
@@ -557,6 +1404,10 @@ where
     /// be found with function.  If no Value contains this Key in the current Frame,
     /// this function will return a None.
     /// 
+    /// Keys like String are created from `key` just to be compared,
+    /// [get_in_frame_ref](crate::stack_frame_dict_allocator::StackFrameDictAllocator::get_in_frame_ref)
+    /// searches with a `&str` instead, without allocating.
+    /// 
     /// # Examples
     /// 
     /// ```edition2020
@@ -591,92 +1442,358 @@ where
         &'a self, 
         key: impl Into<Key>
     ) -> Option<StackRef<'a, Value>> {
+        self.find_key_in_frame(&key.into())
+    }
+
+    /// Gets every Value with the given Key in the current Frame,
+    /// from the latest push to the first push.
+    ///
+    /// Because keys can be shadowed, the current Frame can hold
+    /// multiple Values with the same Key.
+    ///
+    /// # Examples
+    ///
+    /// ```edition2020
+    /// # use stack_frame_allocators::stack_frame_dict_allocator::StackFrameDictAllocator;
+    ///
+    /// let stack = StackFrameDictAllocator::<&str, usize>::new();
+    /// stack.push("x", 1);
+    /// stack.push("y", 2);
+    /// stack.push("x", 3);
+    ///
+    /// let xs: Vec<usize> = stack.get_all_in_frame("x")
+    ///     .into_iter()
+    ///     .map(|x| *x.get())
+    ///     .collect();
+    /// assert_eq!(xs, vec![3, 1]);
+    /// ```
+    pub fn get_all_in_frame<'a>(
+        &'a self,
+        key: impl Into<Key>
+    ) -> Vec<StackRef<'a, Value>> {
         let key = key.into();
+        let mut walker = unsafe {self.walker()};
+        let mut values = Vec::new();
 
-        let current_frame_ptr = unsafe {
-            (*self.current_frame.get()).as_ptr().cast::<u8>()
-        };
-        let mut bytes_remaining = unsafe {*self.buffer_bytes_used.get()};
-        let mut peek_ptr = unsafe {
-            (*current_frame_ptr.cast::<StackFrameHeader>()).current_frame_ptr
-        };
-        let mut curr_block_tail = unsafe {self.get_block_tail()};
-
-        //because we're only searching within the scope,
-        //we can assume the padding after the header
-        //is key padding, because we shouldn't be expecting a header 
-        //after the header we're looking in
-        let stack_frame_ptr_after = {unsafe {
-            let offset_ptr = current_frame_ptr.add(Self::SIZE_HEADER);
-            let padding = offset_ptr.align_offset(Self::ALIGN_KEY);
-            offset_ptr.add(padding)
-        }};
-
-        //we can't use the fun built-in library functions like align_offset,
-        //so we do this math ourselves
-        let value_padding = -(
-            -(Self::SIZE_KEY as isize) % 
-            Self::ALIGN_VALUE as isize
-        ) as usize;
-        let next_key_padding = -(
-            -(Self::SIZE_VALUE as isize) % 
-            Self::ALIGN_KEY as isize
-        ) as usize;
-
-        let key_value_size = Self::SIZE_KEY + value_padding +
-            Self::SIZE_VALUE + next_key_padding;
-
-        //eprintln!("starting search at {:?} until {:?}", peek_ptr, stack_frame_ptr_after);
-        while peek_ptr > stack_frame_ptr_after {
-            // eprintln!("peeking at {:?} until {:?} with {} bytes remaining", 
-            //     peek_ptr, stack_frame_ptr_after, bytes_remaining
-            // );
-            if bytes_remaining == 0 {
-                if curr_block_tail.prev_block.is_null() {
-                    unreachable!("{}", concat!(
-                        "the previous block can only be null ",  
-                        "if the block currently being looked at is the first block.  ",  
-                        "In that case, the header logic would've ran first, ", 
-                        "thus this should never be reached"
-                    ))
-                }
-                bytes_remaining = curr_block_tail.prev_block_bytes_used;
-                peek_ptr = curr_block_tail.prev_block;
+        while let Some(key_ptr) = walker.next_in_frame() {
+            let key_compare = unsafe {&*key_ptr.cast::<Key>()};
 
-                unsafe {
-                    let offset = self.real_size().bytes() - bytes_remaining;
-        
-                    curr_block_tail = peek_ptr
-                        .add(offset)
-                        .cast::<BlockTail>()
-                        .as_mut()
-                        .expect("Error grabbing mutable reference to BlockTail");
-                }
+            if key == *key_compare {
+                values.push(self.stack_ref(unsafe {key_ptr.add(Self::OFFSET_VALUE).cast::<Value>()}));
             }
+        }
 
-            unsafe {
-                peek_ptr = peek_ptr.sub(key_value_size);
-                bytes_remaining -= key_value_size;
-                let key_compare = (peek_ptr as *mut Key).as_ref_unchecked();
-                let value = peek_ptr.add(Self::SIZE_KEY + next_key_padding)
-                    .cast::<Value>();
-
-                // eprintln!("comparing key {} with value {} at {:?} to key {}",
-                //     key_compare, value.as_ref().unwrap(), peek_ptr, &key
-                // );
-
-                if key == *key_compare {
-                    return Some(StackRef {
-                        value,
-                        phantom: PhantomData::default()
-                    });
+        return values;
+    }
+
+    /// Finds the latest Value of every given Key in the current Frame,
+    /// like calling [get_in_frame](crate::stack_frame_dict_allocator::StackFrameDictAllocator::get_in_frame)
+    /// for each Key, but only walking the Frame once.
+    ///
+    /// The walk stops as soon as every Key has been found.
+    /// Requesting the same Key twice gives two StackRefs to the same Value.
+    ///
+    /// # Examples
+    ///
+    /// ```edition2020
+    /// # use stack_frame_allocators::stack_frame_dict_allocator::StackFrameDictAllocator;
+    ///
+    /// let stack = StackFrameDictAllocator::<&str, usize>::new();
+    /// stack.push("a", 1);
+    /// stack.push("b", 2);
+    /// stack.push("a", 3);
+    ///
+    /// let [a, b, c] = stack.get_many_in_frame(["a", "b", "c"]);
+    /// assert_eq!(*a.unwrap().get(), 3);
+    /// assert_eq!(*b.unwrap().get(), 2);
+    /// assert!(c.is_none());
+    /// ```
+    pub fn get_many_in_frame<const N: usize>(&self, keys: [Key; N]) -> [Option<StackRef<'_, Value>>; N] {
+        #[cfg(feature = "std")]
+        if self.index.is_some() {
+            return keys.map(|key| self.find_key_in_frame(&key));
+        }
+
+        let mut found: [Option<StackRef<'_, Value>>; N] = core::array::from_fn(|_| None);
+        let mut remaining = N;
+        let mut walker = unsafe {self.walker()};
+
+        while remaining > 0 {
+            let Some(key_ptr) = walker.next_in_frame() else {
+                break;
+            };
+            let (key_compare, _) = unsafe {Self::key_value(key_ptr)};
+
+            for (key, value) in keys.iter().zip(found.iter_mut()) {
+                if value.is_none() && key == key_compare {
+                    *value = Some(self.stack_ref(unsafe {key_ptr.add(Self::OFFSET_VALUE).cast::<Value>()}));
+                    remaining -= 1;
                 }
             }
         }
 
+        return found;
+    }
+
+    /// Gets the first Value pushed with the given Key in the current Frame,
+    /// which is the Value every later push with that Key shadows.
+    ///
+    /// Unlike [get_in_frame](crate::stack_frame_dict_allocator::StackFrameDictAllocator::get_in_frame),
+    /// the whole Frame is always searched.
+    ///
+    /// # Examples
+    ///
+    /// ```edition2020
+    /// # use stack_frame_allocators::stack_frame_dict_allocator::StackFrameDictAllocator;
+    ///
+    /// let stack = StackFrameDictAllocator::<&str, usize>::new();
+    /// stack.push("x", 1);
+    /// stack.push("x", 2);
+    ///
+    /// assert_eq!(*stack.get_first_in_frame("x").unwrap().get(), 1);
+    /// assert_eq!(*stack.get_in_frame("x").unwrap().get(), 2);
+    /// ```
+    pub fn get_first_in_frame<'a>(
+        &'a self,
+        key: impl Into<Key>
+    ) -> Option<StackRef<'a, Value>> {
+        let key = key.into();
+        let mut walker = unsafe {self.walker()};
+        let mut first = None;
+
+        //the frame is walked from the top,
+        //so the last match is the first push
+        while let Some(key_ptr) = walker.next_in_frame() {
+            let (key_compare, _) = unsafe {Self::key_value(key_ptr)};
+
+            if key == *key_compare {
+                first = Some(self.stack_ref(unsafe {key_ptr.add(Self::OFFSET_VALUE).cast::<Value>()}));
+            }
+        }
+
+        return first;
+    }
+
+    /// Counts how many Values were pushed with the given Key in the current Frame,
+    /// including Values that are shadowed by later pushes.
+    ///
+    /// # Examples
+    ///
+    /// ```edition2020
+    /// # use stack_frame_allocators::stack_frame_dict_allocator::StackFrameDictAllocator;
+    ///
+    /// let stack = StackFrameDictAllocator::<&str, usize>::new();
+    /// stack.push("x", 1);
+    /// stack.push("y", 2);
+    /// stack.push("x", 3);
+    ///
+    /// assert_eq!(stack.count_in_frame("x"), 2);
+    /// assert_eq!(stack.count_in_frame("z"), 0);
+    /// ```
+    pub fn count_in_frame(&self, key: impl Into<Key>) -> usize {
+        let key = key.into();
+        let mut walker = unsafe {self.walker()};
+        let mut count = 0;
+
+        while let Some(key_ptr) = walker.next_in_frame() {
+            let (key_compare, _) = unsafe {Self::key_value(key_ptr)};
+
+            if key == *key_compare {
+                count += 1;
+            }
+        }
+
+        return count;
+    }
+
+    /// Finds how many pairs were pushed onto the current Frame after
+    /// the latest pair with the given Key, so the top pair has a position of 0.
+    /// If no pair in the current Frame has this Key, this function will return a None.
+    ///
+    /// # Examples
+    ///
+    /// ```edition2020
+    /// # use stack_frame_allocators::stack_frame_dict_allocator::StackFrameDictAllocator;
+    ///
+    /// let stack = StackFrameDictAllocator::<&str, usize>::new();
+    /// stack.push("a", 1);
+    /// stack.push("b", 2);
+    /// stack.push("a", 3);
+    ///
+    /// assert_eq!(stack.position_in_frame("a"), Some(0));
+    /// assert_eq!(stack.position_in_frame("b"), Some(1));
+    /// assert_eq!(stack.position_in_frame("c"), None);
+    /// ```
+    pub fn position_in_frame(&self, key: impl Into<Key>) -> Option<usize> {
+        let key = key.into();
+        let mut walker = unsafe {self.walker()};
+        let mut position = 0;
+
+        while let Some(key_ptr) = walker.next_in_frame() {
+            let (key_compare, _) = unsafe {Self::key_value(key_ptr)};
+
+            if key == *key_compare {
+                return Some(position);
+            }
+            position += 1;
+        }
+
+        return None;
+    }
+
+    /// Counts how many Values with the given Key in the current Frame
+    /// are shadowed by the latest Value with that Key,
+    /// which is 0 if the Key is only used once, or isn't used at all.
+    ///
+    /// # Examples
+    ///
+    /// ```edition2020
+    /// # use stack_frame_allocators::stack_frame_dict_allocator::StackFrameDictAllocator;
+    ///
+    /// let stack = StackFrameDictAllocator::<&str, usize>::new();
+    /// stack.push("x", 1);
+    /// stack.push("y", 2);
+    /// stack.push("x", 3);
+    ///
+    /// assert_eq!(stack.shadow_count_in_frame("x"), 1);
+    /// assert_eq!(stack.shadow_count_in_frame("y"), 0);
+    /// ```
+    pub fn shadow_count_in_frame(&self, key: impl Into<Key>) -> usize {
+        self.count_in_frame(key).saturating_sub(1)
+    }
+
+    /// Finds the latest Value with the given Key in the current Frame,
+    /// like [get_in_frame](crate::stack_frame_dict_allocator::StackFrameDictAllocator::get_in_frame),
+    /// but with a borrowed form of the Key, like [HashMap::get](std::collections::HashMap::get),
+    /// so owned Keys don't need to be created just to search for them.
+    ///
+    /// # Examples
+    ///
+    /// ```edition2020
+    /// # use stack_frame_allocators::stack_frame_dict_allocator::StackFrameDictAllocator;
+    ///
+    /// let stack = StackFrameDictAllocator::<String, usize>::new();
+    /// stack.push("x".to_string(), 1);
+    ///
+    /// let x = stack.get_in_frame_ref("x").unwrap().get();
+    /// assert_eq!(*x, 1);
+    /// ```
+    pub fn get_in_frame_ref<Q>(&self, key: &Q) -> Option<StackRef<'_, Value>>
+    where
+        Key: Borrow<Q>,
+        Q: ?Sized + Eq + Hash
+    {
+        self.find_key_in_frame(key)
+    }
+
+    fn find_key_in_frame<Q>(&self, key: &Q) -> Option<StackRef<'_, Value>>
+    where
+        Key: Borrow<Q>,
+        Q: ?Sized + Eq + Hash
+    {
+        #[cfg(feature = "std")]
+        if let Some(index) = &self.index {
+            return unsafe {&*index.get()}.get(KeyQuery::new(key)).map(|&value| self.stack_ref(value));
+        }
+
+        self.find_in_frame(|key_compare, _| key == key_compare.borrow())
+    }
+
+    /// Finds the latest Value in the current Frame whose Key Value pair satisfies `predicate`,
+    /// searching from the last push (top) to the first push (bottom).
+    ///
+    /// # Examples
+    ///
+    /// ```edition2020
+    /// # use stack_frame_allocators::stack_frame_dict_allocator::StackFrameDictAllocator;
+    ///
+    /// let stack = StackFrameDictAllocator::<&str, usize>::new();
+    /// stack.push("a", 10);
+    /// stack.push("b", 20);
+    /// stack.push("c", 5);
+    ///
+    /// let big = stack.find_in_frame(|_, value| *value > 8).unwrap().get();
+    /// assert_eq!(*big, 20);
+    /// ```
+    pub fn find_in_frame<P>(&self, mut predicate: P) -> Option<StackRef<'_, Value>>
+    where
+        P: FnMut(&Key, &Value) -> bool
+    {
+        let mut walker = unsafe {self.walker()};
+
+        while let Some(key_ptr) = walker.next_in_frame() {
+            let (key, value) = unsafe {Self::key_value(key_ptr)};
+
+            if predicate(key, value) {
+                return Some(self.stack_ref(unsafe {key_ptr.add(Self::OFFSET_VALUE).cast::<Value>()}));
+            }
+        }
+
         return None;
     }
 
+    /// Gets the entry for the given Key in the current Frame,
+    /// which can be used to grab the latest Value with that Key,
+    /// or push a new Value if there is none.
+    ///
+    /// Like [get_in_frame](crate::stack_frame_dict_allocator::StackFrameDictAllocator::get_in_frame),
+    /// only the current Frame is searched.  This avoids having to search the Frame with
+    /// [get_in_frame](crate::stack_frame_dict_allocator::StackFrameDictAllocator::get_in_frame),
+    /// and then having to search it again after pushing.
+    ///
+    /// # Examples
+    ///
+    /// ```edition2020
+    /// # use stack_frame_allocators::stack_frame_dict_allocator::StackFrameDictAllocator;
+    ///
+    /// let stack = StackFrameDictAllocator::<&str, usize>::new();
+    /// stack.push("a", 1);
+    ///
+    /// //"a" is already in the frame, so it isn't shadowed
+    /// let a = stack.entry("a").or_push(2).get();
+    /// assert_eq!(*a, 1);
+    ///
+    /// let b = stack.entry("b").or_push_with(|| 3).get();
+    /// assert_eq!(*b, 3);
+    /// ```
+    pub fn entry<'a>(&'a self, key: impl Into<Key>) -> Entry<'a, 's, Key, Value, A, Kind> {
+        let key = key.into();
+        let value = self.find_key_in_frame(&key);
+
+        Entry {
+            stack: self,
+            key,
+            value
+        }
+    }
+
+    /// Returns a StackRef to the latest Value with the given Key in the current Frame,
+    /// or pushes the result of `f` with that Key if there is none.
+    ///
+    /// This is a shorthand for
+    /// [entry](crate::stack_frame_dict_allocator::StackFrameDictAllocator::entry)
+    /// followed by [or_push_with](crate::stack_frame_dict_allocator::Entry::or_push_with),
+    /// so the Frame is only searched once, and a Key already in the Frame isn't shadowed.
+    ///
+    /// # Examples
+    ///
+    /// ```edition2020
+    /// # use stack_frame_allocators::stack_frame_dict_allocator::StackFrameDictAllocator;
+    ///
+    /// let stack = StackFrameDictAllocator::<&str, Vec<usize>>::new();
+    ///
+    /// unsafe {stack.get_or_insert_with("a", Vec::new).get_mut().push(1)};
+    /// unsafe {stack.get_or_insert_with("a", Vec::new).get_mut().push(2)};
+    /// assert_eq!(stack["a"], [1, 2]);
+    /// ```
+    pub fn get_or_insert_with<F: FnOnce() -> Value>(
+        &self,
+        key: impl Into<Key>,
+        f: F
+    ) -> StackRef<'_, Value> {
+        self.entry(key).or_push_with(f)
+    }
+
     /// Finds the latest Value with that Key in the entire Stack, returning a StackRef to it.
     ///
     /// Allows you to dynamically grab values pushed into a frame
@@ -726,595 +1843,3632 @@ where
         &'a self, 
         key: impl Into<Key>
     ) -> Option<StackRef<'a, Value>> {
-        let key = key.into();
+        self.get_in_stack_with_depth(key).map(|(value, _)| value)
+    }
 
-        //we can't use the fun built-in library functions like align_offset,
-        //so we do this math ourselves
-        let value_padding = -(
-            -(Self::SIZE_KEY as isize) % 
-            Self::ALIGN_VALUE as isize
-        ) as usize;
-        let next_key_padding = -(
-            -(Self::SIZE_VALUE as isize) % 
-            Self::ALIGN_KEY as isize
-        ) as usize;
+    /// Finds the latest Value with the given Key in the entire Stack,
+    /// like [get_in_stack](crate::stack_frame_dict_allocator::StackFrameDictAllocator::get_in_stack),
+    /// but with a borrowed form of the Key, like [HashMap::get](std::collections::HashMap::get),
+    /// so owned Keys don't need to be created just to search for them.
+    ///
+    /// # Examples
+    ///
+    /// ```edition2020
+    /// # use stack_frame_allocators::stack_frame_dict_allocator::StackFrameDictAllocator;
+    ///
+    /// let stack = StackFrameDictAllocator::<String, usize>::new();
+    /// stack.push("x".to_string(), 1);
+    ///
+    /// stack.new_scope(|stack| {
+    ///     let x = stack.get_in_stack_ref("x").unwrap().get();
+    ///     assert_eq!(*x, 1);
+    /// });
+    /// ```
+    pub fn get_in_stack_ref<Q>(&self, key: &Q) -> Option<StackRef<'_, Value>>
+    where
+        Key: Borrow<Q>,
+        Q: ?Sized + Eq
+    {
+        self.find_in_stack_with_depth(|key_compare, _| key == key_compare.borrow())
+            .map(|(value, _)| value)
+    }
 
-        let key_value_size = Self::SIZE_KEY + value_padding +
-            Self::SIZE_VALUE + next_key_padding;
+    /// Finds the latest Value with that Key in the entire Stack,
+    /// like [get_in_stack](crate::stack_frame_dict_allocator::StackFrameDictAllocator::get_in_stack),
+    /// alongside how many Frames below the current Frame it was found in.
+    ///
+    /// A Value in the current Frame has a depth of 0,
+    /// and a Value in the root Frame has a depth of one less than the amount of Frames.
+    ///
+    /// # Examples
+    ///
+    /// ```edition2020
+    /// # use stack_frame_allocators::stack_frame_dict_allocator::StackFrameDictAllocator;
+    ///
+    /// let stack = StackFrameDictAllocator::<&str, usize>::new();
+    /// stack.push("captured", 1);
+    ///
+    /// stack.new_scope(|stack| {
+    ///     stack.push("local", 2);
+    ///
+    ///     let (_, depth) = stack.get_in_stack_with_depth("local").unwrap();
+    ///     assert_eq!(depth, 0);
+    ///     let (captured, depth) = stack.get_in_stack_with_depth("captured").unwrap();
+    ///     assert_eq!((*captured.get(), depth), (1, 1));
+    /// });
+    /// ```
+    pub fn get_in_stack_with_depth<'a>(
+        &'a self,
+        key: impl Into<Key>
+    ) -> Option<(StackRef<'a, Value>, usize)> {
+        let key = key.into();
 
-        let mut curr_block_tail = unsafe {self.get_block_tail()};
-        let mut bytes_remaining = unsafe {*self.buffer_bytes_used.get()};
+        self.find_in_stack_with_depth(|key_compare, _| key == *key_compare)
+    }
 
-        let mut stack_frame = unsafe {(*self.current_frame.get()).as_ref()};
-        let mut peek_ptr = stack_frame.current_frame_ptr;
-        
-        //for the first scope we're looking at, because it's the newest scope
-        //there should be no headers after the current frame,
-        //so we'll use key alignment
-        let mut just_jumped_block = false;
-        let mut expect_key_value_pair = true;
-        let mut stack_frame_ptr_after = {unsafe {
-            let offset_ptr = (stack_frame as *const StackFrameHeader as *mut u8)
-                .add(Self::SIZE_HEADER);
-            let padding = offset_ptr.align_offset(Self::ALIGN_KEY);
-            offset_ptr.add(padding)
-        }};
+    /// Finds the latest Value in the entire Stack whose Key Value pair satisfies `predicate`,
+    /// searching from the last push (top) to the first push (bottom).
+    ///
+    /// # Examples
+    ///
+    /// ```edition2020
+    /// # use stack_frame_allocators::stack_frame_dict_allocator::StackFrameDictAllocator;
+    ///
+    /// let stack = StackFrameDictAllocator::<&str, usize>::new();
+    /// stack.push("a", 10);
+    ///
+    /// stack.new_scope(|stack| {
+    ///     stack.push("b", 5);
+    ///
+    ///     let big = stack.find_in_stack(|_, value| *value > 8).unwrap().get();
+    ///     assert_eq!(*big, 10);
+    /// });
+    /// ```
+    pub fn find_in_stack<P>(&self, predicate: P) -> Option<StackRef<'_, Value>>
+    where
+        P: FnMut(&Key, &Value) -> bool
+    {
+        self.find_in_stack_with_depth(predicate).map(|(value, _)| value)
+    }
+
+    fn find_in_stack_with_depth<P>(&self, mut predicate: P) -> Option<(StackRef<'_, Value>, usize)>
+    where
+        P: FnMut(&Key, &Value) -> bool
+    {
+        let mut depth = 0;
 
-        //eprintln!("starting search at {:?} until {:?}", peek_ptr, stack_frame_ptr_after);
+        let mut walker = unsafe {self.walker()};
         loop {
-            // eprintln!("peeking at {:?} until {:?} with {} bytes remaining", 
-            //     peek_ptr, stack_frame_ptr_after, bytes_remaining
-            // );
-            if bytes_remaining == 0 {
-                if curr_block_tail.prev_block.is_null() {
-                    unreachable!("{}", concat!(
-                        "the previous block can only be null ",  
-                        "if the block currently being looked at is the first block.  ",  
-                        "In that case, the header logic would've ran first, ", 
-                        "thus this should never be reached"
-                    ))
-                }
-                bytes_remaining = curr_block_tail.prev_block_bytes_used;
-                peek_ptr = curr_block_tail.prev_block;
+            while let Some(key_ptr) = walker.next_in_frame() {
+                let (key, value) = unsafe {Self::key_value(key_ptr)};
 
-                unsafe {
-                    let offset = self.real_size().bytes() - bytes_remaining;
-        
-                    curr_block_tail = peek_ptr
-                        .add(offset)
-                        .cast::<BlockTail>()
-                        .as_mut()
-                        .expect("Error grabbing mutable reference to BlockTail");
+                if predicate(key, value) {
+                    return Some((self.stack_ref(unsafe {key_ptr.add(Self::OFFSET_VALUE)}.cast::<Value>()), depth));
                 }
             }
 
-            if peek_ptr < stack_frame_ptr_after {
-                unreachable!("unexpected operation caused peek_ptr to go past the stack_frame_ptr");
-            } else if peek_ptr == stack_frame_ptr_after {
-                let Some(new_frame) = stack_frame.previous_frame else {
-                    break;
-                };
-
-                stack_frame = new_frame;
-                peek_ptr = stack_frame.current_frame_ptr;
-
-                //this new header could have zero items
-                just_jumped_block = false;
-                expect_key_value_pair = false;
-                stack_frame_ptr_after = unsafe {
-                    let offset_ptr = (stack_frame as *const StackFrameHeader as *mut u8)
-                        .add(Self::SIZE_HEADER);
-                    let padding = offset_ptr.align_offset(Self::ALIGN_HEADER);
-                    offset_ptr.add(padding)
-                };
+            if !walker.next_frame() {
+                return None;
+            }
+            depth += 1;
+        }
+    }
 
-                continue;
-            } else if !expect_key_value_pair || just_jumped_block {
-                just_jumped_block = false;
-                expect_key_value_pair = true;
+    /// Counts how many Values were pushed with the given Key in the entire Stack,
+    /// including Values that are shadowed by later pushes, in this Frame or any later Frame.
+    ///
+    /// # Examples
+    ///
+    /// ```edition2020
+    /// # use stack_frame_allocators::stack_frame_dict_allocator::StackFrameDictAllocator;
+    ///
+    /// let stack = StackFrameDictAllocator::<&str, usize>::new();
+    /// stack.push("x", 1);
+    ///
+    /// stack.new_scope(|stack| {
+    ///     stack.push("x", 2);
+    ///     stack.push("x", 3);
+    ///
+    ///     assert_eq!(stack.count_in_frame("x"), 2);
+    ///     assert_eq!(stack.count_in_stack("x"), 3);
+    /// });
+    /// ```
+    pub fn count_in_stack(&self, key: impl Into<Key>) -> usize {
+        let key = key.into();
+        let mut walker = unsafe {self.walker()};
+        let mut count = 0;
 
-                stack_frame_ptr_after = unsafe {
-                    let offset_ptr = (stack_frame as *const StackFrameHeader as *mut u8)
-                        .add(Self::SIZE_HEADER);
-                    let padding = offset_ptr.align_offset(Self::ALIGN_KEY);
-                    offset_ptr.add(padding)
-                };
-            }
+        while let Some(key_ptr) = walker.next_in_stack() {
+            let (key_compare, _) = unsafe {Self::key_value(key_ptr)};
 
-            unsafe {
-                peek_ptr = peek_ptr.sub(key_value_size);
-                bytes_remaining -= key_value_size;
-                let key_compare = (peek_ptr as *mut Key).as_ref_unchecked();
-                let value = peek_ptr.add(Self::SIZE_KEY + next_key_padding)
-                    .cast::<Value>();
-
-                // eprintln!("comparing key {} with value {} at {:?} to key {}",
-                //     key_compare, value.as_ref().unwrap(), peek_ptr, &key
-                // );
-
-                if key == *key_compare {
-                    return Some(StackRef {
-                        value,
-                        phantom: PhantomData::default()
-                    });
-                }
+            if key == *key_compare {
+                count += 1;
             }
         }
 
-        return None;
+        return count;
     }
 
-    /// prints out the current stack from last push (top) to first push (bottom)
-    /// 
-    /// Includes where headers are.
-    /// 
+    /// Grabs the most recently pushed Key Value pair in the current frame,
+    /// returning None if the current frame is empty.
+    ///
+    /// Pairs pushed onto previous frames are never returned,
+    /// even if the current frame is empty.
+    ///
     /// # Examples
-    /// 
+    ///
     /// ```edition2020
     /// # use stack_frame_allocators::stack_frame_dict_allocator::StackFrameDictAllocator;
-    /// 
+    ///
     /// let stack = StackFrameDictAllocator::<&str, usize>::new();
-    /// 
-    /// stack.push("I", 0);
-    /// stack.push("II", 1);
-    /// stack.push("III", 2);
-    /// 
-    /// //first print
-    /// stack.print();
-    /// 
+    /// assert_eq!(stack.peek(), None);
+    ///
+    /// stack.push("a", 1);
+    /// stack.push("b", 2);
+    /// assert_eq!(stack.peek(), Some((&"b", &2)));
+    ///
     /// stack.new_scope(|stack| {
-    ///     stack.push("a", 3);
-    ///     stack.push("b", 4);
-    /// 
-    ///     //second print
-    ///     stack.print();
-    /// 
-    ///     unsafe { *stack.get_in_frame("b").unwrap().get_mut() = 69; }
-    /// 
-    ///     //third print
-    ///     stack.print();
+    ///     assert_eq!(stack.peek(), None);
+    ///
+    ///     stack.push("c", 3);
+    ///     assert_eq!(stack.peek(), Some((&"c", &3)));
     /// });
-    /// 
-    /// stack.push("IV", 5);
-    /// stack.push("V", 6);
-    /// 
-    /// //fourth print
-    /// stack.print();
-    /// 
-    /// unsafe { *stack.get_in_frame("III").unwrap().get_mut() = 80085; }
-    /// 
-    /// //fifth print
-    /// stack.print();
     /// ```
-    /// 
-    /// Will print out:
-    /// ```text
-    /// First print!
-    /// 
-    /// top of stack
-    ///     Key: "III", Value: 2
-    ///     Key: "II", Value: 1
-    ///     Key: "I", Value: 0
-    /// header
-    /// 
-    /// Second print!
-    /// 
-    /// top of stack
-    ///     Key: "b", Value: 4
-    ///     Key: "a", Value: 3
-    /// header
-    ///     Key: "III", Value: 2
-    ///     Key: "II", Value: 1
-    ///     Key: "I", Value: 0
-    /// header
-    /// 
-    /// Third print!
-    /// 
-    /// top of stack
-    ///     Key: "b", Value: 69
-    ///     Key: "a", Value: 3
-    /// header
-    ///     Key: "III", Value: 2
-    ///     Key: "II", Value: 1
-    ///     Key: "I", Value: 0
-    /// header
-    /// 
-    /// Fourth print!
-    /// 
-    /// top of stack
-    ///     Key: "V", Value: 6
-    ///     Key: "IV", Value: 5
-    ///     Key: "III", Value: 2
-    ///     Key: "II", Value: 1
-    ///     Key: "I", Value: 0
-    /// header
-    /// 
-    /// Fifth print!
-    /// 
-    /// top of stack
-    ///     Key: "V", Value: 6
-    ///     Key: "IV", Value: 5
-    ///     Key: "III", Value: 80085
-    ///     Key: "II", Value: 1
-    ///     Key: "I", Value: 0
-    /// header
+    pub fn peek(&self) -> Option<(&Key, &Value)> {
+        unsafe {
+            let stack_frame = (*self.current_frame.get()).as_ref();
+            let stack_frame_ptr_after = (stack_frame as *const StackFrameHeader as *mut u8)
+                .add(Self::SIZE_HEADER);
+
+            //pushing always moves the current_frame_ptr past the header,
+            //so the frame can only be empty if it sits right after the header
+            if stack_frame.current_frame_ptr == stack_frame_ptr_after {
+                return None;
+            }
+
+            //the top pair always ends at the current_frame_ptr,
+            //even if it was the first pair pushed onto a new block
+            let key_ptr = stack_frame.current_frame_ptr.sub(Self::SIZE_KEY_VALUE);
+            let key = &*key_ptr.cast::<Key>();
+            let value = &*key_ptr.add(Self::OFFSET_VALUE).cast::<Value>();
+
+            Some((key, value))
+        }
+    }
+
+    /// Iterates over every Key Value pair in the entire Stack,
+    /// from the last push (top) to the first push (bottom).
+    ///
+    /// Pairs that are currently being shadowed are included,
+    /// see [iter_visible_stack](crate::stack_frame_dict_allocator::StackFrameDictAllocator::iter_visible_stack)
+    /// to only iterate over the pairs that can be found with
+    /// [get_in_stack](crate::stack_frame_dict_allocator::StackFrameDictAllocator::get_in_stack).
+    ///
+    /// # Examples
+    ///
+    /// ```edition2020
+    /// # use stack_frame_allocators::stack_frame_dict_allocator::StackFrameDictAllocator;
+    ///
+    /// let stack = StackFrameDictAllocator::<&str, usize>::new();
+    /// stack.push("a", 1);
+    /// stack.push("b", 2);
+    ///
+    /// stack.new_scope(|stack| {
+    ///     stack.push("a", 3);
+    ///
+    ///     let pairs: Vec<_> = stack.iter_stack().collect();
+    ///     assert_eq!(pairs, vec![(&"a", &3), (&"b", &2), (&"a", &1)]);
+    /// });
     /// ```
-    pub fn print(&self) where Key: Display, Value: Display {
-        let mut count_blocks = 1;
+    pub fn iter_stack(&self) -> impl Iterator<Item = (&Key, &Value)> {
+        let mut walker = unsafe {self.walker()};
+
+        core::iter::from_fn(move || {
+            walker.next_in_stack().map(|key_ptr| unsafe {Self::key_value(key_ptr)})
+        })
+    }
+
+    /// Iterates over every Key in the current Frame,
+    /// from the last push (top) to the first push (bottom).
+    ///
+    /// Keys that are currently being shadowed are included,
+    /// so the first time a Key is yielded belongs to the Value that
+    /// [get_in_frame](crate::stack_frame_dict_allocator::StackFrameDictAllocator::get_in_frame)
+    /// would find.
+    ///
+    /// # Examples
+    ///
+    /// ```edition2020
+    /// # use stack_frame_allocators::stack_frame_dict_allocator::StackFrameDictAllocator;
+    ///
+    /// let stack = StackFrameDictAllocator::<&str, usize>::new();
+    /// stack.push("outer", 0);
+    ///
+    /// stack.new_scope(|stack| {
+    ///     stack.push("a", 1);
+    ///     stack.push("b", 2);
+    ///     stack.push("a", 3);
+    ///
+    ///     let keys: Vec<_> = stack.keys_in_frame().collect();
+    ///     assert_eq!(keys, vec![&"a", &"b", &"a"]);
+    /// });
+    /// ```
+    pub fn keys_in_frame(&self) -> impl Iterator<Item = &Key> {
+        self.iter_frame_pairs().map(|(key, _)| key)
+    }
+
+    /// Gets the Key of the most recently pushed pair in the current Frame,
+    /// returning None if the current frame is empty.
+    ///
+    /// # Examples
+    ///
+    /// ```edition2020
+    /// # use stack_frame_allocators::stack_frame_dict_allocator::StackFrameDictAllocator;
+    ///
+    /// let stack = StackFrameDictAllocator::<&str, usize>::new();
+    /// stack.push("a", 1);
+    /// stack.push("b", 2);
+    /// assert_eq!(stack.last_key_in_frame(), Some(&"b"));
+    ///
+    /// stack.new_scope(|stack| {
+    ///     assert_eq!(stack.last_key_in_frame(), None);
+    /// });
+    /// ```
+    pub fn last_key_in_frame(&self) -> Option<&Key> {
+        self.peek().map(|(key, _)| key)
+    }
+
+    /// Gets the Key of the first pair pushed onto the current Frame,
+    /// returning None if the current frame is empty.
+    ///
+    /// The whole frame is walked to find it.
+    ///
+    /// # Examples
+    ///
+    /// ```edition2020
+    /// # use stack_frame_allocators::stack_frame_dict_allocator::StackFrameDictAllocator;
+    ///
+    /// let stack = StackFrameDictAllocator::<&str, usize>::new();
+    /// stack.push("a", 1);
+    /// stack.push("b", 2);
+    /// assert_eq!(stack.first_key_in_frame(), Some(&"a"));
+    ///
+    /// stack.new_scope(|stack| {
+    ///     assert_eq!(stack.first_key_in_frame(), None);
+    /// });
+    /// ```
+    pub fn first_key_in_frame(&self) -> Option<&Key> {
+        self.keys_in_frame().last()
+    }
+
+    /// Iterates over every Value in the current Frame,
+    /// from the last push (top) to the first push (bottom).
+    ///
+    /// Values that are currently being shadowed are included.
+    ///
+    /// # Examples
+    ///
+    /// ```edition2020
+    /// # use stack_frame_allocators::stack_frame_dict_allocator::StackFrameDictAllocator;
+    ///
+    /// let stack = StackFrameDictAllocator::<&str, usize>::new();
+    /// stack.push("outer", 0);
+    ///
+    /// stack.new_scope(|stack| {
+    ///     stack.push("a", 1);
+    ///     stack.push("b", 2);
+    ///     stack.push("a", 3);
+    ///
+    ///     let values: Vec<_> = stack.values_in_frame().collect();
+    ///     assert_eq!(values, vec![&3, &2, &1]);
+    /// });
+    /// ```
+    pub fn values_in_frame(&self) -> impl Iterator<Item = &Value> {
+        self.iter_frame_pairs().map(|(_, value)| value)
+    }
+
+    /// Clones every Key Value pair in the current Frame into a Vec,
+    /// from the last push (top) to the first push (bottom).
+    ///
+    /// Pairs that are currently being shadowed are included.
+    /// The Vec doesn't borrow the StackFrameDictAllocator,
+    /// so it can be handed out after the Frame is popped.
+    ///
+    /// # Examples
+    ///
+    /// ```edition2020
+    /// # use stack_frame_allocators::stack_frame_dict_allocator::StackFrameDictAllocator;
+    ///
+    /// let stack = StackFrameDictAllocator::<&str, usize>::new();
+    ///
+    /// let pairs = stack.new_scope(|stack| {
+    ///     stack.push("a", 1);
+    ///     stack.push("b", 2);
+    ///     stack.to_vec()
+    /// });
+    ///
+    /// assert_eq!(pairs, vec![("b", 2), ("a", 1)]);
+    /// ```
+    pub fn to_vec(&self) -> Vec<(Key, Value)> where Key: Clone, Value: Clone {
+        self.iter_frame_pairs()
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect()
+    }
+
+    /// Runs `body` in a new Frame, like
+    /// [new_scope](crate::stack_frame_dict_allocator::StackFrameDictAllocator::new_scope),
+    /// returning what `body` returns alongside a clone of every Key Value pair
+    /// left in the new Frame, from the last push (top) to the first push (bottom),
+    /// the same as [to_vec](crate::stack_frame_dict_allocator::StackFrameDictAllocator::to_vec).
+    ///
+    /// The new Frame and its pairs are popped before they're handed back.
+    ///
+    /// # Examples
+    ///
+    /// ```edition2020
+    /// # use stack_frame_allocators::stack_frame_dict_allocator::StackFrameDictAllocator;
+    ///
+    /// let stack = StackFrameDictAllocator::<&str, usize>::new();
+    ///
+    /// let (sum, pairs) = stack.frame_scoped(|stack| {
+    ///     stack.push("a", 1);
+    ///     stack.push("b", 2);
+    ///     3
+    /// });
+    ///
+    /// assert_eq!(sum, 3);
+    /// assert_eq!(pairs, vec![("b", 2), ("a", 1)]);
+    /// ```
+    pub fn frame_scoped<'n, R, F>(&self, body: F) -> (R, Vec<(Key, Value)>)
+    where
+        's : 'n,
+        Key : 'n + Clone,
+        Value : Clone,
+        A : Clone,
+        F : FnOnce(&StackFrameDictAllocator<'n, Key, Value, A, Child>) -> R
+    {
+        self.new_scope(|stack| {
+            let result = body(&stack);
+            (result, stack.to_vec())
+        })
+    }
+
+    /// Iterates over every Key Value pair in the current Frame,
+    /// from the first push (bottom) to the last push (top).
+    ///
+    /// Frames can only be walked from the top down,
+    /// so the position of every pair in the Frame is collected into a Vec first.
+    /// Pairs that are currently being shadowed are included.
+    ///
+    /// # Examples
+    ///
+    /// ```edition2020
+    /// # use stack_frame_allocators::stack_frame_dict_allocator::StackFrameDictAllocator;
+    ///
+    /// let stack = StackFrameDictAllocator::<&str, usize>::new();
+    /// stack.push("a", 1);
+    /// stack.push("b", 2);
+    /// stack.push("a", 3);
+    ///
+    /// let pairs: Vec<_> = stack.iter_frame_rev().collect();
+    /// assert_eq!(pairs, vec![(&"a", &1), (&"b", &2), (&"a", &3)]);
+    /// ```
+    pub fn iter_frame_rev(&self) -> impl Iterator<Item = (&Key, &Value)> {
+        let mut walker = unsafe {self.walker()};
+        let positions: Vec<*mut u8> = core::iter::from_fn(|| walker.next_in_frame()).collect();
+
+        positions.into_iter().rev().map(|key_ptr| unsafe {Self::key_value(key_ptr)})
+    }
+
+    fn iter_frame_pairs(&self) -> impl Iterator<Item = (&Key, &Value)> {
+        let mut walker = unsafe {self.walker()};
+
+        core::iter::from_fn(move || {
+            walker.next_in_frame().map(|key_ptr| unsafe {Self::key_value(key_ptr)})
+        })
+    }
+
+    /// Iterates over the currently visible Key Value pair of every Key in the entire Stack,
+    /// from the last push (top) to the first push (bottom).
+    ///
+    /// Every Key is only yielded once, alongside the Value that
+    /// [get_in_stack](crate::stack_frame_dict_allocator::StackFrameDictAllocator::get_in_stack)
+    /// would find, so pairs that are currently being shadowed are skipped.
+    /// Keeping track of which Keys have already been seen allocates a HashSet
+    /// as the iterator is consumed.
+    ///
+    /// # Examples
+    ///
+    /// ```edition2020
+    /// # use stack_frame_allocators::stack_frame_dict_allocator::StackFrameDictAllocator;
+    ///
+    /// let stack = StackFrameDictAllocator::<&str, usize>::new();
+    /// stack.push("a", 1);
+    /// stack.push("b", 2);
+    ///
+    /// stack.new_scope(|stack| {
+    ///     stack.push("a", 3);
+    ///
+    ///     let pairs: Vec<_> = stack.iter_visible_stack().collect();
+    ///     assert_eq!(pairs, vec![(&"a", &3), (&"b", &2)]);
+    /// });
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn iter_visible_stack(&self) -> impl Iterator<Item = (&Key, &Value)> {
+        let mut seen = HashSet::new();
+
+        self.iter_stack().filter(move |(key, _)| seen.insert(*key))
+    }
+
+    /// Clones the currently visible Key Value pair of every Key in the entire Stack into a HashMap,
+    /// so every Key maps to the Value that
+    /// [get_in_stack](crate::stack_frame_dict_allocator::StackFrameDictAllocator::get_in_stack)
+    /// would find.
+    ///
+    /// # Examples
+    ///
+    /// ```edition2020
+    /// # use stack_frame_allocators::stack_frame_dict_allocator::StackFrameDictAllocator;
+    ///
+    /// let stack = StackFrameDictAllocator::<&str, usize>::new();
+    /// stack.push("a", 1);
+    /// stack.push("b", 2);
+    ///
+    /// stack.new_scope(|stack| {
+    ///     stack.push("a", 3);
+    ///
+    ///     let env = stack.to_hashmap();
+    ///     assert_eq!(env.len(), 2);
+    ///     assert_eq!(env["a"], 3);
+    ///     assert_eq!(env["b"], 2);
+    /// });
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn to_hashmap(&self) -> HashMap<Key, Value> where Key: Clone + Eq + Hash, Value: Clone {
+        let mut map = HashMap::new();
+
+        //the stack is walked from the top,
+        //so the first pair seen for a Key is the one that shadows the rest
+        for (key, value) in self.iter_stack() {
+            if !map.contains_key(key) {
+                map.insert(key.clone(), value.clone());
+            }
+        }
+
+        return map;
+    }
+
+    /// Clones the currently visible Key Value pair of every Key in the entire Stack into a BTreeMap,
+    /// the same as [to_hashmap](crate::stack_frame_dict_allocator::StackFrameDictAllocator::to_hashmap),
+    /// so the Keys can be iterated in order.
+    ///
+    /// # Examples
+    ///
+    /// ```edition2020
+    /// # use stack_frame_allocators::stack_frame_dict_allocator::StackFrameDictAllocator;
+    ///
+    /// let stack = StackFrameDictAllocator::<&str, usize>::new();
+    /// stack.push("b", 1);
+    /// stack.push("a", 2);
+    ///
+    /// stack.new_scope(|stack| {
+    ///     stack.push("b", 3);
+    ///
+    ///     let env: Vec<_> = stack.to_btreemap().into_iter().collect();
+    ///     assert_eq!(env, [("a", 2), ("b", 3)]);
+    /// });
+    /// ```
+    pub fn to_btreemap(&self) -> BTreeMap<Key, Value> where Key: Clone + Ord, Value: Clone {
+        let mut map = BTreeMap::new();
+
+        //the stack is walked from the top,
+        //so the first pair seen for a Key is the one that shadows the rest
+        for (key, value) in self.iter_stack() {
+            if !map.contains_key(key) {
+                map.insert(key.clone(), value.clone());
+            }
+        }
+
+        return map;
+    }
+
+    /// Writes every visible Key Value pair in the current Frame to `w`,
+    /// one `key=value` line per Key, from the last push (top) to the first push (bottom).
+    ///
+    /// Unlike [print](crate::stack_frame_dict_allocator::StackFrameDictAllocator::print),
+    /// this doesn't write any headers or indentation, and shadowed pairs are skipped,
+    /// so the output is easy to parse back.
+    ///
+    /// # Examples
+    ///
+    /// ```edition2020
+    /// # use stack_frame_allocators::stack_frame_dict_allocator::StackFrameDictAllocator;
+    ///
+    /// let stack = StackFrameDictAllocator::<&str, usize>::new();
+    /// stack.push("a", 1);
+    /// stack.push("b", 2);
+    /// stack.push("a", 3);
+    ///
+    /// let mut out = Vec::new();
+    /// stack.export_pairs_to(&mut out).unwrap();
+    /// assert_eq!(String::from_utf8(out).unwrap(), "a=3\nb=2\n");
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn export_pairs_to<W: Write>(&self, w: &mut W) -> io::Result<()>
+    where
+        Key: Display,
+        Value: Display
+    {
+        let mut walker = unsafe {self.walker()};
+        let mut seen = HashSet::new();
+
+        while let Some(key_ptr) = walker.next_in_frame() {
+            let (key, value) = unsafe {Self::key_value(key_ptr)};
+
+            if seen.insert(key) {
+                writeln!(w, "{}={}", key, value)?;
+            }
+        }
+
+        return Ok(());
+    }
+
+    /// Moves every visible Key Value pair in the current Frame into a BTreeMap,
+    /// consuming the StackFrameDictAllocator.
+    ///
+    /// If a Key was pushed multiple times, only the latest Value is kept,
+    /// and shadowed pairs are dropped.
+    ///
+    /// # Examples
+    ///
+    /// ```edition2020
+    /// # use stack_frame_allocators::stack_frame_dict_allocator::StackFrameDictAllocator;
+    ///
+    /// let stack = StackFrameDictAllocator::<&str, usize>::new();
+    /// stack.push("b", 1);
+    /// stack.push("a", 2);
+    /// stack.push("b", 3);
+    ///
+    /// let map = stack.frame_to_btreemap();
+    /// assert_eq!(map.into_iter().collect::<Vec<_>>(), vec![("a", 2), ("b", 3)]);
+    /// ```
+    pub fn frame_to_btreemap(self) -> BTreeMap<Key, Value> where Key: Ord {
+        let mut map = BTreeMap::new();
+
+        //the frame is walked from the top,
+        //so the first time a key is seen is its latest push
+        unsafe {
+            self.drain_frame(|key, value| {
+                map.entry(key).or_insert(value);
+            });
+        }
+
+        return map;
+    }
+
+    /// Moves every Key Value pair in `other`'s current Frame onto this allocator's current Frame,
+    /// in the same order they were pushed onto `other`.
+    ///
+    /// The pairs are moved rather than copied byte for byte,
+    /// so `other` can use a different allocator or block size,
+    /// and `other` never drops the pairs that were moved out of it.
+    ///
+    /// # Examples
+    ///
+    /// ```edition2020
+    /// # use stack_frame_allocators::stack_frame_dict_allocator::StackFrameDictAllocator;
+    ///
+    /// let stack = StackFrameDictAllocator::<&str, usize>::new();
+    /// stack.push("a", 1);
+    ///
+    /// let results = StackFrameDictAllocator::<&str, usize>::new();
+    /// results.push("b", 2);
+    /// results.push("a", 3);
+    ///
+    /// stack.merge(results);
+    /// assert_eq!(stack["a"], 3);
+    /// assert_eq!(stack["b"], 2);
+    /// ```
+    pub fn merge<B: Allocator, OtherKind>(&self, other: StackFrameDictAllocator<'_, Key, Value, B, OtherKind>) {
+        let mut pairs = Vec::new();
+        //other is consumed, so there can't be any StackRefs into its frame
+        unsafe {
+            other.drain_frame(|key, value| pairs.push((key, value)));
+        }
+        //other may be a frame on top of this allocator's frame,
+        //so it has to be gone before anything is pushed
+        drop(other);
+
+        //pairs are drained from the top, so they're pushed back in reverse
+        for (key, value) in pairs.into_iter().rev() {
+            self.push(key, value);
+        }
+    }
+
+    /// Moves every Key Value pair out of the current Frame,
+    /// from the last push (top) to the first push (bottom),
+    /// leaving the current Frame empty.
+    ///
+    /// # Safety
+    ///
+    /// There must be no StackRefs into the current Frame,
+    /// since the pairs they point to are moved out.
+    unsafe fn drain_frame(&self, mut f: impl FnMut(Key, Value)) {
+        let mut walker = self.walker();
+
+        while let Some(key_ptr) = walker.next_in_frame() {
+            let key = key_ptr.cast::<Key>().read();
+            let value = key_ptr.add(Self::OFFSET_VALUE).cast::<Value>().read();
+
+            f(key, value);
+        }
+
+        //the walker stops in the header's block,
+        //so we can figure out how many bytes of it the empty frame uses
+        let (peek_ptr, bytes_remaining) = walker.position();
+        let block_size = StackSize(walker.block_size());
+        let stack_frame = (*self.current_frame.get()).as_ptr();
+        let stack_frame_ptr_after = stack_frame.cast::<u8>().add(Self::SIZE_HEADER);
+
+        (*stack_frame).current_frame_ptr = stack_frame_ptr_after;
+        *self.buffer_bytes_used.get() =
+            bytes_remaining - (peek_ptr as usize - stack_frame_ptr_after as usize);
+        *self.block_size.get() = block_size;
+
+        self.clear_index();
+    }
+
+    /// Gets the block after the block `top` lives in, alongside its size,
+    /// allocating it according to the growth policy if it doesn't exist yet.
+    ///
+    /// The tail of the next block is pointed back to `top`.
+    unsafe fn try_link_next_block(
+        &self,
+        block_tail: &mut BlockTail,
+        top: *mut u8,
+        bytes_used: usize,
+        block_size: StackSize
+    ) -> Result<(*mut u8, StackSize), AllocError> {
+        //if there is no next block, create one
+        if block_tail.next_block.is_null() {
+            let next_block_size = self.growth.next_block_size(block_size);
+            let allocated_block = self.try_allocate_block(next_block_size)?;
+            if let Some(on_block_alloc) = &self.on_block_alloc {
+                on_block_alloc(next_block_size.bytes());
+            }
+
+            (Self::block_tail_at(allocated_block, next_block_size) as *mut BlockTail).write(BlockTail {
+                prev_block: top,
+                prev_block_bytes_used: bytes_used,
+                prev_block_size: block_size.bytes(),
+                next_block: core::ptr::null_mut(),
+                next_block_size: 0
+            });
+
+            block_tail.next_block = allocated_block;
+            block_tail.next_block_size = next_block_size.bytes();
+        } else {
+            //the next block was left behind by a frame that has since been popped,
+            //so its tail still points back into that frame
+            let next_block_tail = Self::block_tail_at(
+                block_tail.next_block,
+                StackSize(block_tail.next_block_size)
+            );
+            next_block_tail.prev_block = top;
+            next_block_tail.prev_block_bytes_used = bytes_used;
+        }
+
+        return Ok((block_tail.next_block, StackSize(block_tail.next_block_size)));
+    }
+
+    unsafe fn link_next_block(
+        &self,
+        block_tail: &mut BlockTail,
+        top: *mut u8,
+        bytes_used: usize,
+        block_size: StackSize
+    ) -> (*mut u8, StackSize) {
+        self.try_link_next_block(block_tail, top, bytes_used, block_size)
+            .unwrap_or_else(|_| handle_alloc_error(
+                Self::block_layout(self.growth.next_block_size(block_size))
+            ))
+    }
+
+    /// Panics with `error`, or reports it to the global alloc error handler
+    /// if a new block couldn't be allocated.
+    fn push_failed(&self, error: PushError) -> ! {
+        match error {
+            PushError::Alloc(_) => handle_alloc_error(self.next_block_layout()),
+            PushError::TooLarge { .. } => panic!("{}", error)
+        }
+    }
+
+    fn next_block_layout(&self) -> Layout {
+        Self::block_layout(self.growth.next_block_size(unsafe {*self.block_size.get()}))
+    }
+
+    fn block_layout(size: StackSize) -> Layout {
+        Layout::from_size_align(size.bytes(), Self::ALIGN_BLOCK)
+            .expect("block size should never exceed isize::MAX")
+    }
+
+    fn try_allocate_block(&self, size: StackSize) -> Result<*mut u8, AllocError> {
+        self.allocator
+            .allocate(Self::block_layout(size))
+            .map(|block| block.cast::<u8>().as_ptr())
+            .map_err(|_| AllocError)
+    }
+
+    unsafe fn deallocate_block(&self, block: *mut u8, size: StackSize) {
+        self.allocator.deallocate(NonNull::new_unchecked(block), Self::block_layout(size));
+    }
+
+    unsafe fn block_tail_at<'b>(block: *mut u8, size: StackSize) -> &'b mut BlockTail {
+        block.add(size.bytes() - Self::SIZE_TAIL)
+            .cast::<BlockTail>()
+            .as_mut()
+            .expect("Error grabbing mutable reference to BlockTail")
+    }
+
+    unsafe fn walker(&self) -> StackWalker<'_> {
+        StackWalker::new(
+            (*self.current_frame.get()).as_ref(),
+            *self.buffer_bytes_used.get(),
+            self.real_size().bytes(),
+            Self::SIZE_KEY_VALUE,
+            Self::ALIGN_KEY_VALUE
+        )
+    }
+
+    fn stack_ref(&self, value: *mut Value) -> StackRef<'_, Value> {
+        StackRef {
+            value,
+            #[cfg(feature = "borrow_check")]
+            borrow: BorrowFlag::new(self.borrows.as_deref(), value.cast()),
+            phantom: PhantomData
+        }
+    }
+
+    unsafe fn key_value<'a>(key_ptr: *mut u8) -> (&'a Key, &'a Value) {
+        let key = &*key_ptr.cast::<Key>();
+        let value = &*key_ptr.add(Self::OFFSET_VALUE).cast::<Value>();
+
+        (key, value)
+    }
+
+    /// Gathers the Key Value pairs of every Frame in the entire Stack,
+    /// from the root Frame to the current Frame,
+    /// with every Frame's pairs from the first push (bottom) to the last push (top).
+    fn frames(&self) -> Vec<Vec<(&Key, &Value)>> {
+        //the stack can only be walked from the top,
+        //so everything is gathered first, and reversed after
+        let mut frames = Vec::new();
+        let mut walker = unsafe {self.walker()};
+        loop {
+            let mut frame = Vec::new();
+            while let Some(key_ptr) = walker.next_in_frame() {
+                frame.push(unsafe {Self::key_value(key_ptr)});
+            }
+            frame.reverse();
+            frames.push(frame);
+
+            if !walker.next_frame() {
+                break;
+            }
+        }
+        frames.reverse();
+
+        return frames;
+    }
+
+    /// prints out the current stack from last push (top) to first push (bottom)
+    /// 
+    /// Includes where headers are.
+    /// 
+    /// # Examples
+    /// 
+    /// ```edition2020
+    /// # use stack_frame_allocators::stack_frame_dict_allocator::StackFrameDictAllocator;
+    /// 
+    /// let stack = StackFrameDictAllocator::<&str, usize>::new();
+    /// 
+    /// stack.push("I", 0);
+    /// stack.push("II", 1);
+    /// stack.push("III", 2);
+    /// 
+    /// //first print
+    /// stack.print();
+    /// 
+    /// stack.new_scope(|stack| {
+    ///     stack.push("a", 3);
+    ///     stack.push("b", 4);
+    /// 
+    ///     //second print
+    ///     stack.print();
+    /// 
+    ///     unsafe { *stack.get_in_frame("b").unwrap().get_mut() = 69; }
+    /// 
+    ///     //third print
+    ///     stack.print();
+    /// });
+    /// 
+    /// stack.push("IV", 5);
+    /// stack.push("V", 6);
+    /// 
+    /// //fourth print
+    /// stack.print();
+    /// 
+    /// unsafe { *stack.get_in_frame("III").unwrap().get_mut() = 80085; }
+    /// 
+    /// //fifth print
+    /// stack.print();
+    /// ```
+    /// 
+    /// Will print out:
+    /// ```text
+    /// First print!
+    /// 
+    /// top of stack
+    ///     Key: "III", Value: 2
+    ///     Key: "II", Value: 1
+    ///     Key: "I", Value: 0
+    /// header
+    /// 
+    /// Second print!
+    /// 
+    /// top of stack
+    ///     Key: "b", Value: 4
+    ///     Key: "a", Value: 3
+    /// header
+    ///     Key: "III", Value: 2
+    ///     Key: "II", Value: 1
+    ///     Key: "I", Value: 0
+    /// header
+    /// 
+    /// Third print!
+    /// 
+    /// top of stack
+    ///     Key: "b", Value: 69
+    ///     Key: "a", Value: 3
+    /// header
+    ///     Key: "III", Value: 2
+    ///     Key: "II", Value: 1
+    ///     Key: "I", Value: 0
+    /// header
+    /// 
+    /// Fourth print!
+    /// 
+    /// top of stack
+    ///     Key: "V", Value: 6
+    ///     Key: "IV", Value: 5
+    ///     Key: "III", Value: 2
+    ///     Key: "II", Value: 1
+    ///     Key: "I", Value: 0
+    /// header
+    /// 
+    /// Fifth print!
+    /// 
+    /// top of stack
+    ///     Key: "V", Value: 6
+    ///     Key: "IV", Value: 5
+    ///     Key: "III", Value: 80085
+    ///     Key: "II", Value: 1
+    ///     Key: "I", Value: 0
+    /// header
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn print(&self) where Key: Display, Value: Display {
+        self.print_to(&mut io::stdout().lock()).expect("failed printing to stdout");
+    }
+
+    /// Writes out the current stack from last push (top) to first push (bottom)
+    /// to `w`, the same way
+    /// [print](crate::stack_frame_dict_allocator::StackFrameDictAllocator::print) does.
+    ///
+    /// # Examples
+    ///
+    /// ```edition2020
+    /// # use stack_frame_allocators::stack_frame_dict_allocator::StackFrameDictAllocator;
+    ///
+    /// let stack = StackFrameDictAllocator::<&str, usize>::new();
+    /// stack.push("a", 1);
+    ///
+    /// let mut out = Vec::new();
+    /// stack.print_to(&mut out).unwrap();
+    /// assert!(String::from_utf8(out).unwrap().starts_with("top of stack\n\tKey: a, Value: 1\nheader\n"));
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn print_to(&self, w: &mut impl Write) -> io::Result<()> where Key: Display, Value: Display {
+        writeln!(w, "{}\n", fmt::from_fn(|f| self.fmt_stack(f, false, |key, value, f| {
+            write!(f, "Key: {}, Value: {}", key, value)
+        })))
+    }
+
+    /// Prints out the current stack from last push (top) to first push (bottom),
+    /// the same way [print](crate::stack_frame_dict_allocator::StackFrameDictAllocator::print) does,
+    /// but with every Frame indented one level deeper than the Frame before it.
+    ///
+    /// # Examples
+    ///
+    /// ```edition2020
+    /// # use stack_frame_allocators::stack_frame_dict_allocator::StackFrameDictAllocator;
+    ///
+    /// let stack = StackFrameDictAllocator::<&str, usize>::new();
+    /// stack.push("I", 0);
+    ///
+    /// stack.new_scope(|stack| {
+    ///     stack.push("a", 3);
+    ///
+    ///     stack.new_scope(|stack| {
+    ///         stack.push("b", 69);
+    ///         stack.print_indented();
+    ///     });
+    /// });
+    /// ```
+    ///
+    /// Will print out:
+    /// ```text
+    /// top of stack
+    ///             Key: b, Value: 69
+    ///         header
+    ///         Key: a, Value: 3
+    ///     header
+    ///     Key: I, Value: 0
+    /// header
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn print_indented(&self) where Key: Display, Value: Display {
+        self.print_indented_to(&mut io::stdout().lock()).expect("failed printing to stdout");
+    }
+
+    /// Writes out the current stack from last push (top) to first push (bottom)
+    /// to `w`, the same way
+    /// [print_indented](crate::stack_frame_dict_allocator::StackFrameDictAllocator::print_indented) does.
+    ///
+    /// # Examples
+    ///
+    /// ```edition2020
+    /// # use stack_frame_allocators::stack_frame_dict_allocator::StackFrameDictAllocator;
+    ///
+    /// let stack = StackFrameDictAllocator::<&str, usize>::new();
+    /// stack.push("a", 1);
+    ///
+    /// stack.new_scope(|stack| {
+    ///     stack.push("b", 2);
+    ///
+    ///     let mut out = Vec::new();
+    ///     stack.print_indented_to(&mut out).unwrap();
+    ///     assert!(String::from_utf8(out).unwrap().starts_with(
+    ///         "top of stack\n\t\tKey: b, Value: 2\n\theader\n\tKey: a, Value: 1\nheader\n"
+    ///     ));
+    /// });
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn print_indented_to(&self, w: &mut impl Write) -> io::Result<()> where Key: Display, Value: Display {
+        writeln!(w, "{}\n", fmt::from_fn(|f| self.fmt_stack(f, true, |key, value, f| {
+            write!(f, "Key: {}, Value: {}", key, value)
+        })))
+    }
+
+    /// Writes the current stack from last push (top) to first push (bottom),
+    /// including where headers are, and how many blocks the stack spans.
+    ///
+    /// If `indent` is set, every Frame is indented by its depth,
+    /// otherwise every Frame is indented as if it were the root Frame.
+    fn fmt_stack(
+        &self,
+        f: &mut Formatter<'_>,
+        indent: bool,
+        fmt_pair: fn(&Key, &Value, &mut Formatter<'_>) -> fmt::Result
+    ) -> fmt::Result {
+        let mut walker = unsafe {self.walker()};
+        let mut depth = if indent { self.depth() } else { 1 };
+
+        writeln!(f, "top of stack")?;
+
+        loop {
+            while let Some(key_ptr) = walker.next_in_frame() {
+                let (key, value) = unsafe {Self::key_value(key_ptr)};
+
+                write!(f, "{}", "\t".repeat(depth))?;
+                fmt_pair(key, value, f)?;
+                writeln!(f)?;
+            }
+
+            writeln!(f, "{}header", "\t".repeat(depth - 1))?;
+
+            if !walker.next_frame() {
+                break;
+            }
+            if indent {
+                depth -= 1;
+            }
+        }
+
+        match self.growth {
+            GrowthPolicy::Fixed => write!(f, "\n{} block(s) of size {} bytes have been allocated.",
+                walker.blocks_visited(),
+                self.size.bytes()
+            ),
+            GrowthPolicy::Geometric { .. } => write!(f, "\n{} block(s) totalling {} bytes have been allocated.",
+                walker.blocks_visited(),
+                walker.block_bytes_visited()
+            )
+        }
+    }
+
+    //TODO add allocated_blocks(&self) -> usize and using_blocks(&self) -> usize functions
+}
+
+/// Creates a new StackFrameDictAllocator, the same as
+/// [new](crate::stack_frame_dict_allocator::StackFrameDictAllocator::new).
+impl<'s, Key: Eq + Hash, Value> Default for StackFrameDictAllocator<'s, Key, Value> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Creates a new StackFrameDictAllocator with every Key Value pair pushed onto the root Frame,
+/// in iteration order.
+///
+/// If the iterator knows exactly how many pairs it yields,
+/// the first block is made large enough to fit all of them, the same as
+/// [with_capacity](crate::stack_frame_dict_allocator::StackFrameDictAllocator::with_capacity).
+/// Otherwise blocks have the default size.
+///
+/// # Examples
+///
+/// ```edition2020
+/// # use stack_frame_allocators::stack_frame_dict_allocator::StackFrameDictAllocator;
+///
+/// let stack: StackFrameDictAllocator<&str, usize> = [("a", 1), ("b", 2)].into_iter().collect();
+/// assert_eq!(stack["a"], 1);
+/// assert_eq!(stack["b"], 2);
+/// ```
+impl<'s, Key: Eq + Hash, Value> FromIterator<(Key, Value)> for StackFrameDictAllocator<'s, Key, Value> {
+    fn from_iter<I: IntoIterator<Item = (Key, Value)>>(iter: I) -> Self {
+        let iter = iter.into_iter();
+        let stack = match iter.size_hint() {
+            (lower, Some(upper)) if lower == upper => Self::with_capacity(lower),
+            _ => Self::new()
+        };
+
+        for (key, value) in iter {
+            stack.push(key, value);
+        }
+
+        stack
+    }
+}
+
+/// Pushes every Key Value pair into the current Frame, in iteration order.
+///
+/// Implemented for a reference, since pushing only needs a shared reference,
+/// so `(&stack).extend(pairs)` works while StackRefs into the stack are held.
+///
+/// # Examples
+///
+/// ```edition2020
+/// # use stack_frame_allocators::stack_frame_dict_allocator::StackFrameDictAllocator;
+///
+/// let stack = StackFrameDictAllocator::<&str, usize>::new();
+/// (&stack).extend([("a", 1), ("b", 2), ("a", 3)]);
+/// assert_eq!(stack["a"], 3);
+/// assert_eq!(stack["b"], 2);
+/// ```
+impl<'a, 's, Key, Value, A: Allocator, Kind> Extend<(Key, Value)> for &'a StackFrameDictAllocator<'s, Key, Value, A, Kind>
+where
+    Key: Eq + Hash
+{
+    fn extend<I: IntoIterator<Item = (Key, Value)>>(&mut self, iter: I) {
+        for (key, value) in iter {
+            self.push(key, value);
+        }
+    }
+}
+
+impl<'s, Key, Value, A: Allocator, Kind> Debug for StackFrameDictAllocator<'s, Key, Value, A, Kind>
+where
+    Key: Eq + Hash + Debug,
+    Value: Debug
+{
+    /// Formats the current stack the same way
+    /// [print](crate::stack_frame_dict_allocator::StackFrameDictAllocator::print) does,
+    /// using each Key and Value's Debug implementation.
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        self.fmt_stack(f, false, |key, value, f| write!(f, "Key: {:?}, Value: {:?}", key, value))
+    }
+}
+
+/// Two allocators are equal when every Key maps to an equal Value in both,
+/// as found by [get_in_stack](crate::stack_frame_dict_allocator::StackFrameDictAllocator::get_in_stack).
+///
+/// Only what's currently visible is compared, so how the pairs are split up into frames,
+/// the order they were pushed in, and any pairs being shadowed are ignored.
+///
+/// # Examples
+///
+/// ```edition2020
+/// # use stack_frame_allocators::stack_frame_dict_allocator::StackFrameDictAllocator;
+///
+/// let a = StackFrameDictAllocator::<&str, usize>::new();
+/// a.push("x", 1);
+/// a.push("y", 2);
+///
+/// let b = StackFrameDictAllocator::<&str, usize>::new();
+/// b.push("y", 0);
+/// b.push("x", 1);
+///
+/// b.new_scope(|b| {
+///     b.push("y", 2);
+///     assert_eq!(a, b);
+/// });
+/// assert_ne!(a, b);
+/// ```
+#[cfg(feature = "std")]
+impl<'s, 't, Key, Value, A: Allocator, B: Allocator, Kind, OtherKind> PartialEq<StackFrameDictAllocator<'t, Key, Value, B, OtherKind>>
+for StackFrameDictAllocator<'s, Key, Value, A, Kind>
+where
+    Key: Eq + Hash,
+    Value: PartialEq
+{
+    fn eq(&self, other: &StackFrameDictAllocator<'t, Key, Value, B, OtherKind>) -> bool {
+        let visible: HashMap<&Key, &Value> = self.iter_visible_stack().collect();
+        let other_visible: HashMap<&Key, &Value> = other.iter_visible_stack().collect();
+
+        return visible == other_visible;
+    }
+}
+
+#[cfg(feature = "std")]
+impl<'s, Key, Value, A: Allocator, Kind> Eq for StackFrameDictAllocator<'s, Key, Value, A, Kind>
+where
+    Key: Eq + Hash,
+    Value: Eq
+{}
+
+//SAFETY: the blocks, and every pair in them, are owned by the allocator,
+//the raw pointers in it only ever point into those blocks or to the index,
+//so moving an allocator along with its Keys and Values to another thread
+//moves everything it can reach.  StackRefs borrow the allocator,
+//so none can be left behind on the previous thread.
+//Frames made with new_frame or new_scope push onto their parent's blocks, and share its borrow table when checked,
+//so they're Child frames, which hold raw pointers and stay !Send.
+//Every Child frame borrows the allocator it was made from,
+//so a Root allocator can't be moved to another thread while one is alive,
+//and the borrow table's Rc is only ever shared with Child frames on the same thread.
+//The allocator isn't Sync, since pushing through &self mutates it without synchronization.
+unsafe impl<'s, Key, Value, A: Allocator> Send for StackFrameDictAllocator<'s, Key, Value, A, Root>
+where
+    Key: Eq + Hash + Send,
+    Value: Send,
+    A: Send
+{}
+
+/// Clones every Frame of the stack into newly allocated blocks,
+/// pushing clones of every Key Value pair in the same order they were pushed.
+///
+/// The clone owns every Frame, including Frames belonging to the allocators
+/// this allocator was made from, and is indexed if this allocator is indexed.
+///
+/// # Examples
+///
+/// ```edition2020
+/// # use stack_frame_allocators::stack_frame_dict_allocator::StackFrameDictAllocator;
+///
+/// let stack = StackFrameDictAllocator::<&str, usize>::new();
+/// stack.push("a", 1);
+///
+/// stack.new_scope(|stack| {
+///     stack.push("a", 2);
+///
+///     let clone = stack.clone();
+///     clone.push("a", 3);
+///
+///     assert_eq!(clone["a"], 3);
+///     assert_eq!(stack["a"], 2);
+///     assert_eq!(clone.iter_stack().count(), 3);
+/// });
+/// ```
+impl<'s, Key, Value, A: Allocator, Kind> Clone for StackFrameDictAllocator<'s, Key, Value, A, Kind>
+where
+    Key: Clone + Eq + Hash,
+    Value: Clone,
+    A: Clone
+{
+    fn clone(&self) -> Self {
+        let mut stack = Self::with_stack_size_in(self.size, self.growth, self.allocator.clone());
+        stack.on_block_alloc = self.on_block_alloc.clone();
+        #[cfg(feature = "std")]
+        {
+            stack.index = self.index.as_ref().map(|_| UnsafeCell::new(HashMap::new()));
+        }
+        #[cfg(feature = "borrow_check")]
+        {
+            stack.borrows = self.borrows.as_ref().map(|_| Rc::new(BorrowTable::default()));
+        }
+
+        for (depth, frame) in self.frames().into_iter().enumerate() {
+            if depth != 0 {
+                //the clone owns every frame,
+                //so they're all popped when the clone is dropped
+                unsafe {
+                    stack.generate_frame();
+                    *stack.entered_frames.get() += 1;
+                }
+            }
+
+            for (key, value) in frame {
+                stack.push(key.clone(), value.clone());
+            }
+        }
+
+        return stack;
+    }
+}
+
+/// Serializes the entire Stack as a sequence of Frames, from the root Frame to the current Frame,
+/// where every Frame is a sequence of Key Value pairs in the order they were pushed.
+///
+/// Pairs that are currently being shadowed are included,
+/// so the Stack can be rebuilt exactly as it was.
+///
+/// # Examples
+///
+/// ```edition2020
+/// # use stack_frame_allocators::stack_frame_dict_allocator::StackFrameDictAllocator;
+///
+/// let stack = StackFrameDictAllocator::<&str, usize>::new();
+/// stack.push("a", 1);
+///
+/// stack.new_scope(|stack| {
+///     stack.push("a", 2);
+///     stack.push("b", 3);
+///
+///     let json = serde_json::to_string(&stack).unwrap();
+///     assert_eq!(json, r#"[[["a",1]],[["a",2],["b",3]]]"#);
+/// });
+/// ```
+#[cfg(feature = "serde")]
+impl<'s, Key, Value, A: Allocator, Kind> Serialize for StackFrameDictAllocator<'s, Key, Value, A, Kind>
+where
+    Key: Eq + Hash + Serialize,
+    Value: Serialize
+{
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.frames().serialize(serializer)
+    }
+}
+
+/// Rebuilds a Stack serialized by the StackFrameDictAllocator's Serialize implementation,
+/// pushing every Frame onto a new StackFrameDictAllocator in the same order,
+/// so lookups behave exactly like they did on the serialized Stack.
+///
+/// The deserialized allocator owns every Frame, and an empty sequence
+/// deserializes to an allocator with an empty root Frame.
+///
+/// # Examples
+///
+/// ```edition2020
+/// # use stack_frame_allocators::stack_frame_dict_allocator::StackFrameDictAllocator;
+///
+/// let json = r#"[[["a",1]],[["a",2],["b",3]]]"#;
+/// let stack: StackFrameDictAllocator<String, usize> = serde_json::from_str(json).unwrap();
+///
+/// assert_eq!(stack["a".to_string()], 2);
+/// let (a, depth) = stack.get_in_stack_with_depth("b".to_string()).unwrap();
+/// assert_eq!((*a.get(), depth), (3, 0));
+/// assert_eq!(serde_json::to_string(&stack).unwrap(), json);
+/// ```
+#[cfg(feature = "serde")]
+impl<'de, 's, Key, Value> Deserialize<'de> for StackFrameDictAllocator<'s, Key, Value>
+where
+    Key: Eq + Hash + Deserialize<'de>,
+    Value: Deserialize<'de>
+{
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_seq(StackVisitor(PhantomData))
+    }
+}
+
+/// Visits the sequence of Frames of a serialized Stack.
+#[cfg(feature = "serde")]
+struct StackVisitor<'s, Key, Value>(PhantomData<StackFrameDictAllocator<'s, Key, Value>>)
+where
+    Key: Eq + Hash;
+
+#[cfg(feature = "serde")]
+impl<'de, 's, Key, Value> Visitor<'de> for StackVisitor<'s, Key, Value>
+where
+    Key: Eq + Hash + Deserialize<'de>,
+    Value: Deserialize<'de>
+{
+    type Value = StackFrameDictAllocator<'s, Key, Value>;
+
+    fn expecting(&self, f: &mut Formatter) -> fmt::Result {
+        f.write_str("a sequence of frames")
+    }
+
+    fn visit_seq<S: SeqAccess<'de>>(self, mut frames: S) -> Result<Self::Value, S::Error> {
+        let stack = StackFrameDictAllocator::new();
+
+        let mut root = true;
+        while frames.next_element_seed(FrameSeed { stack: &stack, root })?.is_some() {
+            root = false;
+        }
+
+        return Ok(stack);
+    }
+}
+
+/// Pushes the Key Value pairs of a serialized Frame onto a new Frame,
+/// or onto the root Frame if it's the first Frame.
+#[cfg(feature = "serde")]
+struct FrameSeed<'a, 's, Key, Value>
+where
+    Key: Eq + Hash
+{
+    stack: &'a StackFrameDictAllocator<'s, Key, Value>,
+    root: bool
+}
+
+#[cfg(feature = "serde")]
+impl<'de, 'a, 's, Key, Value> DeserializeSeed<'de> for FrameSeed<'a, 's, Key, Value>
+where
+    Key: Eq + Hash + Deserialize<'de>,
+    Value: Deserialize<'de>
+{
+    type Value = ();
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        if !self.root {
+            //the deserialized allocator owns every frame,
+            //so they're all popped when it's dropped
+            unsafe {
+                self.stack.generate_frame();
+                *self.stack.entered_frames.get() += 1;
+            }
+        }
+
+        deserializer.deserialize_seq(self)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, 'a, 's, Key, Value> Visitor<'de> for FrameSeed<'a, 's, Key, Value>
+where
+    Key: Eq + Hash + Deserialize<'de>,
+    Value: Deserialize<'de>
+{
+    type Value = ();
+
+    fn expecting(&self, f: &mut Formatter) -> fmt::Result {
+        f.write_str("a sequence of key value pairs")
+    }
+
+    fn visit_seq<S: SeqAccess<'de>>(self, mut pairs: S) -> Result<(), S::Error> {
+        while let Some((key, value)) = pairs.next_element::<(Key, Value)>()? {
+            self.stack.push(key, value);
+        }
+
+        return Ok(());
+    }
+}
+
+/// Grabs the latest Value with the given Key in the current Frame,
+/// the same way [get_in_frame](crate::stack_frame_dict_allocator::StackFrameDictAllocator::get_in_frame) does.
+///
+/// There is no `IndexMut`, since multiple StackRefs can point to the same Value,
+/// so handing out a mutable reference could alias.
+/// Wrap Values in a type with interior mutability, like RefCell, to mutate them instead.
+///
+/// # Panics
+///
+/// Panics if the Key is not in the current Frame.
+///
+/// # Examples
+///
+/// ```edition2020
+/// # use stack_frame_allocators::stack_frame_dict_allocator::StackFrameDictAllocator;
+///
+/// let stack = StackFrameDictAllocator::<&str, usize>::new();
+/// stack.push("a", 5);
+///
+/// assert_eq!(stack["a"], 5);
+/// ```
+impl<'s, Key, Value, A: Allocator, Kind> Index<Key> for StackFrameDictAllocator<'s, Key, Value, A, Kind>
+where
+    Key: Eq + Hash
+{
+    type Output = Value;
+
+    fn index(&self, key: Key) -> &Value {
+        self.find_key_in_frame(&key)
+            .expect("key is not in the current frame")
+            .get()
+    }
+}
+
+/// Configures a [StackFrameDictAllocator](crate::stack_frame_dict_allocator::StackFrameDictAllocator)
+/// before creating it, created by
+/// [builder](crate::stack_frame_dict_allocator::StackFrameDictAllocator::builder).
+pub struct StackFrameDictAllocatorBuilder<Key, Value, A: Allocator = Global> {
+    size: StackSize,
+    growth: GrowthPolicy,
+    on_block_alloc: Option<Arc<dyn Fn(usize) + Send + Sync>>,
+    allocator: A,
+    phantom: PhantomData<(Key, Value)>
+}
+
+impl<Key, Value, A: Allocator> StackFrameDictAllocatorBuilder<Key, Value, A> {
+    /// Sets how large the first block is in bytes.
+    ///
+    /// Defaults to 1024 bytes.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `bytes` isn't a power of two,
+    /// [build](crate::stack_frame_dict_allocator::StackFrameDictAllocatorBuilder::build) panics if `bytes`
+    /// is too small to hold a frame header and a block tail.
+    pub fn block_size(mut self, bytes: usize) -> Self {
+        //block sizes are kept as powers of two,
+        //so the block tail at the end of the block stays aligned
+        assert!(bytes.is_power_of_two(), "block size must be a power of two, got {} bytes", bytes);
+        self.size = StackSize::from_num_bytes(bytes);
+        self
+    }
+
+    /// Sets how large every block allocated after the first block is.
+    ///
+    /// Defaults to [GrowthPolicy::Fixed](crate::growth_policy::GrowthPolicy::Fixed).
+    pub fn growth(mut self, growth: GrowthPolicy) -> Self {
+        self.growth = growth;
+        self
+    }
+
+    /// Sets a callback called with the size in bytes of every block allocated after the first block,
+    /// so the allocator growing can be observed without polling it.
+    ///
+    /// Frames made from the allocator call the same callback.
+    /// Defaults to no callback.
+    pub fn on_block_alloc(mut self, on_block_alloc: impl Fn(usize) + Send + Sync + 'static) -> Self {
+        self.on_block_alloc = Some(Arc::new(on_block_alloc));
+        self
+    }
+
+    /// Sets the allocator blocks are allocated and deallocated with.
+    ///
+    /// Defaults to [Global](allocator_api2::alloc::Global).
+    pub fn allocator<B: Allocator>(self, allocator: B) -> StackFrameDictAllocatorBuilder<Key, Value, B> {
+        StackFrameDictAllocatorBuilder {
+            size: self.size,
+            growth: self.growth,
+            on_block_alloc: self.on_block_alloc,
+            allocator,
+            phantom: PhantomData
+        }
+    }
+
+    /// Creates the configured StackFrameDictAllocator.
+    pub fn build<'s>(self) -> StackFrameDictAllocator<'s, Key, Value, A> where Key: Eq + Hash {
+        let mut stack = StackFrameDictAllocator::with_stack_size_in(self.size, self.growth, self.allocator);
+        stack.on_block_alloc = self.on_block_alloc;
+        stack
+    }
+
+    /// Creates the configured StackFrameDictAllocator,
+    /// returning an error instead of aborting if the first block can't be allocated.
+    pub fn try_build<'s>(self) -> Result<StackFrameDictAllocator<'s, Key, Value, A>, AllocError> where Key: Eq + Hash {
+        let mut stack = StackFrameDictAllocator::try_with_stack_size_in(self.size, self.growth, self.allocator)?;
+        stack.on_block_alloc = self.on_block_alloc;
+        Ok(stack)
+    }
+}
+
+impl<'s, Key, Value, A: Allocator, Kind> Drop for StackFrameDictAllocator<'s, Key, Value, A, Kind> 
+where 
+    Key: Eq + Hash
+{
+    fn drop(&mut self) {
+        //eprintln!("dropping stack frame");
+        unsafe {
+            //frames entered on top of this allocator's own frame
+            //are popped first, in case their guards were forgotten
+            while *self.entered_frames.get() > 0 {
+                self.pop_frame();
+            }
+
+            //we're only dropping the current frame,
+            //previous frames are dropped by the allocators that created them
+            let mut walker = self.walker();
+            while let Some(key_ptr) = walker.next_in_frame() {
+                crate::drop_element(key_ptr.cast::<Key>());
+                crate::drop_element(key_ptr.add(Self::OFFSET_VALUE).cast::<Value>());
+            }
+            
+            if self.is_root() {
+                //eprintln!("dropping whole stack");
+                //the root header always sits at the start of the first block
+                let mut next_addr = (*self.current_frame.get()).as_ptr() as *mut u8;
+                let mut next_size = self.size;
+
+                while !next_addr.is_null() {
+                    //eprintln!("dropping block of size {} bytes at {:?}", next_size.bytes(), next_addr);
+                    let (block, size) = (next_addr, next_size);
+                    let block_tail = Self::block_tail_at(block, size);
+                    next_addr = block_tail.next_block;
+                    next_size = StackSize(block_tail.next_block_size);
+
+                    self.deallocate_block(block, size);
+                }
+            }
+        }
+    }
+}
+
+/// Pops the Frame created by
+/// [enter_frame](crate::stack_frame_dict_allocator::StackFrameDictAllocator::enter_frame)
+/// when dropped.
+///
+/// Dereferences to the allocator, so items can be pushed onto and found in the guard's Frame.
+pub struct FrameGuard<'a, 's, Key, Value, A: Allocator = Global, Kind = Root>
+where
+    Key: Eq + Hash
+{
+    stack: &'a StackFrameDictAllocator<'s, Key, Value, A, Kind>
+}
+
+impl<'a, 's, Key, Value, A: Allocator, Kind> Deref for FrameGuard<'a, 's, Key, Value, A, Kind>
+where
+    Key: Eq + Hash
+{
+    type Target = StackFrameDictAllocator<'s, Key, Value, A, Kind>;
+
+    fn deref(&self) -> &Self::Target {
+        self.stack
+    }
+}
+
+impl<'a, 's, Key, Value, A: Allocator, Kind> Drop for FrameGuard<'a, 's, Key, Value, A, Kind>
+where
+    Key: Eq + Hash
+{
+    fn drop(&mut self) {
+        //enter_frame's caller guarantees the guard's frame is the current frame
+        unsafe {self.stack.pop_frame()}
+    }
+}
+
+/// Moves every Key Value pair out of the Frames an allocator owns,
+/// from the last push (top) to the first push (bottom).
+///
+/// An allocator made with [new](crate::stack_frame_dict_allocator::StackFrameDictAllocator::new)
+/// owns the entire stack, while one made with
+/// [new_frame](crate::stack_frame_dict_allocator::StackFrameDictAllocator::new_frame)
+/// or [new_scope](crate::stack_frame_dict_allocator::StackFrameDictAllocator::new_scope)
+/// only owns its own Frame, and any Frames entered on top of it.
+///
+/// # Examples
+///
+/// ```edition2020
+/// # use stack_frame_allocators::stack_frame_dict_allocator::StackFrameDictAllocator;
+///
+/// let stack = StackFrameDictAllocator::<&str, String>::new();
+/// stack.push("a", "1".to_string());
+/// stack.push("b", "2".to_string());
+///
+/// let pairs: Vec<_> = stack.into_iter().collect();
+/// assert_eq!(pairs, [("b", "2".to_string()), ("a", "1".to_string())]);
+/// ```
+impl<'s, Key, Value, A: Allocator, Kind> IntoIterator for StackFrameDictAllocator<'s, Key, Value, A, Kind>
+where
+    Key: Eq + Hash
+{
+    type Item = (Key, Value);
+    type IntoIter = IntoIter<'s, Key, Value, A, Kind>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        IntoIter { stack: self }
+    }
+}
+
+/// Moves the Key Value pairs out of an allocator, created by
+/// [into_iter](crate::stack_frame_dict_allocator::StackFrameDictAllocator::into_iter).
+///
+/// Dropping the iterator drops the pairs it hasn't yielded yet, and frees the allocator.
+pub struct IntoIter<'s, Key, Value, A: Allocator = Global, Kind = Root>
+where
+    Key: Eq + Hash
+{
+    stack: StackFrameDictAllocator<'s, Key, Value, A, Kind>
+}
+
+impl<'s, Key, Value, A: Allocator, Kind> Iterator for IntoIter<'s, Key, Value, A, Kind>
+where
+    Key: Eq + Hash
+{
+    type Item = (Key, Value);
+
+    fn next(&mut self) -> Option<(Key, Value)> {
+        unsafe {
+            loop {
+                let mut walker = self.stack.walker();
+
+                if let Some(key_ptr) = walker.next_in_frame() {
+                    //the frame is cut down to the pairs not yet yielded,
+                    //so dropping the allocator afterwards never drops a moved out pair
+                    let (top, bytes_used) = walker.position();
+                    (*(*self.stack.current_frame.get()).as_ptr()).current_frame_ptr = top;
+                    *self.stack.buffer_bytes_used.get() = bytes_used;
+                    *self.stack.block_size.get() = StackSize(walker.block_size());
+
+                    let key = key_ptr.cast::<Key>().read();
+                    let value = key_ptr.add(StackFrameDictAllocator::<Key, Value, A, Kind>::OFFSET_VALUE)
+                        .cast::<Value>()
+                        .read();
+
+                    return Some((key, value));
+                }
+
+                //frames below the allocator's own frame belong to other allocators
+                if *self.stack.entered_frames.get() == 0 {
+                    return None;
+                }
+
+                self.stack.pop_frame();
+            }
+        }
+    }
+}
+
+/// A view into the latest key value pair with a given Key in the current Frame,
+/// created by [entry](crate::stack_frame_dict_allocator::StackFrameDictAllocator::entry).
+pub struct Entry<'a, 's, Key, Value, A: Allocator = Global, Kind = Root>
+where
+    Key: Eq + Hash
+{
+    stack: &'a StackFrameDictAllocator<'s, Key, Value, A, Kind>,
+    key: Key,
+    value: Option<StackRef<'a, Value>>
+}
+
+impl<'a, 's, Key, Value, A: Allocator, Kind> Entry<'a, 's, Key, Value, A, Kind>
+where
+    Key: Eq + Hash
+{
+    /// Returns a StackRef to the latest Value with the Entry's Key in the current Frame,
+    /// or pushes the given Value if there is none.
+    ///
+    /// A Value already in the current Frame is never shadowed.
+    pub fn or_push(self, value: Value) -> StackRef<'a, Value> {
+        self.or_push_with(|| value)
+    }
+
+    /// Returns a StackRef to the latest Value with the Entry's Key in the current Frame,
+    /// or pushes the result of `default` if there is none.
+    ///
+    /// `default` is only called if the Key is not in the current Frame.
+    pub fn or_push_with<F: FnOnce() -> Value>(self, default: F) -> StackRef<'a, Value> {
+        match self.value {
+            Some(value) => value,
+            None => self.stack.push(self.key, default())
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    pub fn get_in_frame_test() {
+        let stack = StackFrameDictAllocator::<&str, &str>::new();
+        
+        stack.push("red", "first");
+        stack.push("blue", "first");
+        
+        stack.new_scope(|stack| {
+            stack.push("red", "second");
+        
+            let red = stack.get_in_frame("red").unwrap().get();
+            let blue = stack.get_in_frame("blue");
+            assert_eq!(*red, "second");
+            assert!(blue.is_none());
+        });
+        
+        let red = stack.get_in_frame("red").unwrap().get();
+        let blue = stack.get_in_frame("blue").unwrap().get();
+        assert_eq!(*red, "first");
+        assert_eq!(*blue, "first");
+        
+        //shadow blue
+        stack.push("blue", "second");
+        
+        let blue = stack.get_in_frame("blue").unwrap().get();
+        assert_eq!(*blue, "second");
+    }
+
+    #[test]
+    pub fn get_in_stack_test() {
+        let stack = StackFrameDictAllocator::<&str, &str>::new();
+    
+        stack.push("red", "old");
+        stack.push("blue", "old");
+    
+        stack.new_scope(|stack| {
+            stack.push("green", "new");
+    
+            let red = stack.get_in_stack("red").unwrap().get();
+            let blue = stack.get_in_stack("blue");
+            let green = stack.get_in_stack("green").unwrap().get();
+            assert_eq!(*red, "old");
+            assert!(blue.is_some());
+            assert_eq!(*green, "new");
+    
+            //shadow blue
+            stack.push("red", "new");
+    
+            let red = stack.get_in_stack("red").unwrap().get();
+            assert_eq!(*red, "new");
+        });
+
+        let red = stack.get_in_stack("red").unwrap().get();
+        let blue = stack.get_in_stack("blue").unwrap().get();
+        assert_eq!(*red, "old");
+        assert_eq!(*blue, "old");
+    }
+
+    #[test]
+    pub fn get_in_stack_with_depth_test() {
+        let stack = StackFrameDictAllocator::<&str, u64>::new();
+        stack.push("root", 0);
+        stack.push("shadowed", 0);
+
+        stack.new_scope(|stack| {
+            stack.push("one", 1);
+
+            stack.new_scope(|stack| {
+                stack.push("shadowed", 2);
+
+                stack.new_scope(|stack| {
+                    stack.push("three", 3);
+
+                    let depth = |key| stack.get_in_stack_with_depth(key)
+                        .map(|(value, depth)| (*value.get(), depth));
+                    assert_eq!(depth("three"), Some((3, 0)));
+                    assert_eq!(depth("shadowed"), Some((2, 1)));
+                    assert_eq!(depth("one"), Some((1, 2)));
+                    assert_eq!(depth("root"), Some((0, 3)));
+                    assert_eq!(depth("missing"), None);
+                });
+            });
+        });
+
+        let (_, depth) = stack.get_in_stack_with_depth("shadowed").unwrap();
+        assert_eq!(depth, 0);
+    }
+
+    #[test]
+    pub fn get_ref_test() {
+        for stack in [
+            StackFrameDictAllocator::<String, u64>::new(),
+            StackFrameDictAllocator::<String, u64>::new_indexed()
+        ] {
+            stack.push("outer".to_string(), 0);
+
+            stack.new_scope(|stack| {
+                for i in 0..200u64 {
+                    stack.push(format!("key{}", i % 20), i);
+                }
+
+                assert_eq!(*stack.get_in_frame_ref("key3").unwrap().get(), 183);
+                assert_eq!(*stack.get_in_frame_ref(&"key3".to_string()).unwrap().get(), 183);
+                assert!(stack.get_in_frame_ref("outer").is_none());
+                assert_eq!(*stack.get_in_stack_ref("outer").unwrap().get(), 0);
+                assert_eq!(*stack.get_in_stack_ref("key19").unwrap().get(), 199);
+                assert!(stack.get_in_stack_ref("key20").is_none());
+            });
+        }
+    }
+
+    #[test]
+    pub fn get_ref_no_alloc_test() {
+        for stack in [
+            StackFrameDictAllocator::<String, u64>::new(),
+            StackFrameDictAllocator::<String, u64>::new_indexed()
+        ] {
+            stack.push("outer".to_string(), 0);
+            for i in 0..20u64 {
+                stack.push(format!("key{}", i), i);
+            }
+
+            let allocations = counting_alloc::allocations();
+            assert_eq!(*stack.get_in_frame_ref("key7").unwrap().get(), 7);
+            assert!(stack.get_in_frame_ref("missing").is_none());
+            assert_eq!(*stack.get_in_stack_ref("outer").unwrap().get(), 0);
+            assert_eq!(counting_alloc::allocations(), allocations);
+
+            //get_in_frame allocates a String just to compare it
+            assert_eq!(*stack.get_in_frame("key7").unwrap().get(), 7);
+            assert!(counting_alloc::allocations() > allocations);
+        }
+    }
+
+    /// Counts the allocations made on each thread,
+    /// so tests running in parallel don't see each other's allocations.
+    mod counting_alloc {
+        use std::{alloc::{GlobalAlloc, Layout, System}, cell::Cell};
+
+        thread_local! {
+            static ALLOCATIONS: Cell<usize> = const {Cell::new(0)};
+        }
+
+        pub fn allocations() -> usize {
+            ALLOCATIONS.with(Cell::get)
+        }
+
+        struct CountingAlloc;
+
+        unsafe impl GlobalAlloc for CountingAlloc {
+            unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+                //the counter is gone while the thread is being torn down
+                let _ = ALLOCATIONS.try_with(|allocations| allocations.set(allocations.get() + 1));
+                System.alloc(layout)
+            }
+
+            unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+                System.dealloc(ptr, layout)
+            }
+        }
+
+        #[global_allocator]
+        static GLOBAL: CountingAlloc = CountingAlloc;
+    }
+
+    #[test]
+    pub fn find_test() {
+        let stack = StackFrameDictAllocator::<u64, u64>::new();
+        stack.push(0u64, 1000);
+        stack.push(1u64, 1);
+
+        stack.new_scope(|stack| {
+            for i in 0..200u64 {
+                stack.push(i, i);
+            }
+
+            let found = stack.find_in_frame(|_, value| *value > 150 && *value % 7 == 0);
+            assert_eq!(*found.unwrap().get(), 196);
+            let found = stack.find_in_frame(|key, _| *key < 3);
+            assert_eq!(*found.unwrap().get(), 2);
+            assert!(stack.find_in_frame(|_, value| *value > 200).is_none());
+
+            let found = stack.find_in_stack(|_, value| *value > 200);
+            assert_eq!(*found.unwrap().get(), 1000);
+            assert!(stack.find_in_stack(|_, value| *value > 1000).is_none());
+        });
+    }
+
+    #[test]
+    pub fn peek_test() {
+        let stack = StackFrameDictAllocator::<u8, u64>::new();
+        assert_eq!(stack.peek(), None);
+
+        for i in 0..200 {
+            stack.push(i, i as u64 * 1000);
+            assert_eq!(stack.peek(), Some((&i, &(i as u64 * 1000))));
+        }
+
+        stack.new_scope(|stack| {
+            assert_eq!(stack.peek(), None);
+            stack.push(255, 0u64);
+            assert_eq!(stack.peek(), Some((&255, &0)));
+        });
+
+        assert_eq!(stack.peek(), Some((&199, &199000)));
+    }
+
+    #[test]
+    pub fn keys_values_in_frame_test() {
+        let stack = StackFrameDictAllocator::<u64, u64>::new();
+        stack.push(1000u64, 1000);
+
+        stack.new_scope(|stack| {
+            assert_eq!(stack.keys_in_frame().count(), 0);
+
+            //spill the frame over multiple blocks
+            for i in 0..300u64 {
+                stack.push(i % 100, i);
+            }
+
+            let keys: Vec<u64> = stack.keys_in_frame().copied().collect();
+            let values: Vec<u64> = stack.values_in_frame().copied().collect();
+            assert_eq!(keys, (0..300).rev().map(|i| i % 100).collect::<Vec<u64>>());
+            assert_eq!(values, (0..300).rev().collect::<Vec<u64>>());
+
+            //the first value yielded for a key is the one get_in_frame finds
+            let (key, value) = stack.keys_in_frame().zip(stack.values_in_frame())
+                .find(|(key, _)| **key == 42)
+                .unwrap();
+            assert_eq!(value, stack.get_in_frame(*key).unwrap().get());
+        });
+
+        assert_eq!(stack.keys_in_frame().collect::<Vec<_>>(), vec![&1000]);
+    }
+
+    #[test]
+    pub fn new_scope_result_test() {
+        let stack = StackFrameDictAllocator::<u64, u64>::new();
+        stack.push(0u64, 1000);
+
+        let total = stack.new_scope(|stack| {
+            for i in 0..500u64 {
+                stack.push(i, i);
+            }
+
+            stack.values_in_frame().sum::<u64>()
+        });
+
+        assert_eq!(total, (0..500).sum::<u64>());
+        assert_eq!(stack.values_in_frame().sum::<u64>(), 1000);
+    }
+
+    #[test]
+    pub fn enter_frame_test() {
+        let stack = StackFrameDictAllocator::<&str, u64>::new_indexed();
+        stack.push("x", 0);
+
+        {
+            let outer = unsafe {stack.enter_frame()};
+            outer.push("x", 1);
+            outer.push("y", 1);
+
+            {
+                let inner = unsafe {outer.enter_frame()};
+                for i in 0..200 {
+                    inner.push("x", i);
+                }
+
+                assert_eq!(inner["x"], 199);
+                assert!(inner.get_in_frame("y").is_none());
+                let (y, depth) = inner.get_in_stack_with_depth("y").unwrap();
+                assert_eq!((*y.get(), depth), (1, 1));
+            }
+
+            assert_eq!(outer["x"], 1);
+            assert_eq!(outer.keys_in_frame().count(), 2);
+        }
+
+        assert_eq!(stack["x"], 0);
+        assert!(stack.get_in_stack("y").is_none());
+        assert_eq!(stack.iter_stack().count(), 1);
+    }
+
+    #[test]
+    pub fn clear_test() {
+        use std::rc::Rc;
+
+        let counter = Rc::new(());
+        let mut stack = StackFrameDictAllocator::<u64, Rc<()>>::new_indexed();
+        for i in 0..200u64 {
+            stack.push(i, counter.clone());
+        }
+
+        stack.clear();
+        assert_eq!(Rc::strong_count(&counter), 1);
+        assert!(stack.peek().is_none());
+        assert!(stack.get_in_frame(0u64).is_none());
+
+        for i in 0..200u64 {
+            stack.push(i, counter.clone());
+        }
+        assert_eq!(stack.iter_stack().count(), 200);
+        assert!(stack.get_in_frame(199u64).is_some());
+
+        drop(stack);
+        assert_eq!(Rc::strong_count(&counter), 1);
+    }
+
+    #[test]
+    pub fn clone_test() {
+        use std::{cell::RefCell, rc::Rc};
+
+        let counter = Rc::new(());
+        {
+            let stack = StackFrameDictAllocator::<u64, (Rc<()>, RefCell<u64>)>::builder()
+                .growth(GrowthPolicy::Geometric { factor: 2, cap: 1 << 16 })
+                .build();
+            stack.push(0u64, (counter.clone(), RefCell::new(0)));
+
+            stack.new_scope(|stack| {
+                for i in 0..300u64 {
+                    stack.push(i % 50, (counter.clone(), RefCell::new(i)));
+                }
+
+                stack.new_scope(|stack| {
+                    //an empty frame
+                    stack.new_scope(|stack| {
+                        let clone = stack.clone();
+                        assert_eq!(Rc::strong_count(&counter), 603);
+
+                        let pairs = |stack: &StackDictFrame<u64, (Rc<()>, RefCell<u64>)>| {
+                            stack.iter_stack()
+                                .map(|(key, (_, value))| (*key, *value.borrow()))
+                                .collect::<Vec<_>>()
+                        };
+                        assert_eq!(pairs(&clone), pairs(&stack));
+                        assert!(clone.peek().is_none());
+
+                        //frames are rebuilt, so lookups see the same frames
+                        let (value, depth) = clone.get_in_stack_with_depth(7u64).unwrap();
+                        assert_eq!((*value.get().1.borrow(), depth), (257, 2));
+                        let (value, depth) = clone.get_in_stack_with_depth(0u64).unwrap();
+                        assert_eq!((*value.get().1.borrow(), depth), (250, 2));
+
+                        *value.get().1.borrow_mut() = 1000;
+                        clone.push(1u64, (counter.clone(), RefCell::new(1)));
+                        assert_eq!(*stack.get_in_stack(0u64).unwrap().get().1.borrow(), 250);
+                        assert!(stack.get_in_frame(1u64).is_none());
+                    });
+                    assert_eq!(Rc::strong_count(&counter), 302);
+                });
+            });
+        }
+
+        assert_eq!(Rc::strong_count(&counter), 1);
+    }
+
+    #[test]
+    pub fn send_test() {
+        let stack = StackFrameDictAllocator::<String, u64>::new_indexed();
+        stack.push("main".to_string(), 0);
+
+        let stack = std::thread::spawn(move || {
+            stack.push("thread".to_string(), 1);
+
+            stack
+        }).join().unwrap();
+
+        assert_eq!(stack["main".to_string()], 0);
+        assert_eq!(stack["thread".to_string()], 1);
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    pub fn serialize_test() {
+        let stack = StackFrameDictAllocator::<u64, u64>::new();
+        assert_eq!(serde_json::to_string(&stack).unwrap(), "[[]]");
+        stack.push(0u64, 0);
+
+        stack.new_scope(|stack| {
+            //spill the frame over multiple blocks
+            for i in 0..300u64 {
+                stack.push(i % 10, i);
+            }
+
+            stack.new_scope(|stack| {
+                let json: Vec<Vec<(u64, u64)>> = serde_json::from_str(
+                    &serde_json::to_string(&stack).unwrap()
+                ).unwrap();
+
+                assert_eq!(json, vec![
+                    vec![(0, 0)],
+                    (0..300).map(|i| (i % 10, i)).collect(),
+                    vec![]
+                ]);
+            });
+        });
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    pub fn deserialize_test() {
+        type Stack<'s> = StackFrameDictAllocator<'s, u64, u64>;
+
+        let empty: Stack = serde_json::from_str("[]").unwrap();
+        assert!(empty.peek().is_none());
+        assert_eq!(serde_json::to_string(&empty).unwrap(), "[[]]");
+
+        let empty_frames: Stack = serde_json::from_str("[[],[],[[1,1]],[]]").unwrap();
+        assert!(empty_frames.peek().is_none());
+        assert_eq!(empty_frames.get_in_stack_with_depth(1u64).map(|(_, depth)| depth), Some(1));
+
+        assert!(serde_json::from_str::<Stack>("[[[1,1]],[[2]]]").is_err());
+
+        //round trip randomly shaped stacks
+        let mut seed = 0x2545_f491_4f6c_dd1du64;
+        let mut random = |max: u64| {
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+            seed % max
+        };
+
+        for _ in 0..50 {
+            let frames: Vec<Vec<(u64, u64)>> = (0..=random(5))
+                .map(|_| (0..random(300)).map(|_| (random(20), random(1000))).collect())
+                .collect();
+
+            let json = serde_json::to_string(&frames).unwrap();
+            let stack: Stack = serde_json::from_str(&json).unwrap();
+            assert_eq!(serde_json::to_string(&stack).unwrap(), json);
+
+            for key in 0..20u64 {
+                let expected = frames.iter().rev().enumerate()
+                    .find_map(|(depth, frame)| frame.iter().rev()
+                        .find(|(k, _)| *k == key)
+                        .map(|(_, value)| (*value, depth))
+                    );
+                let found = stack.get_in_stack_with_depth(key)
+                    .map(|(value, depth)| (*value.get(), depth));
+                assert_eq!(found, expected);
+            }
+        }
+    }
+
+    #[test]
+    pub fn index_test() {
+        let stack = StackFrameDictAllocator::<&str, u64>::new();
+        stack.push("a", 5);
+        assert_eq!(stack["a"], 5);
+
+        stack.new_scope(|stack| {
+            stack.push("a", 6);
+            stack.push("a", 7);
+            assert_eq!(stack["a"], 7);
+        });
+
+        assert_eq!(stack["a"], 5);
+        assert!(std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| stack["b"])).is_err());
+    }
+
+    #[test]
+    pub fn mixed_alignment_test() {
+        let stack = StackFrameDictAllocator::<u8, u64>::new();
+        for i in 0..200 {
+            stack.push(i, i as u64);
+        }
+        for i in 0..200 {
+            assert_eq!(*stack.get_in_frame(i).unwrap().get(), i as u64);
+        }
+
+        let stack = StackFrameDictAllocator::<u64, u8>::new();
+        for i in 0..200u64 {
+            stack.push(i, i as u8);
+        }
+        for i in 0..200u64 {
+            assert_eq!(*stack.get_in_frame(i).unwrap().get(), i as u8);
+        }
+    }
+
+    #[test]
+    pub fn mixed_alignment_frames_test() {
+        //pairs padded to 32 bytes, with frame headers landing at offsets
+        //that aren't aligned to the pairs, so every walk has to skip padding
+        fn check<Key: Eq + Hash + Copy, Value: PartialEq + Debug + Copy>(key: fn(u64) -> Key, value: fn(u64) -> Value) {
+            let stack = StackFrameDictAllocator::<Key, Value>::new();
+            for i in 0..50 {
+                stack.push(key(i), value(i));
+            }
+
+            stack.new_scope(|stack| {
+                stack.new_scope(|stack| {
+                    for i in 50..120 {
+                        stack.push(key(i), value(i));
+                    }
+
+                    stack.new_scope(|stack| {
+                        assert!(stack.peek().is_none());
+                        assert_eq!(stack.iter_stack().count(), 120);
+                        for i in 0..120 {
+                            assert_eq!(*stack.get_in_stack(key(i)).unwrap().get(), value(i));
+                            assert_eq!(stack.count_in_stack(key(i)), 1);
+                        }
+                    });
+
+                    assert_eq!(stack.keys_in_frame().count(), 70);
+                    assert_eq!(stack.position_in_frame(key(50)), Some(69));
+                });
+
+                assert!(stack.get_in_frame(key(0)).is_none());
+                assert_eq!(*stack.get_in_stack(key(49)).unwrap().get(), value(49));
+            });
+
+            assert_eq!(stack.keys_in_frame().count(), 50);
+        }
+
+        check(|i| [i as u8; 3], |i| i as u128);
+        check(|i| i as u128, |i| i as u8);
+        check(|i| i as u8, |i| (i as u16, i as u128));
+    }
+
+    #[test]
+    pub fn iter_stack_test() {
+        let stack = StackFrameDictAllocator::<u32, u32>::new();
+        for i in 0..100 {
+            stack.push(i, i);
+        }
+
+        stack.new_scope(|stack| {
+            //spills into a new block, which is popped at the end of the scope
+            for i in 100..300 {
+                stack.push(i, i);
+            }
+        });
+
+        //reuses the block left behind by the previous scope
+        for i in 100..300 {
+            stack.push(i, i);
+        }
+
+        stack.new_scope(|stack| {
+            for i in 300..400 {
+                stack.push(i, i);
+            }
+
+            stack.new_scope(|stack| {
+                for i in 400..600 {
+                    stack.push(i, i);
+                }
+
+                let keys: Vec<u32> = stack.iter_stack().map(|(key, _)| *key).collect();
+                assert_eq!(keys, (0..600).rev().collect::<Vec<_>>());
+            });
+        });
+    }
+
+    #[test]
+    pub fn iter_visible_stack_test() {
+        let stack = StackFrameDictAllocator::<&str, usize>::new();
+        stack.push("a", 1);
+        stack.push("b", 2);
+        stack.push("c", 3);
+
+        stack.new_scope(|stack| {
+            stack.push("a", 10);
+            stack.push("d", 40);
+
+            stack.new_scope(|stack| {
+                stack.push("b", 200);
+                stack.push("b", 201);
+
+                let visible: Vec<_> = stack.iter_visible_stack().collect();
+                assert_eq!(visible, vec![
+                    (&"b", &201),
+                    (&"d", &40),
+                    (&"a", &10),
+                    (&"c", &3)
+                ]);
+            });
+        });
+    }
+
+    use std::cell::RefCell;
+
+    #[doc(hidden)]
+    pub struct DropTest<'d>(&'d str, &'d RefCell<Vec<&'d str>>);
+
+    impl<'d> Drop for DropTest<'d> {
+        fn drop(&mut self) {
+            let (value, dropped) = (self.0, self.1);
+
+            dropped.borrow_mut().push(value);
+        }
+    }
+
+    #[doc(hidden)]
+    #[derive(PartialEq, Eq, Hash)]
+    pub struct DropPrint<T>(T) where T : Display;
+
+    impl<T> Drop for DropPrint<T> where T : Display {
+        fn drop(&mut self) {
+            println!("{}", self.0);
+        }
+    }
+
+    #[test]
+    pub fn drop_scope_test() {
+        let dropped = RefCell::new(vec![]);
+        {
+            let stack = StackFrameDictAllocator::<DropPrint<&str>, DropTest>::new();
+            stack.push(DropPrint("key1scope1"), DropTest("value1scope1", &dropped));
+            stack.push(DropPrint("key2scope1"), DropTest("value2scope1", &dropped));
+            stack.push(DropPrint("key3scope1"), DropTest("value3scope1", &dropped));
+            stack.new_scope(|stack| {
+                stack.push(DropPrint("key1scope2"), DropTest("value1scope2", &dropped));
+                stack.push(DropPrint("key2scope2"), DropTest("value2scope2", &dropped));
+                stack.push(DropPrint("key3scope2"), DropTest("value3scope2", &dropped));
+            });
+        }
+
+        let compare = vec![
+            "value3scope2", 
+            "value2scope2", 
+            "value1scope2", 
+            "value3scope1", 
+            "value2scope1", 
+            "value1scope1"
+        ];
+
+        assert_eq!(*dropped.borrow(), compare);
+    }
+
+    #[test]
+    pub fn drop_frame_test() {
+        let dropped = RefCell::new(vec![]);
+        {
+            let stack = StackFrameDictAllocator::<DropPrint<&str>, DropTest>::new();
+            stack.push(DropPrint("key1scope1"), DropTest("value1scope1", &dropped));
+            stack.push(DropPrint("key2scope1"), DropTest("value2scope1", &dropped));
+            stack.push(DropPrint("key3scope1"), DropTest("value3scope1", &dropped));
+            let stack = stack.new_frame();
+            stack.push(DropPrint("key1scope2"), DropTest("value1scope2", &dropped));
+            stack.push(DropPrint("key2scope2"), DropTest("value2scope2", &dropped));
+            stack.push(DropPrint("key3scope2"), DropTest("value3scope2", &dropped));
+        }
+    }
+
+    #[doc(hidden)]
+    pub struct Explode;
+
+    impl From<Explode> for u64 {
+        fn from(_: Explode) -> u64 {
+            panic!("exploded");
+        }
+    }
+
+    #[test]
+    pub fn panic_scope_test() {
+        use std::panic::{self, AssertUnwindSafe};
+
+        //panic after every amount of pushes,
+        //so one of them panics while spilling over to a new block
+        for pushes in 0..100u64 {
+            let dropped = RefCell::new(vec![]);
+            {
+                let stack = StackFrameDictAllocator::<u64, DropTest>::new();
+                stack.push(0u64, DropTest("root", &dropped));
+
+                let result = panic::catch_unwind(AssertUnwindSafe(|| {
+                    stack.new_scope(|stack| {
+                        for i in 0..pushes {
+                            stack.push(i, DropTest("scope", &dropped));
+                        }
+
+                        stack.push(Explode, DropTest("exploded", &dropped));
+                    });
+                }));
+                assert!(result.is_err());
+
+                //the value of the failed push is dropped when it unwinds
+                let mut expected = vec!["exploded"];
+                expected.extend(["scope"].repeat(pushes as usize));
+                assert_eq!(*dropped.borrow(), expected);
+
+                //the parent frame is left untouched
+                for i in 0..100u64 {
+                    stack.push(i, DropTest("after", &dropped));
+                }
+                assert_eq!(stack.iter_stack().count(), 101);
+            }
+
+            assert_eq!(dropped.borrow().len(), pushes as usize + 102);
+            assert_eq!(dropped.borrow().last(), Some(&"root"));
+        }
+    }
+
+    #[test]
+    pub fn empty_stack_test() {
+        fn printed<Kind>(stack: &StackFrameDictAllocator<u64, u64, Global, Kind>) -> String {
+            let mut out = Vec::new();
+            stack.print_to(&mut out).unwrap();
+            String::from_utf8(out).unwrap()
+        }
+
+        let stack = StackFrameDictAllocator::<u64, u64>::new();
+        assert!(stack.get_in_frame(0u64).is_none());
+        assert!(stack.get_in_stack(0u64).is_none());
+        assert!(stack.peek().is_none());
+        assert_eq!(stack.iter_visible_stack().count(), 0);
+        assert_eq!(printed(&stack), concat!(
+            "top of stack\n",
+            "header\n\n",
+            "1 block(s) of size 1024 bytes have been allocated.\n\n"
+        ));
+
+        stack.new_scope(|stack| {
+            assert!(stack.get_in_frame(0u64).is_none());
+            assert!(stack.get_in_stack(0u64).is_none());
+            assert_eq!(printed(&stack), concat!(
+                "top of stack\n",
+                "header\n",
+                "header\n\n",
+                "1 block(s) of size 1024 bytes have been allocated.\n\n"
+            ));
+        });
+
+        //fill the first block, so the next frame's header starts a new block
+        for i in 0..60u64 {
+            stack.push(i, i);
+        }
+        stack.new_scope(|stack| {
+            assert!(stack.get_in_frame(0u64).is_none());
+            assert_eq!(*stack.get_in_stack(0u64).unwrap().get(), 0);
+            assert_eq!(stack.count_in_stack(0u64), 1);
+
+            let printed = printed(&stack);
+            assert!(printed.starts_with("top of stack\nheader\n\tKey: 59, Value: 59\n"));
+            assert!(printed.ends_with(concat!(
+                "\tKey: 0, Value: 0\n",
+                "header\n\n",
+                "2 block(s) of size 1024 bytes have been allocated.\n\n"
+            )));
+        });
+    }
+
+    #[test]
+    #[allow(unused_variables)]
+    pub fn empty_drop_test() {
+        let stack_u8_u8 = StackFrameDictAllocator::<DropPrint<u8>, DropPrint<u8>>::new();
+        let stack_u8_u16 = StackFrameDictAllocator::<DropPrint<u8>, DropPrint<u16>>::new();
+        let stack_u8_u32 = StackFrameDictAllocator::<DropPrint<u8>, DropPrint<u32>>::new();
+        let stack_u8_u64 = StackFrameDictAllocator::<DropPrint<u8>, DropPrint<u64>>::new();
+        let stack_u8_u128 = StackFrameDictAllocator::<DropPrint<u8>, DropPrint<u128>>::new();
+
+        let stack_u16_u8 = StackFrameDictAllocator::<DropPrint<u16>, DropPrint<u8>>::new();
+        let stack_u16_u16 = StackFrameDictAllocator::<DropPrint<u16>, DropPrint<u16>>::new();
+        let stack_u16_u32 = StackFrameDictAllocator::<DropPrint<u16>, DropPrint<u32>>::new();
+        let stack_u16_u64 = StackFrameDictAllocator::<DropPrint<u16>, DropPrint<u64>>::new();
+        let stack_u16_u128 = StackFrameDictAllocator::<DropPrint<u16>, DropPrint<u128>>::new();
+
+        let stack_u32_u8 = StackFrameDictAllocator::<DropPrint<u32>, DropPrint<u8>>::new();
+        let stack_u32_u16 = StackFrameDictAllocator::<DropPrint<u32>, DropPrint<u16>>::new();
+        let stack_u32_u32 = StackFrameDictAllocator::<DropPrint<u32>, DropPrint<u32>>::new();
+        let stack_u32_u64 = StackFrameDictAllocator::<DropPrint<u32>, DropPrint<u64>>::new();
+        let stack_u32_u128 = StackFrameDictAllocator::<DropPrint<u32>, DropPrint<u128>>::new();
+
+        let stack_u64_u8 = StackFrameDictAllocator::<DropPrint<u64>, DropPrint<u8>>::new();
+        let stack_u64_u16 = StackFrameDictAllocator::<DropPrint<u64>, DropPrint<u16>>::new();
+        let stack_u64_u32 = StackFrameDictAllocator::<DropPrint<u64>, DropPrint<u32>>::new();
+        let stack_u64_u64 = StackFrameDictAllocator::<DropPrint<u64>, DropPrint<u64>>::new();
+        let stack_u64_u128 = StackFrameDictAllocator::<DropPrint<u64>, DropPrint<u128>>::new();
+
+        let stack_u128_u8 = StackFrameDictAllocator::<DropPrint<u128>, DropPrint<u8>>::new();
+        let stack_u128_u16 = StackFrameDictAllocator::<DropPrint<u128>, DropPrint<u16>>::new();
+        let stack_u128_u32 = StackFrameDictAllocator::<DropPrint<u128>, DropPrint<u32>>::new();
+        let stack_u128_u64 = StackFrameDictAllocator::<DropPrint<u128>, DropPrint<u64>>::new();
+        let stack_u128_u128 = StackFrameDictAllocator::<DropPrint<u128>, DropPrint<u128>>::new();
+    }
+
+    #[test]
+    pub fn stack_ref_eq_test() {
+        let stack = StackFrameDictAllocator::<&str, String>::new();
+        let a = stack.push("a", "same".to_string());
+        let b = stack.push("b", "same".to_string());
+        let found_a = stack.get_in_frame("a").unwrap();
+
+        //equal values, different places
+        assert!(a == b);
+        assert!(!a.ptr_eq(&b));
+
+        //the same place
+        assert!(a == found_a);
+        assert!(a.ptr_eq(&found_a));
+
+        let clones = vec![a.clone(), b.clone(), found_a.clone()];
+        let mut unique: Vec<StackRef<'_, String>> = vec![];
+        for clone in clones {
+            if !unique.iter().any(|seen| seen.ptr_eq(&clone)) {
+                unique.push(clone);
+            }
+        }
+        assert_eq!(unique.len(), 2);
+
+        stack.new_scope(|stack| {
+            let shadow = stack.push("a", "other".to_string());
+            assert!(shadow != a);
+            assert!(shadow.ptr_eq(&stack.get_in_stack("a").unwrap()));
+        });
+    }
+
+    #[test]
+    pub fn get_or_insert_with_test() {
+        let stack = StackFrameDictAllocator::<&str, usize>::new();
+        stack.push("outer", 0);
+
+        stack.new_scope(|stack| {
+            let mut calls = 0;
+            for _ in 0..2 {
+                let value = stack.get_or_insert_with("a", || {
+                    calls += 1;
+                    calls * 10
+                });
+                assert_eq!(*value.get(), 10);
+            }
+            assert_eq!(calls, 1);
+            assert_eq!(stack.count_in_frame("a"), 1);
+
+            //only the current frame is searched
+            assert_eq!(*stack.get_or_insert_with("outer", || 5).get(), 5);
+            assert_eq!(stack.count_in_stack("outer"), 2);
+        });
+    }
+
+    #[test]
+    pub fn entry_test() {
+        let stack = StackFrameDictAllocator::<&str, usize>::new();
+
+        stack.push("a", 1);
+
+        let a = stack.entry("a").or_push(2).get();
+        assert_eq!(*a, 1);
+        assert_eq!(stack.iter_stack().filter(|(key, _)| **key == "a").count(), 1);
+
+        stack.new_scope(|stack| {
+            //"a" lives in the parent frame, so it gets pushed into this one
+            let a = stack.entry("a").or_push(3).get();
+            assert_eq!(*a, 3);
+            assert_eq!(stack.iter_stack().filter(|(key, _)| **key == "a").count(), 2);
+        });
+
+        let mut calls = 0;
+        let b = stack.entry("b").or_push_with(|| {calls += 1; 4}).get();
+        assert_eq!(*b, 4);
+        let b = stack.entry("b").or_push_with(|| {calls += 1; 5}).get();
+        assert_eq!(*b, 4);
+        assert_eq!(calls, 1);
+    }
+
+    #[test]
+    pub fn get_all_in_frame_test() {
+        let stack = StackFrameDictAllocator::<&str, u64>::new();
+
+        stack.push("x", 0);
+
+        stack.new_scope(|stack| {
+            stack.push("x", 1);
+            stack.push("x", 2);
+            stack.push("x", 3);
+
+            let xs: Vec<u64> = stack.get_all_in_frame("x").into_iter().map(|x| *x.get()).collect();
+            assert_eq!(xs, vec![3, 2, 1]);
+            assert!(stack.get_all_in_frame("y").is_empty());
+
+            //spill the frame over multiple blocks
+            for i in 4..200 {
+                stack.push("x", i);
+                stack.push("y", i);
+            }
+
+            let xs: Vec<u64> = stack.get_all_in_frame("x").into_iter().map(|x| *x.get()).collect();
+            assert_eq!(xs, (1..200).rev().collect::<Vec<u64>>());
+        });
+
+        assert_eq!(stack.get_all_in_frame("x").len(), 1);
+    }
+
+    #[test]
+    pub fn get_first_in_frame_test() {
+        let stack = StackFrameDictAllocator::<&str, u64>::new();
+        stack.push("x", 0);
+
+        stack.new_scope(|stack| {
+            assert!(stack.get_first_in_frame("x").is_none());
+
+            stack.push("x", 1);
+            stack.push("y", 1);
+            stack.push("x", 2);
+            stack.push("x", 3);
+
+            assert_eq!(*stack.get_first_in_frame("x").unwrap().get(), 1);
+            assert_eq!(*stack.get_first_in_frame("y").unwrap().get(), 1);
+            assert_eq!(*stack.get_in_frame("x").unwrap().get(), 3);
+
+            //spill the frame over multiple blocks
+            for i in 0..200 {
+                stack.push("x", i + 4);
+            }
+            assert_eq!(*stack.get_first_in_frame("x").unwrap().get(), 1);
+        });
+
+        assert_eq!(*stack.get_first_in_frame("x").unwrap().get(), 0);
+    }
+
+    #[test]
+    pub fn export_pairs_to_test() {
+        let stack = StackFrameDictAllocator::<String, u64>::new();
+        stack.push("outer".to_string(), 0);
+
+        stack.new_scope(|stack| {
+            for i in 0..100u64 {
+                stack.push(format!("key{}", i % 10), i);
+            }
+
+            let mut out = Vec::new();
+            stack.export_pairs_to(&mut out).unwrap();
+
+            let pairs: Vec<(String, u64)> = String::from_utf8(out).unwrap()
+                .lines()
+                .map(|line| {
+                    let (key, value) = line.split_once('=').unwrap();
+                    (key.to_string(), value.parse().unwrap())
+                })
+                .collect();
+
+            let expected: Vec<(String, u64)> = (90..100u64).rev()
+                .map(|i| (format!("key{}", i % 10), i))
+                .collect();
+            assert_eq!(pairs, expected);
+        });
+    }
+
+    #[test]
+    pub fn retain_frame_test() {
+        let dropped = RefCell::new(vec![]);
+        let names = ["1", "2", "3", "4", "5", "6"];
+        {
+            let stack = StackFrameDictAllocator::<u64, DropTest>::new_indexed();
+            stack.push(0u64, DropTest("0", &dropped));
+
+            stack.new_scope(|stack| {
+                for (i, name) in (1..=6u64).zip(names) {
+                    stack.push(i, DropTest(name, &dropped));
+                }
+
+                unsafe {stack.retain_frame(|key, _| key % 2 == 0)};
+                assert_eq!(*dropped.borrow(), vec!["1", "3", "5"]);
+
+                let pairs: Vec<_> = stack.iter_frame_rev().map(|(key, value)| (*key, value.0)).collect();
+                assert_eq!(pairs, vec![(2, "2"), (4, "4"), (6, "6")]);
+                assert_eq!(stack.get_in_frame(4u64).map(|value| value.get().0), Some("4"));
+                assert!(stack.get_in_frame(3u64).is_none());
+
+                //pushes land right after the last pair kept
+                stack.push(7u64, DropTest("7", &dropped));
+                assert_eq!(stack.peek().map(|(key, _)| *key), Some(7));
+                assert_eq!(stack.count_in_frame(7u64), 1);
+                dropped.borrow_mut().clear();
+            });
+
+            assert_eq!(*dropped.borrow(), vec!["7", "6", "4", "2"]);
+            assert_eq!(stack.peek().map(|(key, _)| *key), Some(0));
+            dropped.borrow_mut().clear();
+        }
+        assert_eq!(*dropped.borrow(), vec!["0"]);
+
+        //compacting a frame spanning several blocks
+        let stack = StackFrameDictAllocator::<u64, u64>::new();
+        stack.push(1000u64, 1000);
+        stack.new_scope(|stack| {
+            for i in 0..300u64 {
+                stack.push(i, i);
+            }
+
+            unsafe {stack.retain_frame(|key, _| key % 3 == 0)};
+            let keys: Vec<_> = stack.iter_frame_rev().map(|(key, _)| *key).collect();
+            assert_eq!(keys, (0..300).step_by(3).collect::<Vec<_>>());
+
+            for i in 300..400u64 {
+                stack.push(i, i);
+            }
+            assert_eq!(stack.count_in_frame(399u64), 1);
+            assert_eq!(stack.iter_frame_rev().count(), 200);
+
+            unsafe {stack.retain_frame(|_, _| false)};
+            assert!(stack.peek().is_none());
+        });
+        assert_eq!(stack.peek(), Some((&1000, &1000)));
+    }
+
+    #[test]
+    #[cfg(feature = "borrow_check")]
+    pub fn checked_test() {
+        let stack = StackFrameDictAllocator::<&str, (usize, usize)>::new_checked();
+        stack.push("a", (0, 0));
+        stack.push("b", (1, 1));
+
+        //sharing a value is fine
+        let a = stack.get_in_frame("a").unwrap();
+        let also_a = stack.get_in_frame("a").unwrap();
+        assert_eq!(a.get(), also_a.get());
+        drop((a, also_a));
+
+        //so is mutably borrowing it once the other StackRefs are gone
+        let mut a = stack.get_in_frame("a").unwrap();
+        a.get();
+        unsafe {a.get_mut().0 = 1};
+        let mut b = stack.get_in_frame("b").unwrap();
+        unsafe {b.get_mut().0 = 2};
+        drop((a, b));
+
+        //mapping hands the borrow over
+        let first = unsafe {stack.get_in_frame("a").unwrap().map(|a| &mut a.0)};
+        assert_eq!(*first.get(), 1);
+        drop(first);
+
+        stack.new_scope(|stack| {
+            let mut a = stack.get_in_stack("a").unwrap();
+            unsafe {a.get_mut().1 = 3};
+        });
+        assert_eq!(*stack.get_in_frame("a").unwrap().get(), (1, 3));
+    }
+
+    #[test]
+    #[cfg(feature = "borrow_check")]
+    #[should_panic(expected = "value already borrowed")]
+    pub fn checked_get_mut_test() {
+        let stack = StackFrameDictAllocator::<&str, usize>::new_checked();
+        stack.push("a", 0);
+
+        let mut a = stack.get_in_frame("a").unwrap();
+        let mut also_a = stack.get_in_frame("a").unwrap();
+        unsafe {
+            *a.get_mut() = 1;
+            *also_a.get_mut() = 2;
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "borrow_check")]
+    #[should_panic(expected = "value already mutably borrowed")]
+    pub fn checked_get_test() {
+        let stack = StackFrameDictAllocator::<&str, usize>::new_checked();
+        stack.push("a", 0);
+
+        stack.new_scope(|stack| {
+            //frames share the parent's borrows
+            let mut a = stack.get_in_stack("a").unwrap();
+            unsafe {*a.get_mut() = 1};
+            stack.get_in_stack("a").unwrap().get();
+        });
+    }
+
+    #[test]
+    pub fn push_default_test() {
+        let stack = StackFrameDictAllocator::<&str, Vec<u8>>::new();
+        stack.push("a", vec![1]);
+        stack.push_default("b");
+
+        assert_eq!(stack["a"], vec![1]);
+        assert!(stack.get_in_frame("b").unwrap().get().is_empty());
+    }
+
+    #[test]
+    pub fn from_iter_test() {
+        let stack: StackFrameDictAllocator<u64, u64> = (0..500u64).map(|i| (i, i * 3)).collect();
+        for i in 0..500u64 {
+            assert_eq!(stack.get_in_frame(i).map(|value| *value.get()), Some(i * 3));
+        }
+        let keys: Vec<_> = stack.iter_frame_rev().map(|(key, _)| *key).collect();
+        assert_eq!(keys, (0..500).collect::<Vec<_>>());
+
+        let stack: StackFrameDictAllocator<&str, usize> = ["a", "b", "a"].into_iter()
+            .filter(|key| !key.is_empty())
+            .zip(0..)
+            .collect();
+        assert_eq!(stack["a"], 2);
+        assert_eq!(stack.count_in_frame("a"), 2);
+    }
+
+    #[test]
+    pub fn extend_test() {
+        let pairs: Vec<(u64, u64)> = (0..1000).map(|i| (i, i * 2)).collect();
+
+        for stack in [StackFrameDictAllocator::<u64, u64>::new(), StackFrameDictAllocator::new_indexed()] {
+            stack.new_scope(|stack| {
+                (&stack).extend(pairs.iter().copied());
+
+                assert_eq!(stack.count_in_frame(999u64), 1);
+                assert_eq!(stack[500], 1000);
+                let keys: Vec<_> = stack.iter_frame_rev().map(|(key, _)| *key).collect();
+                assert_eq!(keys, (0..1000).collect::<Vec<_>>());
+            });
+        }
+    }
+
+    #[test]
+    pub fn merge_test() {
+        use std::rc::Rc;
+
+        let counter = Rc::new(());
+        let stack = StackFrameDictAllocator::<u64, Rc<()>>::new();
+        stack.push(0u64, counter.clone());
+
+        let other = StackFrameDictAllocator::<u64, Rc<()>>::with_capacity(8);
+        for i in 1..=300u64 {
+            other.push(i, counter.clone());
+        }
+        assert_eq!(Rc::strong_count(&counter), 302);
+
+        stack.merge(other);
+        assert_eq!(Rc::strong_count(&counter), 302);
+        for i in 0..=300u64 {
+            assert!(stack.get_in_frame(i).is_some());
+        }
+        let keys: Vec<_> = stack.iter_frame_rev().map(|(key, _)| *key).collect();
+        assert_eq!(keys, (0..=300).collect::<Vec<_>>());
+
+        //merging a frame made from this allocator
+        stack.new_scope(|stack| {
+            let frame = stack.new_frame();
+            frame.push(1000u64, counter.clone());
+            frame.push(1001u64, counter.clone());
+
+            stack.merge(frame);
+            assert!(stack.get_in_frame(1000u64).is_some());
+            assert!(stack.get_in_frame(1001u64).is_some());
+            assert_eq!(stack.peek().map(|(key, _)| *key), Some(1001));
+            assert_eq!(Rc::strong_count(&counter), 304);
+        });
+
+        assert_eq!(Rc::strong_count(&counter), 302);
+        drop(stack);
+        assert_eq!(Rc::strong_count(&counter), 1);
+    }
+
+    #[test]
+    pub fn frame_to_btreemap_test() {
+        use std::rc::Rc;
+
+        let counter = Rc::new(());
+        let stack = StackFrameDictAllocator::<u64, Rc<()>>::new();
+        stack.push(1000u64, counter.clone());
+
+        let frame = stack.new_frame();
+        for i in 0..300u64 {
+            frame.push((i * 7) % 50, counter.clone());
+        }
+        assert_eq!(Rc::strong_count(&counter), 302);
+
+        let map = frame.frame_to_btreemap();
+        assert_eq!(map.keys().copied().collect::<Vec<_>>(), (0..50).collect::<Vec<_>>());
+        //shadowed values were dropped, and nothing was dropped twice
+        assert_eq!(Rc::strong_count(&counter), 52);
+        drop(map);
+        assert_eq!(Rc::strong_count(&counter), 2);
+
+        //the parent frame is untouched
+        stack.push(2000u64, counter.clone());
+        let map = stack.frame_to_btreemap();
+        assert_eq!(map.keys().copied().collect::<Vec<_>>(), vec![1000, 2000]);
+        drop(map);
+        assert_eq!(Rc::strong_count(&counter), 1);
+
+        let stack = StackFrameDictAllocator::<&str, u64>::new();
+        stack.push("b", 1);
+        stack.push("a", 2);
+        stack.push("b", 3);
+        let map = stack.frame_to_btreemap();
+        assert_eq!(map.into_iter().collect::<Vec<_>>(), vec![("a", 2), ("b", 3)]);
+    }
+
+    #[test]
+    pub fn pop_frame_test() {
+        use std::rc::Rc;
+
+        let counter = Rc::new(());
+        let stack = StackFrameDictAllocator::<u64, Rc<()>>::new();
+        stack.push(0u64, counter.clone());
+
+        unsafe {stack.generate_frame();}
+        for i in 1..100u64 {
+            stack.push(i, counter.clone());
+        }
+        assert_eq!(Rc::strong_count(&counter), 101);
+
+        unsafe {stack.pop_frame();}
+        assert_eq!(Rc::strong_count(&counter), 2);
+        assert!(stack.get_in_frame(0u64).is_some());
+        assert!(stack.get_in_stack(1u64).is_none());
+    }
+
+    #[test]
+    #[cfg(feature = "zeroize")]
+    pub fn zeroize_test() {
+        let stack = StackFrameDictAllocator::<u64, u64>::new();
+
+        unsafe {stack.generate_frame();}
+        let secret = stack.push(0xdead_beef_u64, 0xcafe_babe);
+        let value_ptr = secret.get() as *const u64 as *const u8;
+        let key_ptr = unsafe {value_ptr.sub(StackFrameDictAllocator::<u64, u64>::OFFSET_VALUE)};
+        unsafe {stack.pop_frame();}
+
+        //the block is still owned by the allocator, so its bytes can be read
+        let bytes = unsafe {
+            core::slice::from_raw_parts(key_ptr, StackFrameDictAllocator::<u64, u64>::SIZE_KEY_VALUE)
+        };
+        assert!(bytes.iter().all(|byte| *byte == 0));
+    }
+
+    #[test]
+    pub fn debug_test() {
+        let stack = StackFrameDictAllocator::<&str, u64>::new();
+        stack.push("a", 1);
+
+        stack.new_scope(|stack| {
+            stack.push("b", 2);
+            stack.push("a", 3);
+
+            assert_eq!(format!("{:?}", stack), concat!(
+                "top of stack\n",
+                "\tKey: \"a\", Value: 3\n",
+                "\tKey: \"b\", Value: 2\n",
+                "header\n",
+                "\tKey: \"a\", Value: 1\n",
+                "header\n",
+                "\n1 block(s) of size 1024 bytes have been allocated."
+            ));
+        });
+    }
+
+    #[test]
+    pub fn on_block_alloc_test() {
+        use std::sync::{Arc, Mutex};
+
+        let sizes = Arc::new(Mutex::new(vec![]));
+        let recorded = sizes.clone();
+        let stack = StackFrameDictAllocator::<u64, u64>::builder()
+            .growth(GrowthPolicy::Geometric { factor: 2, cap: 4096 })
+            .on_block_alloc(move |size| recorded.lock().unwrap().push(size))
+            .build();
+
+        //the first block comes with the allocator
+        assert!(sizes.lock().unwrap().is_empty());
+
+        stack.new_scope(|stack| {
+            for i in 0..1000u64 {
+                stack.push(i, i);
+            }
+        });
+        assert_eq!(*sizes.lock().unwrap(), [2048, 4096, 4096, 4096, 4096]);
+
+        //blocks left behind by popped frames are reused, so they aren't reported again
+        stack.new_scope(|stack| {
+            for i in 0..1000u64 {
+                stack.push(i, i);
+            }
+        });
+        assert_eq!(sizes.lock().unwrap().len(), 5);
+    }
+
+    #[test]
+    pub fn block_size_test() {
+        let stack = StackFrameDictAllocator::<u64, u64>::builder()
+            .block_size(64)
+            .build();
+        for i in 0..100u64 {
+            stack.push(i, i * 2);
+        }
+        assert_eq!(*stack.get_in_stack(50u64).unwrap().get(), 100);
+        assert_eq!(stack.iter_visible_stack().count(), 100);
+    }
+
+    #[test]
+    #[should_panic(expected = "a block of 8 bytes is too small, blocks must be at least")]
+    pub fn block_size_too_small_test() {
+        StackFrameDictAllocator::<u64, u64>::builder()
+            .block_size(8)
+            .build();
+    }
+
+    #[test]
+    pub fn growth_test() {
+        use std::rc::Rc;
+
+        let counter = Rc::new(());
+        {
+            let stack = StackFrameDictAllocator::<u64, Rc<()>>::builder()
+                .growth(GrowthPolicy::Geometric { factor: 2, cap: 1 << 16 })
+                .build();
+            stack.push(0u64, counter.clone());
+
+            stack.new_scope(|stack| {
+                for i in 1..2000u64 {
+                    stack.push(i, counter.clone());
+                }
+
+                //pairs are found across blocks of different sizes
+                assert_eq!(stack.get_all_in_frame(1u64).len(), 1);
+                assert!(stack.get_in_stack(0u64).is_some());
+                assert!(stack.get_in_stack(1999u64).is_some());
+                assert_eq!(stack.iter_stack().count(), 2000);
+                assert!(format!("{:?}", stack).contains("block(s) totalling"));
+            });
+            assert_eq!(Rc::strong_count(&counter), 2);
+
+            unsafe {stack.generate_frame();}
+            for i in 1..2000u64 {
+                stack.push(i, counter.clone());
+            }
+            unsafe {stack.pop_frame();}
+            assert_eq!(Rc::strong_count(&counter), 2);
+            assert_eq!(stack.peek().map(|(key, _)| *key), Some(0));
+        }
+
+        assert_eq!(Rc::strong_count(&counter), 1);
+    }
+
+    #[test]
+    pub fn indexed_test() {
+        let stack = StackFrameDictAllocator::<u64, u64>::new_indexed();
+        stack.push(0u64, 0);
+        stack.push(1u64, 1);
+
+        stack.new_scope(|stack| {
+            //spill the frame over multiple blocks
+            for i in 2..500u64 {
+                stack.push(i % 100, i);
+            }
+
+            //the latest push wins
+            assert_eq!(*stack.get_in_frame(1u64).unwrap().get(), 401);
+            assert_eq!(*stack.get_in_frame(99u64).unwrap().get(), 499);
+            assert!(stack.get_in_frame(100u64).is_none());
+            assert_eq!(stack.get_all_in_frame(0u64).len(), 4);
+            assert_eq!(*stack.get_in_stack(0u64).unwrap().get(), 400);
+
+            stack.new_scope(|stack| {
+                //keys of the previous frame are not in the new frame's index
+                assert!(stack.get_in_frame(1u64).is_none());
+                stack.push(1u64, 1000);
+                assert_eq!(*stack.get_in_frame(1u64).unwrap().get(), 1000);
+            });
+
+            assert_eq!(*stack.get_in_frame(1u64).unwrap().get(), 401);
+        });
+
+        assert_eq!(*stack.get_in_frame(1u64).unwrap().get(), 1);
+        assert!(stack.get_in_frame(2u64).is_none());
+
+        //popping a frame rebuilds the index of the frame below it
+        unsafe {stack.generate_frame();}
+        stack.push(1u64, 2);
+        assert_eq!(*stack.get_in_frame(1u64).unwrap().get(), 2);
+        assert!(stack.get_in_frame(0u64).is_none());
+        unsafe {stack.pop_frame();}
+        stack.push(0u64, 3);
+        assert_eq!(*stack.get_in_frame(0u64).unwrap().get(), 3);
+        assert_eq!(*stack.get_in_frame(1u64).unwrap().get(), 1);
+    }
+
+    #[test]
+    pub fn count_test() {
+        let stack = StackFrameDictAllocator::<&str, usize>::new();
+        stack.push("x", 0);
+        stack.push("y", 0);
+
+        stack.new_scope(|stack| {
+            stack.push("x", 1);
+            stack.push("x", 2);
+
+            stack.new_scope(|stack| {
+                for i in 0..100 {
+                    stack.push("x", i);
+                }
+                stack.push("z", 3);
+
+                assert_eq!(stack.count_in_frame("x"), 100);
+                assert_eq!(stack.count_in_stack("x"), 103);
+                assert_eq!(stack.count_in_stack("y"), 1);
+                assert_eq!(stack.count_in_stack("w"), 0);
+            });
+
+            assert_eq!(stack.count_in_frame("x"), 2);
+            assert_eq!(stack.count_in_stack("x"), 3);
+            assert_eq!(stack.count_in_stack("z"), 0);
+        });
+
+        assert_eq!(stack.count_in_stack("x"), 1);
+    }
+
+    #[test]
+    pub fn position_in_frame_test() {
+        let stack = StackFrameDictAllocator::<&str, usize>::new();
+        stack.push("a", 0);
+        stack.push("b", 1);
+        stack.push("c", 2);
+        assert_eq!(stack.position_in_frame("a"), Some(2));
+        assert_eq!(stack.position_in_frame("c"), Some(0));
+        assert_eq!(stack.position_in_frame("d"), None);
+
+        stack.new_scope(|stack| {
+            //only the current frame is searched
+            assert_eq!(stack.position_in_frame("a"), None);
+
+            //enough pairs to span multiple blocks
+            for i in 0..200 {
+                stack.push(if i == 0 {"a"} else {"b"}, i);
+            }
+            assert_eq!(stack.position_in_frame("a"), Some(199));
+            assert_eq!(stack.position_in_frame("b"), Some(0));
+        });
+    }
+
+    #[test]
+    pub fn shadow_count_in_frame_test() {
+        let stack = StackFrameDictAllocator::<&str, usize>::new();
+        stack.push("x", 0);
+        stack.push("y", 0);
+
+        stack.new_scope(|stack| {
+            stack.push("x", 1);
+            stack.push("x", 2);
+            stack.push("x", 3);
+            stack.push("y", 1);
+
+            //shadowing a previous frame's key isn't counted
+            assert_eq!(stack.shadow_count_in_frame("x"), 2);
+            assert_eq!(stack.shadow_count_in_frame("y"), 0);
+            assert_eq!(stack.shadow_count_in_frame("z"), 0);
+        });
+    }
+
+    #[test]
+    pub fn frame_scoped_test() {
+        let stack = StackFrameDictAllocator::<u64, String>::new();
+        stack.push(0u64, "outer".to_string());
+
+        let (total, pairs) = stack.frame_scoped(|stack| {
+            for i in 1..=100u64 {
+                stack.push(i, i.to_string());
+            }
+
+            stack.count_in_stack(50u64)
+        });
+        assert_eq!(total, 1);
+
+        let expected = (1..=100u64).rev()
+            .map(|i| (i, i.to_string()))
+            .collect::<Vec<_>>();
+        assert_eq!(pairs, expected);
+
+        //the frame was popped, only the snapshot is left
+        assert!(stack.get_in_stack(50u64).is_none());
+        assert_eq!(stack.to_vec(), vec![(0, "outer".to_string())]);
+    }
+
+    #[test]
+    pub fn to_vec_test() {
+        let stack = StackFrameDictAllocator::<&str, String>::new();
+        stack.push("outer", "0".to_string());
+
+        let pairs = stack.new_scope(|stack| {
+            for i in 0..100 {
+                stack.push("x", i.to_string());
+            }
+            stack.push("y", "last".to_string());
+
+            stack.to_vec()
+        });
+
+        assert_eq!(pairs.len(), 101);
+        assert_eq!(pairs[0], ("y", "last".to_string()));
+        assert_eq!(pairs[1], ("x", "99".to_string()));
+        assert_eq!(pairs[100], ("x", "0".to_string()));
+        assert_eq!(stack.to_vec(), vec![("outer", "0".to_string())]);
+    }
+
+    #[test]
+    pub fn into_iter_test() {
+        use std::rc::Rc;
+
+        let counter = Rc::new(());
+        let stack = StackFrameDictAllocator::<u64, Rc<()>>::new_indexed();
+        for i in 0..100u64 {
+            stack.push(i, counter.clone());
+        }
+
+        unsafe {
+            stack.generate_frame();
+            *stack.entered_frames.get() += 1;
+        }
+        for i in 100..300u64 {
+            stack.push(i, counter.clone());
+        }
 
-        //we can't use the fun built-in library functions like align_offset,
-        //so we do this math ourselves
-        let value_padding = -(
-            -(Self::SIZE_KEY as isize) % 
-            Self::ALIGN_VALUE as isize
-        ) as usize;
-        let next_key_padding = -(
-            -(Self::SIZE_VALUE as isize) % 
-            Self::ALIGN_KEY as isize
-        ) as usize;
+        let keys: Vec<u64> = stack.into_iter().map(|(key, _)| key).collect();
+        assert_eq!(keys.len(), 300);
+        assert!(keys.into_iter().eq((0..300).rev()));
+        assert_eq!(Rc::strong_count(&counter), 1);
 
-        let key_value_size = Self::SIZE_KEY + value_padding +
-            Self::SIZE_VALUE + next_key_padding;
+        //pairs that weren't yielded are dropped with the iterator
+        let stack = StackFrameDictAllocator::<u64, Rc<()>>::new();
+        for i in 0..300u64 {
+            stack.push(i, counter.clone());
+        }
 
-        let mut curr_block_tail = unsafe {self.get_block_tail()};
-        let mut bytes_remaining = unsafe {*self.buffer_bytes_used.get()};
+        let mut iter = stack.into_iter();
+        let taken: Vec<_> = iter.by_ref().take(150).collect();
+        assert_eq!(taken.last().unwrap().0, 150);
+        assert_eq!(Rc::strong_count(&counter), 301);
 
-        let mut stack_frame = unsafe {(*self.current_frame.get()).as_ref()};
-        let mut peek_ptr = stack_frame.current_frame_ptr;
-        
-        //for the first scope we're looking at, because it's the newest scope
-        //there should be no headers after the current frame,
-        //so we'll use key alignment
-        let mut just_jumped_block = false;
-        let mut expect_key_value_pair = true;
-        let mut stack_frame_ptr_after = {unsafe {
-            let offset_ptr = (stack_frame as *const StackFrameHeader as *mut u8)
-                .add(Self::SIZE_HEADER);
-            let padding = offset_ptr.align_offset(Self::ALIGN_KEY);
-            offset_ptr.add(padding)
-        }};
-
-        println!("top of stack");
-
-        loop {unsafe {
-            if bytes_remaining == 0 {
-                if curr_block_tail.prev_block.is_null() {
-                    unreachable!("{}", concat!(
-                        "the previous block can only be null ",  
-                        "if the block currently being looked at is the first block.  ",  
-                        "In that case, the header logic would've ran first, ", 
-                        "thus this should never be reached"
-                    ))
-                }
+        drop(iter);
+        assert_eq!(Rc::strong_count(&counter), 151);
+        drop(taken);
+        assert_eq!(Rc::strong_count(&counter), 1);
 
-                count_blocks += 1;
+        //frames made from another allocator only give up their own pairs
+        let stack = StackFrameDictAllocator::<u64, Rc<()>>::new();
+        stack.push(0u64, counter.clone());
+        let frame = stack.new_frame();
+        frame.push(1u64, counter.clone());
+        frame.push(2u64, counter.clone());
 
-                bytes_remaining = curr_block_tail.prev_block_bytes_used;
-                peek_ptr = curr_block_tail.prev_block;
+        let keys: Vec<u64> = frame.into_iter().map(|(key, _)| key).collect();
+        assert_eq!(keys, [2, 1]);
+        assert_eq!(stack.peek().map(|(key, _)| *key), Some(0));
+        drop(stack);
+        assert_eq!(Rc::strong_count(&counter), 1);
+    }
 
-                let offset = self.real_size().bytes() - bytes_remaining;
-        
-                curr_block_tail = peek_ptr
-                    .add(offset)
-                    .cast::<BlockTail>()
-                    .as_mut()
-                    .expect("Error grabbing mutable reference to BlockTail");
-
-                //we must check for the case, the first key value pair attached
-                //to this header was in the block we were just looking in
-                //in this case, there should be no padding
-                stack_frame_ptr_after = (
-                    stack_frame 
-                    as *const StackFrameHeader 
-                    as *mut u8
-                ).add(Self::SIZE_HEADER);
-
-                just_jumped_block = true;
-            }
-            
-            if peek_ptr < stack_frame_ptr_after {
-                unreachable!("unexpected operation caused peek_ptr to go past the stack_frame_ptr");
-            } else if peek_ptr == stack_frame_ptr_after {
-                println!("header");
-
-                let Some(new_frame) = stack_frame.previous_frame else {
-                    break;
-                };
-
-                stack_frame = new_frame;
-                peek_ptr = stack_frame.current_frame_ptr;
-
-                //this new header could have zero items
-                just_jumped_block = false;
-                expect_key_value_pair = false;
-                stack_frame_ptr_after = {
-                    let offset_ptr = (stack_frame as *const StackFrameHeader as *mut u8)
-                        .add(Self::SIZE_HEADER);
-                    let padding = offset_ptr.align_offset(Self::ALIGN_HEADER);
-                    offset_ptr.add(padding)
-                };
+    #[test]
+    pub fn first_last_key_in_frame_test() {
+        let stack = StackFrameDictAllocator::<&str, usize>::new();
+        assert_eq!(stack.last_key_in_frame(), None);
+        assert_eq!(stack.first_key_in_frame(), None);
 
-                continue;
-            } else if !expect_key_value_pair || just_jumped_block {
-                just_jumped_block = false;
-                expect_key_value_pair = true;
+        stack.push("a", 0);
+        stack.push("b", 1);
+        assert_eq!(stack.last_key_in_frame(), Some(&"b"));
+        assert_eq!(stack.first_key_in_frame(), Some(&"a"));
+
+        stack.new_scope(|stack| {
+            assert_eq!(stack.last_key_in_frame(), None);
+            assert_eq!(stack.first_key_in_frame(), None);
 
-                stack_frame_ptr_after = {
-                    let offset_ptr = (stack_frame as *const StackFrameHeader as *mut u8)
-                        .add(Self::SIZE_HEADER);
-                    let padding = offset_ptr.align_offset(Self::ALIGN_KEY);
-                    offset_ptr.add(padding)
-                };
+            //across blocks
+            stack.push("first", 0);
+            for i in 0..200 {
+                stack.push("middle", i);
             }
-            
-            peek_ptr = peek_ptr.sub(key_value_size);
-            let key = peek_ptr.cast::<Key>().as_ref().unwrap_unchecked();
-            let value = peek_ptr.add(Self::SIZE_KEY + value_padding)
-                .cast::<Value>().as_ref().unwrap_unchecked();
-            println!("\tKey: {}, Value: {}", key, value);
-        }}
+            stack.push("last", 0);
+            assert_eq!(stack.last_key_in_frame(), Some(&"last"));
+            assert_eq!(stack.first_key_in_frame(), Some(&"first"));
+        });
 
-        println!("\n{} block(s) of size {} bytes have been allocated.\n", 
-            count_blocks, 
-            self.size.bytes()
-        );
+        assert_eq!(stack.last_key_in_frame(), Some(&"b"));
     }
 
-    //TODO add allocated_blocks(&self) -> usize and using_blocks(&self) -> usize functions
-}
+    #[test]
+    pub fn to_btreemap_test() {
+        let stack = StackFrameDictAllocator::<&str, usize>::new();
+        stack.push("shadowed", 0);
+        stack.push("outer", 1);
+        stack.push("shadowed", 2);
 
-impl<'s, Key, Value> Drop for StackFrameDictAllocator<'s, Key, Value> 
-where 
-    Key: Eq + Hash
-{
-    fn drop(&mut self) {
-        //eprintln!("dropping stack frame");
-        unsafe {
-            let current_frame_ptr = (*self.current_frame.get()).as_ptr().cast::<u8>();
-            let mut bytes_remaining = *self.buffer_bytes_used.get();
-            let mut peek_ptr = (*current_frame_ptr.cast::<StackFrameHeader>()).current_frame_ptr;
-            let mut curr_block_tail = self.get_block_tail();
-    
-            //because we're only dropping the current scope,
-            //we can assume the padding after the header
-            //is key padding, because we shouldn't be expecting a header 
-            //after the header we're looking in
-            let stack_frame_ptr_after = {
-                let offset_ptr = current_frame_ptr.add(Self::SIZE_HEADER);
-                let padding = offset_ptr.align_offset(Self::ALIGN_KEY);
-                offset_ptr.add(padding)
-            };
-    
-            //we can't use the fun built-in library functions like align_offset,
-            //so we do this math ourselves
-            let value_padding = -(
-                -(Self::SIZE_KEY as isize) % 
-                Self::ALIGN_VALUE as isize
-            ) as usize;
-            let next_key_padding = -(
-                -(Self::SIZE_VALUE as isize) % 
-                Self::ALIGN_KEY as isize
-            ) as usize;
-    
-            let key_value_size = Self::SIZE_KEY + value_padding +
-                Self::SIZE_VALUE + next_key_padding;
-    
-            //eprintln!("starting search at {:?} until {:?}", peek_ptr, stack_frame_ptr_after);
-            while peek_ptr > stack_frame_ptr_after {
-                // eprintln!("peeking at {:?} until {:?} with {} bytes remaining", 
-                //     peek_ptr, stack_frame_ptr_after, bytes_remaining
-                // );
-                if bytes_remaining == 0 {
-                    if curr_block_tail.prev_block.is_null() {
-                        unreachable!("{}", concat!(
-                            "the previous block can only be null ",  
-                            "if the block currently being looked at is the first block.  ",  
-                            "In that case, the header logic would've ran first, ", 
-                            "thus this should never be reached"
-                        ))
-                    }
-                    bytes_remaining = curr_block_tail.prev_block_bytes_used;
-                    peek_ptr = curr_block_tail.prev_block;
-    
-                    let offset = self.real_size().bytes() - bytes_remaining;
-            
-                    curr_block_tail = peek_ptr
-                        .add(offset)
-                        .cast::<BlockTail>()
-                        .as_mut()
-                        .expect("Error grabbing mutable reference to BlockTail");
-                }
-    
-                //dropping key and value pair
-                peek_ptr = peek_ptr.sub(key_value_size);
-                bytes_remaining -= key_value_size;
-                
-                std::ptr::drop_in_place(peek_ptr as *mut Key);
-                std::ptr::drop_in_place(peek_ptr.add(Self::SIZE_KEY + next_key_padding)
-                    .cast::<Value>()
-                );
-            }
-            
-            if (*self.current_frame.get()).as_ref().previous_frame.is_none() {
-                //eprintln!("dropping whole stack");
-                let mut prev_addr;
-                let mut next_addr = (*self.current_frame.get()).as_ptr() as *mut u8;
+        stack.new_scope(|stack| {
+            stack.push("shadowed", 3);
+            stack.push("inner", 4);
+            stack.push("a", 5);
 
-                while !next_addr.is_null() {
-                    //eprintln!("dropping block of size {} bytes at {:?}", self.size.bytes(), next_addr);
-                    
-                    prev_addr = next_addr;
-                    //eprintln!("grabbing tail at {:?}", next_addr.add(self.real_size().bytes()));
-                    let block_tail = next_addr.add(self.real_size().bytes())
-                        .cast::<BlockTail>().as_ref().unwrap_unchecked();
-                    //eprintln!("successfully grabbed tail");
-                    next_addr = block_tail.next_block;
+            let env: Vec<_> = stack.to_btreemap().into_iter().collect();
+            assert_eq!(env, [("a", 5), ("inner", 4), ("outer", 1), ("shadowed", 3)]);
+        });
 
-                    std::alloc::dealloc(prev_addr, Layout::array::<u8>(self.size.bytes()).expect("fuck"));
-                }
-            }
-        }
+        let env: Vec<_> = stack.to_btreemap().into_iter().collect();
+        assert_eq!(env, [("outer", 1), ("shadowed", 2)]);
     }
-}
 
-#[cfg(test)]
-mod test {
-    use super::*;
+    #[test]
+    pub fn eq_test() {
+        let a = StackFrameDictAllocator::<&str, usize>::new();
+        let b = StackFrameDictAllocator::<&str, usize>::new();
+        assert_eq!(a, b);
+
+        for (i, key) in ["x", "y", "z"].into_iter().enumerate() {
+            a.push(key, i);
+        }
+        assert_ne!(a, b);
+
+        //pushed in a different order, over different frames, with hidden pairs
+        b.push("z", 2);
+        b.push("x", 100);
+        b.new_scope(|b| {
+            b.push("y", 100);
+            b.push("x", 0);
+            assert_ne!(a, b);
+
+            b.push("y", 1);
+            assert_eq!(a, b);
+            assert_eq!(b, a);
+
+            //the same keys mapping to different values
+            b.push("z", 3);
+            assert_ne!(a, b);
+        });
+
+        //a missing key
+        b.push("y", 1);
+        assert_ne!(a, b);
+        b.push("x", 0);
+        assert_eq!(a, b);
+
+        let pool = crate::stack_pool::StackPool::new();
+        let c = StackFrameDictAllocator::<&str, usize, _>::new_in(&pool);
+        (&c).extend([("x", 0), ("y", 1), ("z", 2)]);
+        assert!(a == c);
+    }
 
     #[test]
-    pub fn get_in_frame_test() {
-        let stack = StackFrameDictAllocator::<&str, &str>::new();
-        
-        stack.push("red", "first");
-        stack.push("blue", "first");
-        
+    pub fn to_hashmap_test() {
+        let stack = StackFrameDictAllocator::<&str, usize>::new();
+        stack.push("shadowed", 0);
+        stack.push("outer", 1);
+        stack.push("shadowed", 2);
+
         stack.new_scope(|stack| {
-            stack.push("red", "second");
-        
-            let red = stack.get_in_frame("red").unwrap().get();
-            let blue = stack.get_in_frame("blue");
-            assert_eq!(*red, "second");
-            assert!(blue.is_none());
+            stack.push("shadowed", 3);
+            stack.push("inner", 4);
+
+            stack.new_scope(|stack| {
+                let env = stack.to_hashmap();
+                assert_eq!(env.len(), 3);
+                assert_eq!(env["shadowed"], 3);
+                assert_eq!(env["outer"], 1);
+                assert_eq!(env["inner"], 4);
+            });
         });
-        
-        let red = stack.get_in_frame("red").unwrap().get();
-        let blue = stack.get_in_frame("blue").unwrap().get();
-        assert_eq!(*red, "first");
-        assert_eq!(*blue, "first");
-        
-        //shadow blue
-        stack.push("blue", "second");
-        
-        let blue = stack.get_in_frame("blue").unwrap().get();
-        assert_eq!(*blue, "second");
+
+        let env = stack.to_hashmap();
+        assert_eq!(env.len(), 2);
+        assert_eq!(env["shadowed"], 2);
+        assert_eq!(env["outer"], 1);
     }
 
     #[test]
-    pub fn get_in_stack_test() {
-        let stack = StackFrameDictAllocator::<&str, &str>::new();
-    
-        stack.push("red", "old");
-        stack.push("blue", "old");
-    
+    pub fn capacity_per_block_test() {
+        use std::sync::{Arc, atomic::{AtomicUsize, Ordering}};
+
+        let stack = StackFrameDictAllocator::<u32, u64>::new();
+
+        //1024 bytes, minus the 40 byte tail and the 16 byte root header
+        assert_eq!(stack.usable_block_size(), 1024 - 40 - 16);
+        //minus at most 7 bytes of padding and the byte the top of a block never reaches,
+        //each pair is a padded u32 and a u64
+        assert_eq!(stack.capacity_per_block(), (968 - 7 - 1) / 16);
+
+        let allocs = Arc::new(AtomicUsize::new(0));
+        let counted = allocs.clone();
+        let stack = StackFrameDictAllocator::<u8, u8>::builder()
+            .on_block_alloc(move |_| { counted.fetch_add(1, Ordering::Relaxed); })
+            .build();
+        assert_eq!(stack.capacity_per_block(), 483);
+        for i in 0..stack.capacity_per_block() {
+            stack.push(i as u8, i as u8);
+        }
+        //every pair still fits in the first block
+        assert_eq!(allocs.load(Ordering::Relaxed), 0);
+        stack.push(0u8, 0u8);
+        assert_eq!(allocs.load(Ordering::Relaxed), 1);
+
+        assert_eq!(StackFrameDictAllocator::<(), ()>::new().capacity_per_block(), usize::MAX);
+    }
+
+    #[test]
+    pub fn frame_bytes_test() {
+        let stack = StackFrameDictAllocator::<u64, u64>::new();
+        for i in 0..10u64 {
+            stack.push(i, i);
+        }
+        let parent_bytes = stack.frame_bytes();
+        assert_eq!(parent_bytes, 16 + 10 * 16);
+
         stack.new_scope(|stack| {
-            stack.push("green", "new");
-    
-            let red = stack.get_in_stack("red").unwrap().get();
-            let blue = stack.get_in_stack("blue");
-            let green = stack.get_in_stack("green").unwrap().get();
-            assert_eq!(*red, "old");
-            assert!(blue.is_some());
-            assert_eq!(*green, "new");
-    
-            //shadow blue
-            stack.push("red", "new");
-    
-            let red = stack.get_in_stack("red").unwrap().get();
-            assert_eq!(*red, "new");
+            assert_eq!(stack.frame_bytes(), 16);
+            for i in 0..300u64 {
+                stack.push(i, i);
+            }
+            assert_eq!(stack.frame_bytes(), 16 + 300 * 16);
         });
 
-        let red = stack.get_in_stack("red").unwrap().get();
-        let blue = stack.get_in_stack("blue").unwrap().get();
-        assert_eq!(*red, "old");
-        assert_eq!(*blue, "old");
+        assert_eq!(stack.frame_bytes(), parent_bytes);
     }
 
-    use std::cell::RefCell;
+    #[test]
+    pub fn depth_test() {
+        fn recurse<Kind>(stack: &StackFrameDictAllocator<&str, usize, Global, Kind>, n: usize) {
+            assert_eq!(stack.depth(), n);
+            if n < 50 {
+                stack.new_scope(|stack| {
+                    stack.push("n", n);
+                    recurse(&stack, n + 1);
+                });
+            }
+            assert_eq!(stack.depth(), n);
+        }
 
-    #[doc(hidden)]
-    pub struct DropTest<'d>(&'d str, &'d RefCell<Vec<&'d str>>);
+        let stack = StackFrameDictAllocator::<&str, usize>::new();
+        recurse(&stack, 1);
 
-    impl<'d> Drop for DropTest<'d> {
-        fn drop(&mut self) {
-            let (value, dropped) = (self.0, self.1);
+        let frame = stack.new_frame();
+        assert_eq!(frame.depth(), 2);
+        drop(frame);
 
-            dropped.borrow_mut().push(value);
+        unsafe {
+            let guard = stack.enter_frame();
+            assert_eq!(guard.depth(), 2);
         }
+        assert_eq!(stack.depth(), 1);
     }
 
-    #[doc(hidden)]
-    #[derive(PartialEq, Eq, Hash)]
-    pub struct DropPrint<T>(T) where T : Display;
+    #[test]
+    pub fn is_root_test() {
+        let stack = StackFrameDictAllocator::<&str, usize>::new();
+        stack.push("a", 0);
+        assert!(stack.is_root());
 
-    impl<T> Drop for DropPrint<T> where T : Display {
-        fn drop(&mut self) {
-            println!("{}", self.0);
+        stack.new_scope(|stack| {
+            assert!(!stack.is_root());
+            stack.new_scope(|stack| assert!(!stack.is_root()));
+        });
+
+        let guard = unsafe {stack.enter_frame()};
+        assert!(!guard.is_root());
+        drop(guard);
+        assert!(stack.is_root());
+    }
+
+    #[test]
+    pub fn default_test() {
+        #[derive(Default)]
+        struct Interpreter<'s> {
+            env: StackFrameDictAllocator<'s, &'static str, i64>
         }
+
+        let interpreter = Interpreter::default();
+        interpreter.env.push("x", 1);
+        assert_eq!(*interpreter.env.get_in_frame("x").unwrap().get(), 1);
     }
 
     #[test]
-    pub fn drop_scope_test() {
-        let dropped = RefCell::new(vec![]);
-        {
-            let stack = StackFrameDictAllocator::<DropPrint<&str>, DropTest>::new();
-            stack.push(DropPrint("key1scope1"), DropTest("value1scope1", &dropped));
-            stack.push(DropPrint("key2scope1"), DropTest("value2scope1", &dropped));
-            stack.push(DropPrint("key3scope1"), DropTest("value3scope1", &dropped));
-            stack.new_scope(|stack| {
-                stack.push(DropPrint("key1scope2"), DropTest("value1scope2", &dropped));
-                stack.push(DropPrint("key2scope2"), DropTest("value2scope2", &dropped));
-                stack.push(DropPrint("key3scope2"), DropTest("value3scope2", &dropped));
-            });
+    pub fn with_capacity_test() {
+        for elements in [0, 1, 61, 62, 63, 1000] {
+            let stack = StackFrameDictAllocator::<u8, u64>::with_capacity(elements);
+            let root = unsafe {(*stack.current_frame.get()).as_ptr() as *mut u8};
+            for i in 0..elements {
+                stack.push((i % 256) as u8, i as u64);
+            }
+
+            //every pair is still in the first block
+            let top = unsafe {(*stack.current_frame.get()).as_ref().current_frame_ptr};
+            assert_eq!(top as usize - root as usize, unsafe {*stack.buffer_bytes_used.get()});
+            assert_eq!(stack.to_vec().len(), elements);
         }
+    }
 
-        let compare = vec![
-            "value3scope2", 
-            "value2scope2", 
-            "value1scope2", 
-            "value3scope1", 
-            "value2scope1", 
-            "value1scope1"
-        ];
+    #[test]
+    pub fn over_aligned_test() {
+        #[repr(align(128))]
+        #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+        struct Big(u8);
 
-        assert_eq!(*dropped.borrow(), compare);
+        let stack = StackFrameDictAllocator::<u8, Big>::new();
+        stack.push(0, Big(0));
+
+        stack.new_scope(|stack| {
+            let refs: Vec<_> = (1..=40).map(|i| stack.push(i, Big(i)).get()).collect();
+            for (i, value) in refs.iter().enumerate() {
+                assert_eq!(**value, Big(i as u8 + 1));
+                assert_eq!(*value as *const Big as usize % 128, 0);
+            }
+
+            for i in 0..=40 {
+                assert_eq!(*stack.get_in_stack(i).unwrap().get(), Big(i));
+            }
+        });
     }
 
     #[test]
-    pub fn drop_frame_test() {
-        let dropped = RefCell::new(vec![]);
-        {
-            let stack = StackFrameDictAllocator::<DropPrint<&str>, DropTest>::new();
-            stack.push(DropPrint("key1scope1"), DropTest("value1scope1", &dropped));
-            stack.push(DropPrint("key2scope1"), DropTest("value2scope1", &dropped));
-            stack.push(DropPrint("key3scope1"), DropTest("value3scope1", &dropped));
-            let stack = stack.new_frame();
-            stack.push(DropPrint("key1scope2"), DropTest("value1scope2", &dropped));
-            stack.push(DropPrint("key2scope2"), DropTest("value2scope2", &dropped));
-            stack.push(DropPrint("key3scope2"), DropTest("value3scope2", &dropped));
+    #[should_panic(expected = "can't fit in a block")]
+    pub fn too_large_test() {
+        let stack = StackFrameDictAllocator::<u8, [u8; 2048]>::new();
+        stack.push(0, [0; 2048]);
+    }
+
+    #[test]
+    pub fn try_push_too_large_test() {
+        //a 1024 byte block holds at most 1024 - 40 - 1 bytes, the 40 being the block tail
+        let stack = StackFrameDictAllocator::<u8, [u8; 982]>::new();
+        stack.try_push(0, [0; 982]).unwrap();
+
+        let stack = StackFrameDictAllocator::<u8, [u8; 983]>::new();
+        assert_eq!(
+            stack.try_push(0, [0; 983]).err(),
+            Some(PushError::TooLarge { required: 984, block_capacity: 983 })
+        );
+        assert!(stack.peek().is_none());
+    }
+
+    #[test]
+    pub fn try_new_test() {
+        use std::cell::Cell;
+
+        use crate::test_alloc::LimitedAllocator;
+
+        let limited = LimitedAllocator(Cell::new(0));
+        assert!(matches!(StackFrameDictAllocator::<u64, [u64; 16], _>::try_new_in(&limited), Err(AllocError)));
+        assert!(matches!(StackFrameDictAllocator::<u64, [u64; 16]>::builder().allocator(&limited).try_build(), Err(AllocError)));
+
+        limited.0.set(1);
+        let stack = StackFrameDictAllocator::<u64, [u64; 16], _>::try_new_in(&limited).unwrap();
+        stack.push(1u64, [1; 16]);
+
+        //the allocator is out of blocks, so the stack can't grow past its first block
+        assert!((0..100).any(|_| stack.try_push(2u64, [2; 16]).is_err()));
+    }
+
+    #[test]
+    pub fn get_many_in_frame_test() {
+        for stack in [StackFrameDictAllocator::<i32, i32>::new(), StackFrameDictAllocator::new_indexed()] {
+            stack.push(1000, 0);
+
+            stack.new_scope(|stack| {
+                for i in 0..300 {
+                    stack.push(i % 100, i);
+                }
+
+                let keys = [5, 99, 50, 5, 1000];
+                let many = stack.get_many_in_frame(keys);
+                for (key, value) in keys.into_iter().zip(many) {
+                    let single = stack.get_in_frame(key);
+                    assert_eq!(value.map(|value| *value.get()), single.map(|value| *value.get()));
+                }
+
+                let [a, b, c] = stack.get_many_in_frame([0, 1, 2]);
+                assert_eq!((*a.unwrap().get(), *b.unwrap().get(), *c.unwrap().get()), (200, 201, 202));
+            });
         }
     }
 
     #[test]
-    #[allow(unused_variables)]
-    pub fn empty_drop_test() {
-        let stack_u8_u8 = StackFrameDictAllocator::<DropPrint<u8>, DropPrint<u8>>::new();
-        let stack_u8_u16 = StackFrameDictAllocator::<DropPrint<u8>, DropPrint<u16>>::new();
-        let stack_u8_u32 = StackFrameDictAllocator::<DropPrint<u8>, DropPrint<u32>>::new();
-        let stack_u8_u64 = StackFrameDictAllocator::<DropPrint<u8>, DropPrint<u64>>::new();
-        let stack_u8_u128 = StackFrameDictAllocator::<DropPrint<u8>, DropPrint<u128>>::new();
+    pub fn iter_frame_rev_test() {
+        let stack = StackFrameDictAllocator::<u64, u64>::new();
+        stack.push(1000u64, 1000);
 
-        let stack_u16_u8 = StackFrameDictAllocator::<DropPrint<u16>, DropPrint<u8>>::new();
-        let stack_u16_u16 = StackFrameDictAllocator::<DropPrint<u16>, DropPrint<u16>>::new();
-        let stack_u16_u32 = StackFrameDictAllocator::<DropPrint<u16>, DropPrint<u32>>::new();
-        let stack_u16_u64 = StackFrameDictAllocator::<DropPrint<u16>, DropPrint<u64>>::new();
-        let stack_u16_u128 = StackFrameDictAllocator::<DropPrint<u16>, DropPrint<u128>>::new();
+        stack.new_scope(|stack| {
+            for i in 1..=3u64 {
+                stack.push(i, i * 10);
+            }
+            let pairs: Vec<_> = stack.iter_frame_rev().map(|(key, value)| (*key, *value)).collect();
+            assert_eq!(pairs, vec![(1, 10), (2, 20), (3, 30)]);
 
-        let stack_u32_u8 = StackFrameDictAllocator::<DropPrint<u32>, DropPrint<u8>>::new();
-        let stack_u32_u16 = StackFrameDictAllocator::<DropPrint<u32>, DropPrint<u16>>::new();
-        let stack_u32_u32 = StackFrameDictAllocator::<DropPrint<u32>, DropPrint<u32>>::new();
-        let stack_u32_u64 = StackFrameDictAllocator::<DropPrint<u32>, DropPrint<u64>>::new();
-        let stack_u32_u128 = StackFrameDictAllocator::<DropPrint<u32>, DropPrint<u128>>::new();
+            for i in 4..=300u64 {
+                stack.push(i, i * 10);
+            }
+            let keys: Vec<_> = stack.iter_frame_rev().map(|(key, _)| *key).collect();
+            assert_eq!(keys, (1..=300).collect::<Vec<_>>());
+        });
+    }
 
-        let stack_u64_u8 = StackFrameDictAllocator::<DropPrint<u64>, DropPrint<u8>>::new();
-        let stack_u64_u16 = StackFrameDictAllocator::<DropPrint<u64>, DropPrint<u16>>::new();
-        let stack_u64_u32 = StackFrameDictAllocator::<DropPrint<u64>, DropPrint<u32>>::new();
-        let stack_u64_u64 = StackFrameDictAllocator::<DropPrint<u64>, DropPrint<u64>>::new();
-        let stack_u64_u128 = StackFrameDictAllocator::<DropPrint<u64>, DropPrint<u128>>::new();
+    #[test]
+    pub fn push_while_holding_test() {
+        for stack in [StackFrameDictAllocator::<u64, u64>::new(), StackFrameDictAllocator::new_indexed()] {
+            stack.push(0u64, 0);
 
-        let stack_u128_u8 = StackFrameDictAllocator::<DropPrint<u128>, DropPrint<u8>>::new();
-        let stack_u128_u16 = StackFrameDictAllocator::<DropPrint<u128>, DropPrint<u16>>::new();
-        let stack_u128_u32 = StackFrameDictAllocator::<DropPrint<u128>, DropPrint<u32>>::new();
-        let stack_u128_u64 = StackFrameDictAllocator::<DropPrint<u128>, DropPrint<u64>>::new();
-        let stack_u128_u128 = StackFrameDictAllocator::<DropPrint<u128>, DropPrint<u128>>::new();
+            stack.new_scope(|stack| {
+                let outer = stack.get_in_stack(0u64).unwrap().get();
+
+                //every push reads a value grabbed before it,
+                //and the pushes spill over to new blocks
+                let mut previous = stack.push(1u64, *outer + 1).get();
+                for i in 2..300u64 {
+                    let held = stack.get_in_frame(i - 1).unwrap().get();
+                    assert!(core::ptr::eq(held, previous));
+                    previous = stack.push(i, *held + 1).get();
+                }
+
+                stack.new_scope(|stack| {
+                    let captured = stack.get_in_stack(150u64).unwrap().get();
+                    stack.push(150u64, *captured * 2);
+                    assert_eq!(*stack.get_in_stack(150u64).unwrap().get(), 300);
+                    assert_eq!(*captured, 150);
+                });
+
+                assert_eq!(*outer, 0);
+                assert_eq!(*previous, 299);
+                for i in 0..300u64 {
+                    assert_eq!(*stack.get_in_stack(i).unwrap().get(), i);
+                }
+            });
+        }
     }
 }
\ No newline at end of file
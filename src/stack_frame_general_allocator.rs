@@ -0,0 +1,688 @@
+//! The StackFrameGeneralAllocator allows the creation of "Frames"
+//! where values of any type can be pushed onto this frame.
+//! Frames only exist in the scope they're created in using
+//! the [new_frame](crate::stack_frame_general_allocator::StackFrameGeneralAllocator::new_frame)
+//! function.  At the end of a frame's scope, the entire frame is popped,
+//! and the StackFrameGeneralAllocator will continue pushing items
+//! onto the previous frame.  Every value is followed by an element tail,
+//! recording where the value lives and how to drop it,
+//! so frames of differently sized values can still be walked and dropped.
+
+use core::{cell::UnsafeCell, marker::PhantomData, ptr::NonNull};
+
+use alloc::alloc::{handle_alloc_error, Layout};
+
+use allocator_api2::alloc::{Allocator, Global};
+
+use crate::{block_tail::BlockTail, max_align, error::{AllocError, PushError}, growth_policy::GrowthPolicy, stack_frame_header::StackFrameHeader, stack_ref::safe_ref::StackRef, stack_size::StackSize};
+
+/// The StackFrameGeneralAllocator allows the creation of "Frames"
+/// where values of any type can be pushed onto this frame.
+/// Frames only exist in the scope they're created in using
+/// the [new_frame](crate::stack_frame_general_allocator::StackFrameGeneralAllocator::new_frame)
+/// function.  At the end of a frame's scope, the entire frame is popped,
+/// dropping every value in it with the destructor of its own type,
+/// and the StackFrameGeneralAllocator will continue pushing items
+/// onto the previous frame.  Like the
+/// [StackFrameAllocator](crate::stack_frame_allocator::StackFrameAllocator),
+/// only one StackRef can exist at a time for a given value.
+///
+/// # Examples
+///
+/// ```edition2020
+/// # use stack_frame_allocators::stack_frame_general_allocator::StackFrameGeneralAllocator;
+///
+/// let stack = StackFrameGeneralAllocator::new();
+/// let number = stack.push(1u8).get();
+/// let name = stack.push(String::from("one")).get();
+///
+/// stack.new_scope(|stack| {
+///     let mut list = stack.push(vec![*number; 3]);
+///     list.push(2);
+///
+///     let pair = stack.push((name.as_str(), list.len())).get();
+///     assert_eq!(*pair, ("one", 4));
+///
+///     //this frame will pop here,
+///     //the Vec and the tuple are dropped
+///     //and are not reachable past this point
+/// });
+/// ```
+pub struct StackFrameGeneralAllocator<'s, A: Allocator = Global> {
+    pub(crate) size: StackSize,
+    pub(crate) growth: GrowthPolicy,
+    pub(crate) allocator: A,
+    pub(crate) current_frame: UnsafeCell<NonNull<StackFrameHeader<'s>>>,
+    pub(crate) buffer_bytes_used: UnsafeCell<usize>,
+    pub(crate) block_size: UnsafeCell<StackSize>
+}
+
+/// Placed right after every value, so the top of a frame
+/// always ends with the tail of its latest value.
+struct ElementTail {
+    /// Where the top of the frame was in the value's block before the value was pushed,
+    /// or the start of the block if the value spilled over to a new block.
+    start: *mut u8,
+    value: *mut u8,
+    drop: unsafe fn(*mut u8)
+}
+
+unsafe fn drop_value<T>(value: *mut u8) {
+    crate::drop_element(value.cast::<T>());
+}
+
+impl<'s> StackFrameGeneralAllocator<'s> {
+    /// Creates a new StackFrameGeneralAllocator
+    ///
+    /// # Examples
+    ///
+    /// ```edition2020
+    /// # use stack_frame_allocators::stack_frame_general_allocator::StackFrameGeneralAllocator;
+    ///
+    /// let stack = StackFrameGeneralAllocator::new();
+    /// stack.push(1u64);
+    /// stack.push("two");
+    /// ```
+    pub fn new() -> Self {
+        Self::new_in(Global)
+    }
+}
+
+impl<'s, A: Allocator> StackFrameGeneralAllocator<'s, A> {
+    const SIZE_HEADER:   usize = core::mem::size_of::<StackFrameHeader>();
+    const SIZE_TAIL:     usize = core::mem::size_of::<BlockTail>();
+    const SIZE_ELEMENT:  usize = core::mem::size_of::<ElementTail>();
+
+    const ALIGN_HEADER:     usize = core::mem::align_of::<StackFrameHeader>();
+    const ALIGN_TAIL:       usize = core::mem::align_of::<BlockTail>();
+    const ALIGN_ELEMENT:    usize = core::mem::align_of::<ElementTail>();
+
+    //values can have any alignment, so they're padded on every push,
+    //blocks are only aligned so the block tail at the end of the block stays aligned
+    const ALIGN_BLOCK:      usize = max_align(max_align(Self::ALIGN_HEADER, Self::ALIGN_TAIL), Self::ALIGN_ELEMENT);
+
+    /// Creates a new StackFrameGeneralAllocator,
+    /// whose blocks are allocated and deallocated with `allocator`.
+    ///
+    /// Frames made from this StackFrameGeneralAllocator use clones of `allocator`.
+    ///
+    /// # Examples
+    ///
+    /// ```edition2020
+    /// # #![cfg_attr(feature = "nightly", feature(allocator_api))]
+    /// # use stack_frame_allocators::stack_frame_general_allocator::StackFrameGeneralAllocator;
+    /// use allocator_api2::alloc::Global;
+    ///
+    /// let stack = StackFrameGeneralAllocator::new_in(Global);
+    /// stack.push(1usize);
+    /// ```
+    pub fn new_in(allocator: A) -> Self {
+        let size = StackSize::default();
+        let allocated_block;
+        unsafe {
+            let layout = Self::block_layout(size);
+            allocated_block = match allocator.allocate(layout) {
+                Ok(block) => block.cast::<u8>().as_ptr(),
+                Err(_) => handle_alloc_error(layout)
+            };
+
+            (Self::block_tail_at(allocated_block, size) as *mut BlockTail).write(BlockTail {
+                prev_block: core::ptr::null_mut(),
+                prev_block_bytes_used: 0 /* we'll never read this value if prev_block is null */,
+                prev_block_size: 0,
+                next_block: core::ptr::null_mut(),
+                next_block_size: 0
+            });
+
+            (allocated_block as *mut StackFrameHeader).write(StackFrameHeader {
+                previous_frame: None,
+                current_frame_ptr: allocated_block.add(Self::SIZE_HEADER)
+            });
+        }
+
+        StackFrameGeneralAllocator {
+            size,
+            growth: GrowthPolicy::default(),
+            allocator,
+            current_frame: UnsafeCell::new(unsafe {
+                NonNull::new_unchecked(allocated_block as *mut StackFrameHeader)
+            }),
+            buffer_bytes_used: UnsafeCell::new(Self::SIZE_HEADER),
+            block_size: UnsafeCell::new(size)
+        }
+    }
+
+    /// Creates a new frame to push elements onto.
+    ///
+    /// Creates a new scope where a new frame lives,
+    /// at the end of the scope, the new frame and all its items
+    /// will be popped.
+    ///
+    /// Whatever `scope` returns is handed back once the new frame has been popped,
+    /// so the result can't borrow from the new frame.
+    ///
+    /// # Examples
+    ///
+    /// ```edition2020
+    /// # use stack_frame_allocators::stack_frame_general_allocator::StackFrameGeneralAllocator;
+    ///
+    /// let stack = StackFrameGeneralAllocator::new();
+    /// let base = stack.push(2u32).get();
+    ///
+    /// let len = stack.new_scope(|stack| {
+    ///     let word = stack.push(String::from("frame")).get();
+    ///     let doubled = stack.push(word.len() as u32 * *base).get();
+    ///     *doubled
+    /// });
+    ///
+    /// assert_eq!(len, 10);
+    /// ```
+    pub fn new_scope<'n, R, F>(&self, scope: F) -> R
+    where
+        's : 'n,
+        A : Clone,
+        F : FnOnce(StackFrameGeneralAllocator<'n, A>) -> R
+    {
+        unsafe {
+            let new_frame = StackFrameGeneralAllocator {
+                size: self.size,
+                growth: self.growth,
+                allocator: self.allocator.clone(),
+                current_frame: UnsafeCell::new(*self.current_frame.get()),
+                buffer_bytes_used: UnsafeCell::new(*self.buffer_bytes_used.get()),
+                block_size: UnsafeCell::new(*self.block_size.get())
+            };
+
+            new_frame.generate_frame();
+
+            //scope will automatically pop the new frame,
+            //before its result is handed back
+            scope(new_frame)
+        }
+    }
+
+    /// Creates a new frame to push elements onto within the same scope
+    ///
+    /// [new_scope](crate::stack_frame_general_allocator::StackFrameGeneralAllocator::new_scope)
+    /// is generally preferred, however there are some use cases where you should be able to create
+    /// a new frame and give ownership to it to a new scope.  A general rule of thumb
+    /// is to never assign the return value to variable.
+    ///
+    /// # Examples
+    ///
+    /// ```edition2020
+    /// # use stack_frame_allocators::stack_frame_general_allocator::StackFrameGeneralAllocator;
+    ///
+    /// fn describe(stack: StackFrameGeneralAllocator, id: u8) -> usize {
+    ///     let label = stack.push(format!("item {}", id)).get();
+    ///     label.len()
+    /// }
+    ///
+    /// let stack = StackFrameGeneralAllocator::new();
+    /// assert_eq!(describe(stack.new_frame(), 7), 6);
+    /// ```
+    pub fn new_frame(&self) -> StackFrameGeneralAllocator<'s, A> where A: Clone {
+        let stack;
+        unsafe {
+            stack = StackFrameGeneralAllocator {
+                size: self.size,
+                growth: self.growth,
+                allocator: self.allocator.clone(),
+                current_frame: UnsafeCell::new(*self.current_frame.get()),
+                buffer_bytes_used: UnsafeCell::new(*self.buffer_bytes_used.get()),
+                block_size: UnsafeCell::new(*self.block_size.get())
+            };
+
+            stack.generate_frame();
+        }
+
+        return stack;
+    }
+
+    unsafe fn generate_frame(&self) {
+        let header_padding = (*(*self.current_frame.get()).as_ptr())
+            .current_frame_ptr
+            .align_offset(Self::ALIGN_HEADER);
+        let can_push_to_block = *self.buffer_bytes_used.get() +
+            header_padding + Self::SIZE_HEADER <
+            self.real_size().bytes();
+
+        let mem = if can_push_to_block {
+            *self.buffer_bytes_used.get() += header_padding + Self::SIZE_HEADER;
+
+            (*(*self.current_frame.get()).as_ptr())
+                .current_frame_ptr
+                .add(header_padding)
+        } else {
+            let (next_block, next_block_size) = self.link_next_block(
+                self.get_block_tail(),
+                (*self.current_frame.get()).as_ref().current_frame_ptr,
+                *self.buffer_bytes_used.get(),
+                *self.block_size.get()
+            );
+
+            //the header sits at the start of the next block,
+            //so the only bytes used in that block are the header's
+            *self.buffer_bytes_used.get() = Self::SIZE_HEADER;
+            *self.block_size.get() = next_block_size;
+
+            next_block
+        };
+
+        (mem as *mut StackFrameHeader).write(StackFrameHeader {
+            previous_frame: Some((*self.current_frame.get()).as_ref()),
+            current_frame_ptr: mem.add(Self::SIZE_HEADER)
+        });
+
+        *self.current_frame.get() = NonNull::new_unchecked(mem as *mut StackFrameHeader);
+    }
+
+    /// The usable size of the block the top of the current frame lives in,
+    /// the tail end of every block is reserved for its BlockTail.
+    #[inline]
+    fn real_size(&self) -> StackSize {
+        StackSize(unsafe {(*self.block_size.get()).bytes()} - Self::SIZE_TAIL)
+    }
+
+    //the tail lives in a block the allocator points to, not in the allocator itself
+    #[allow(clippy::mut_from_ref)]
+    unsafe fn get_block_tail(&self) -> &mut BlockTail {
+        let offset = self.real_size().bytes() - *self.buffer_bytes_used.get();
+
+        return (*self.current_frame.get())
+            .as_ref()
+            .current_frame_ptr
+            .add(offset)
+            .cast::<BlockTail>()
+            .as_mut()
+            .expect("Error grabbing mutable reference to BlockTail");
+    }
+
+    /// Pushes a value of any type into the current frame,
+    /// returning a StackRef to the value.
+    ///
+    /// The value is dropped with its own destructor once its frame is popped.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the value can't fit in a block.
+    ///
+    /// # Examples
+    ///
+    /// ```edition2020
+    /// # use stack_frame_allocators::stack_frame_general_allocator::StackFrameGeneralAllocator;
+    ///
+    /// let stack = StackFrameGeneralAllocator::new();
+    ///
+    /// let a = stack.push(1u8).get();
+    /// let b = stack.push([2u64; 4]).get();
+    /// let c = stack.push("three").get();
+    ///
+    /// assert_eq!(*a, 1);
+    /// assert_eq!(b[3], 2);
+    /// assert_eq!(*c, "three");
+    /// ```
+    pub fn push<'a, T: 's>(&'a self, value: T) -> StackRef<'a, 's, T> {
+        self.try_push(value).unwrap_or_else(|error| self.push_failed(error))
+    }
+
+    /// Pushes a value of any type into the current frame,
+    /// returning a StackRef to the value,
+    /// or a PushError if a new block was needed but couldn't be allocated,
+    /// or if the value can't fit in an empty block.
+    ///
+    /// # Examples
+    ///
+    /// ```edition2020
+    /// # use stack_frame_allocators::stack_frame_general_allocator::StackFrameGeneralAllocator;
+    ///
+    /// let stack = StackFrameGeneralAllocator::new();
+    ///
+    /// let a = stack.try_push(vec![1, 2, 3]).unwrap().get();
+    /// assert_eq!(a.len(), 3);
+    /// ```
+    pub fn try_push<'a, T: 's>(&'a self, value: T) -> Result<StackRef<'a, 's, T>, PushError> {
+        let size_value = core::mem::size_of::<T>();
+        let align_value = core::mem::align_of::<T>();
+
+        unsafe {
+            let stack_frame = (*self.current_frame.get()).as_ptr();
+            let current_frame_ptr = (*stack_frame).current_frame_ptr;
+            let bytes_used = *self.buffer_bytes_used.get();
+            let block_size = *self.block_size.get();
+
+            let (mut start, mut value_ptr, mut tail_ptr, mut offset) =
+                Self::element_layout(current_frame_ptr, size_value, align_value);
+
+            if bytes_used + offset < block_size.bytes() - Self::SIZE_TAIL {
+                *self.buffer_bytes_used.get() = bytes_used + offset;
+            } else {
+                let (next_block, next_block_size) = self.try_link_next_block(
+                    self.get_block_tail(),
+                    current_frame_ptr,
+                    bytes_used,
+                    block_size
+                )?;
+
+                (start, value_ptr, tail_ptr, offset) =
+                    Self::element_layout(next_block, size_value, align_value);
+
+                //the top of a block never reaches its tail
+                let block_capacity = next_block_size.bytes() - Self::SIZE_TAIL - 1;
+                if offset > block_capacity {
+                    return Err(PushError::TooLarge { required: offset, block_capacity });
+                }
+
+                *self.block_size.get() = next_block_size;
+                *self.buffer_bytes_used.get() = offset;
+            }
+
+            value_ptr.cast::<T>().write(value);
+            tail_ptr.cast::<ElementTail>().write(ElementTail {
+                start,
+                value: value_ptr,
+                drop: drop_value::<T>
+            });
+            (*stack_frame).current_frame_ptr = start.add(offset);
+
+            return Ok(StackRef {
+                value: value_ptr.cast::<T>(),
+                phantom: PhantomData
+            });
+        }
+    }
+
+    /// Lays out a value followed by its element tail, starting at `start`,
+    /// returning `start`, where the value and the tail go,
+    /// and how many bytes the value and tail take up, including padding.
+    fn element_layout(start: *mut u8, size_value: usize, align_value: usize) -> (*mut u8, *mut u8, *mut u8, usize) {
+        let value_ptr = start.wrapping_add(start.align_offset(align_value));
+        let value_end = value_ptr.wrapping_add(size_value);
+        let tail_ptr = value_end.wrapping_add(value_end.align_offset(Self::ALIGN_ELEMENT));
+        let offset = tail_ptr as usize + Self::SIZE_ELEMENT - start as usize;
+
+        (start, value_ptr, tail_ptr, offset)
+    }
+
+    /// Drops every value in the current frame, from the last push to the first,
+    /// following element tails down each block and block tails back to previous blocks.
+    unsafe fn drop_frame(&self) {
+        let stack_frame = (*self.current_frame.get()).as_ptr();
+        let header_ptr = stack_frame as *mut u8;
+
+        let mut top = (*stack_frame).current_frame_ptr;
+        let mut bytes_used = *self.buffer_bytes_used.get();
+        let mut real_size = self.real_size().bytes();
+
+        loop {
+            let block_start = top.sub(bytes_used);
+            let header_in_block = block_start <= header_ptr &&
+                header_ptr < block_start.add(real_size);
+
+            //if the header lives in a previous block,
+            //the frame's values in this block start at the beginning of the block
+            let items_start = if header_in_block {
+                header_ptr.add(Self::SIZE_HEADER)
+            } else {
+                block_start
+            };
+
+            while top > items_start {
+                let element_tail = top.sub(Self::SIZE_ELEMENT).cast::<ElementTail>().read();
+                (element_tail.drop)(element_tail.value);
+                top = element_tail.start;
+            }
+
+            if header_in_block {
+                return;
+            }
+
+            let block_tail = Self::block_tail_at(block_start, StackSize(real_size + Self::SIZE_TAIL));
+            top = block_tail.prev_block;
+            bytes_used = block_tail.prev_block_bytes_used;
+            real_size = block_tail.prev_block_size - Self::SIZE_TAIL;
+        }
+    }
+
+    /// Gets the block after the block `top` lives in, alongside its size,
+    /// allocating it according to the growth policy if it doesn't exist yet.
+    ///
+    /// The tail of the next block is pointed back to `top`.
+    unsafe fn try_link_next_block(
+        &self,
+        block_tail: &mut BlockTail,
+        top: *mut u8,
+        bytes_used: usize,
+        block_size: StackSize
+    ) -> Result<(*mut u8, StackSize), AllocError> {
+        //if there is no next block, create one
+        if block_tail.next_block.is_null() {
+            let next_block_size = self.growth.next_block_size(block_size);
+            let allocated_block = self.try_allocate_block(next_block_size)?;
+
+            (Self::block_tail_at(allocated_block, next_block_size) as *mut BlockTail).write(BlockTail {
+                prev_block: top,
+                prev_block_bytes_used: bytes_used,
+                prev_block_size: block_size.bytes(),
+                next_block: core::ptr::null_mut(),
+                next_block_size: 0
+            });
+
+            block_tail.next_block = allocated_block;
+            block_tail.next_block_size = next_block_size.bytes();
+        } else {
+            //the next block was left behind by a frame that has since been popped,
+            //so its tail still points back into that frame
+            let next_block_tail = Self::block_tail_at(
+                block_tail.next_block,
+                StackSize(block_tail.next_block_size)
+            );
+            next_block_tail.prev_block = top;
+            next_block_tail.prev_block_bytes_used = bytes_used;
+        }
+
+        return Ok((block_tail.next_block, StackSize(block_tail.next_block_size)));
+    }
+
+    unsafe fn link_next_block(
+        &self,
+        block_tail: &mut BlockTail,
+        top: *mut u8,
+        bytes_used: usize,
+        block_size: StackSize
+    ) -> (*mut u8, StackSize) {
+        self.try_link_next_block(block_tail, top, bytes_used, block_size)
+            .unwrap_or_else(|_| handle_alloc_error(
+                Self::block_layout(self.growth.next_block_size(block_size))
+            ))
+    }
+
+    /// Panics with `error`, or reports it to the global alloc error handler
+    /// if a new block couldn't be allocated.
+    fn push_failed(&self, error: PushError) -> ! {
+        match error {
+            PushError::Alloc(_) => handle_alloc_error(self.next_block_layout()),
+            PushError::TooLarge { .. } => panic!("{}", error)
+        }
+    }
+
+    fn next_block_layout(&self) -> Layout {
+        Self::block_layout(self.growth.next_block_size(unsafe {*self.block_size.get()}))
+    }
+
+    fn block_layout(size: StackSize) -> Layout {
+        Layout::from_size_align(size.bytes(), Self::ALIGN_BLOCK)
+            .expect("block size should never exceed isize::MAX")
+    }
+
+    fn try_allocate_block(&self, size: StackSize) -> Result<*mut u8, AllocError> {
+        self.allocator
+            .allocate(Self::block_layout(size))
+            .map(|block| block.cast::<u8>().as_ptr())
+            .map_err(|_| AllocError)
+    }
+
+    unsafe fn deallocate_block(&self, block: *mut u8, size: StackSize) {
+        self.allocator.deallocate(NonNull::new_unchecked(block), Self::block_layout(size));
+    }
+
+    unsafe fn block_tail_at<'b>(block: *mut u8, size: StackSize) -> &'b mut BlockTail {
+        block.add(size.bytes() - Self::SIZE_TAIL)
+            .cast::<BlockTail>()
+            .as_mut()
+            .expect("Error grabbing mutable reference to BlockTail")
+    }
+}
+
+/// Creates a new StackFrameGeneralAllocator, the same as
+/// [new](crate::stack_frame_general_allocator::StackFrameGeneralAllocator::new).
+impl<'s> Default for StackFrameGeneralAllocator<'s> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'s, A: Allocator> Drop for StackFrameGeneralAllocator<'s, A> {
+    fn drop(&mut self) {
+        unsafe {
+            //we're only dropping the current frame,
+            //previous frames are dropped by the allocators that created them
+            self.drop_frame();
+
+            if (*self.current_frame.get()).as_ref().previous_frame.is_none() {
+                //the root header always sits at the start of the first block
+                let mut next_addr = (*self.current_frame.get()).as_ptr() as *mut u8;
+                let mut next_size = self.size;
+
+                while !next_addr.is_null() {
+                    let (block, size) = (next_addr, next_size);
+                    let block_tail = Self::block_tail_at(block, size);
+                    next_addr = block_tail.next_block;
+                    next_size = StackSize(block_tail.next_block_size);
+
+                    self.deallocate_block(block, size);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::{cell::RefCell, string::String, vec, vec::Vec};
+
+    #[doc(hidden)]
+    pub struct DropTest<'d>(&'d str, &'d RefCell<Vec<&'d str>>);
+
+    impl<'d> Drop for DropTest<'d> {
+        fn drop(&mut self) {
+            self.1.borrow_mut().push(self.0);
+        }
+    }
+
+    #[repr(align(64))]
+    #[doc(hidden)]
+    pub struct Aligned(u8);
+
+    #[test]
+    pub fn mixed_types_test() {
+        let stack = StackFrameGeneralAllocator::new();
+
+        let a = stack.push(1u8).get();
+        let b = stack.push(Aligned(2)).get();
+        let c = stack.push(()).get();
+        let d = stack.push(String::from("four")).get();
+        let e = stack.push([5u16; 3]).get();
+
+        assert_eq!(*a, 1);
+        assert_eq!(b.0, 2);
+        assert_eq!(b as *const Aligned as usize % 64, 0);
+        assert_eq!(*c, ());
+        assert_eq!(d, "four");
+        assert_eq!(*e, [5, 5, 5]);
+    }
+
+    #[test]
+    pub fn drop_scope_test() {
+        let dropped = RefCell::new(vec![]);
+        let stack = StackFrameGeneralAllocator::new();
+
+        stack.push(DropTest("a", &dropped));
+        stack.push(7u64);
+
+        stack.new_scope(|stack| {
+            stack.push(DropTest("b", &dropped));
+            stack.push(String::from("between"));
+            stack.push(DropTest("c", &dropped));
+
+            stack.new_scope(|stack| {
+                stack.push(DropTest("d", &dropped));
+            });
+            assert_eq!(*dropped.borrow(), vec!["d"]);
+        });
+        assert_eq!(*dropped.borrow(), vec!["d", "c", "b"]);
+
+        drop(stack);
+        assert_eq!(*dropped.borrow(), vec!["d", "c", "b", "a"]);
+    }
+
+    #[test]
+    pub fn spill_test() {
+        let dropped = RefCell::new(vec![]);
+        let names: Vec<String> = (0..300).map(|i| i.to_string()).collect();
+        let stack = StackFrameGeneralAllocator::new();
+
+        stack.new_scope(|stack| {
+            let mut refs = vec![];
+            for (i, name) in names.iter().enumerate() {
+                if i % 2 == 0 {
+                    stack.push(DropTest(name, &dropped));
+                } else {
+                    refs.push((i, stack.push(i as u32 * 3).get()));
+                }
+            }
+
+            for (i, value) in refs {
+                assert_eq!(*value, i as u32 * 3);
+            }
+        });
+
+        let expected: Vec<&str> = names.iter().step_by(2).rev().map(String::as_str).collect();
+        assert_eq!(*dropped.borrow(), expected);
+    }
+
+    #[test]
+    pub fn new_frame_test() {
+        let dropped = RefCell::new(vec![]);
+        let stack = StackFrameGeneralAllocator::new();
+        stack.push(DropTest("root", &dropped));
+
+        for _ in 0..3 {
+            let frame = stack.new_frame();
+            for _ in 0..100 {
+                frame.push(DropTest("frame", &dropped));
+                frame.push([0u8; 13]);
+            }
+        }
+        assert_eq!(dropped.borrow().len(), 300);
+
+        drop(stack);
+        assert_eq!(dropped.borrow().last(), Some(&"root"));
+    }
+
+    #[test]
+    #[should_panic(expected = "can't fit in a block")]
+    pub fn too_large_test() {
+        let stack = StackFrameGeneralAllocator::new();
+        stack.push([0u8; 2048]);
+    }
+
+    #[test]
+    pub fn default_test() {
+        let stack: StackFrameGeneralAllocator = Default::default();
+        let a = stack.push(1u8).get();
+        assert_eq!(*a, 1);
+    }
+}
\ No newline at end of file
@@ -0,0 +1,167 @@
+//! A pool recycling the blocks of dropped allocators,
+//! so creating and dropping many short lived allocators
+//! doesn't allocate new blocks every time.
+
+use core::{alloc::Layout, cell::RefCell, ptr::NonNull};
+
+use alloc::vec::Vec;
+
+use allocator_api2::alloc::{AllocError, Allocator, Global};
+
+use crate::stack_frame_allocator::StackFrameAllocator;
+
+/// Keeps the blocks of dropped allocators around,
+/// handing them back out to the next allocators made from the pool.
+///
+/// The pool is itself an [Allocator](allocator_api2::alloc::Allocator),
+/// blocks are only freed once the pool is dropped.
+/// Any of the crate's allocators can be made from a reference to the pool with their `new_in` function,
+/// [allocator](crate::stack_pool::StackPool::allocator) is a shorthand for the
+/// [StackFrameAllocator](crate::stack_frame_allocator::StackFrameAllocator).
+///
+/// # Examples
+///
+/// ```edition2020
+/// # use stack_frame_allocators::stack_pool::StackPool;
+/// use stack_frame_allocators::stack_frame_dict_allocator::StackFrameDictAllocator;
+///
+/// let pool = StackPool::new();
+///
+/// for i in 0..100 {
+///     //after the first iteration, every block comes from the pool
+///     let stack = pool.allocator::<u64>();
+///     stack.push(i);
+///
+///     let dict = StackFrameDictAllocator::<u64, u64, _>::new_in(&pool);
+///     dict.push(i, i);
+/// }
+///
+/// assert_eq!(pool.free_blocks(), 2);
+/// ```
+pub struct StackPool<A: Allocator = Global> {
+    allocator: A,
+    free_blocks: RefCell<Vec<(NonNull<u8>, Layout)>>
+}
+
+impl StackPool {
+    /// Creates a new empty StackPool.
+    pub fn new() -> Self {
+        Self::new_in(Global)
+    }
+}
+
+impl Default for StackPool {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<A: Allocator> StackPool<A> {
+    /// Creates a new empty StackPool,
+    /// whose blocks are allocated and deallocated with `allocator`.
+    pub fn new_in(allocator: A) -> Self {
+        StackPool {
+            allocator,
+            free_blocks: RefCell::new(Vec::new())
+        }
+    }
+
+    /// Creates a new StackFrameAllocator,
+    /// whose blocks are taken from and given back to this pool.
+    pub fn allocator<'s, Value>(&self) -> StackFrameAllocator<'s, Value, &Self> {
+        StackFrameAllocator::new_in(self)
+    }
+
+    /// Gets the amount of blocks waiting in the pool to be reused.
+    pub fn free_blocks(&self) -> usize {
+        self.free_blocks.borrow().len()
+    }
+}
+
+unsafe impl<A: Allocator> Allocator for StackPool<A> {
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        let mut free_blocks = self.free_blocks.borrow_mut();
+
+        //blocks are only reused for the exact same layout,
+        //so they're always deallocated with the layout they were allocated with
+        if let Some(i) = free_blocks.iter().position(|(_, free_layout)| *free_layout == layout) {
+            let (block, _) = free_blocks.swap_remove(i);
+            return Ok(NonNull::slice_from_raw_parts(block, layout.size()));
+        }
+
+        return self.allocator.allocate(layout);
+    }
+
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        self.free_blocks.borrow_mut().push((ptr, layout));
+    }
+}
+
+impl<A: Allocator> Drop for StackPool<A> {
+    fn drop(&mut self) {
+        for (block, layout) in self.free_blocks.get_mut().drain(..) {
+            unsafe {self.allocator.deallocate(block, layout)};
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::{cell::Cell, rc::Rc};
+
+    use crate::stack_frame_dict_allocator::StackFrameDictAllocator;
+
+    #[derive(Clone)]
+    struct CountingAllocator(Rc<Cell<usize>>);
+
+    unsafe impl Allocator for CountingAllocator {
+        fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+            self.0.set(self.0.get() + 1);
+            Global.allocate(layout)
+        }
+
+        unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+            Global.deallocate(ptr, layout)
+        }
+    }
+
+    #[test]
+    pub fn reuse_test() {
+        let allocations = Rc::new(Cell::new(0));
+        let pool = StackPool::new_in(CountingAllocator(allocations.clone()));
+
+        //warm up the pool with the most blocks a single allocator needs
+        {
+            let stack = pool.allocator::<u64>();
+            for i in 0..300 {
+                stack.push(i);
+            }
+        }
+        let warm = allocations.get();
+        assert_eq!(pool.free_blocks(), warm);
+
+        for n in 0..1000 {
+            let stack = pool.allocator::<u64>();
+            stack.new_scope(|stack| {
+                for i in 0..n % 300 {
+                    stack.push(i);
+                }
+            });
+        }
+        assert_eq!(allocations.get(), warm);
+
+        //blocks with the same layout are shared between allocators
+        drop(StackFrameDictAllocator::<u64, u64, _>::new_in(&pool));
+        assert_eq!(allocations.get(), warm);
+
+        //blocks of a different layout aren't reused
+        #[repr(align(64))]
+        struct Aligned(#[allow(dead_code)] u8);
+
+        drop(pool.allocator::<Aligned>());
+        assert_eq!(allocations.get(), warm + 1);
+        assert_eq!(pool.free_blocks(), warm + 1);
+    }
+}
@@ -21,7 +21,103 @@ pub mod unsafe_ref {
     //TODO are compared instead of their contents, however this would make using the Allocator
     //TODO less ergonomic 
 
-    use std::marker::PhantomData;
+    use core::marker::PhantomData;
+    #[cfg(feature = "borrow_check")]
+    use core::cell::{Cell, RefCell};
+
+    #[cfg(feature = "borrow_check")]
+    use alloc::collections::BTreeMap;
+
+    /// How every value of a checked StackFrameDictAllocator is currently borrowed,
+    /// by the address of the value.  A positive count is the amount of StackRefs
+    /// sharing the value, -1 means a single StackRef mutably borrows it.
+    #[cfg(feature = "borrow_check")]
+    pub(crate) type BorrowTable = RefCell<BTreeMap<*const u8, isize>>;
+
+    /// The borrow a StackRef holds on its value,
+    /// given back to the allocator's [BorrowTable] when the StackRef is dropped.
+    #[cfg(feature = "borrow_check")]
+    pub(crate) struct BorrowFlag<'a> {
+        borrows: Option<&'a BorrowTable>,
+        value: *const u8,
+        state: Cell<isize>
+    }
+
+    #[cfg(feature = "borrow_check")]
+    impl<'a> BorrowFlag<'a> {
+        pub(crate) fn new(borrows: Option<&'a BorrowTable>, value: *const u8) -> Self {
+            BorrowFlag {
+                borrows,
+                value,
+                state: Cell::new(0)
+            }
+        }
+
+        fn shared(&self) {
+            let Some(borrows) = self.borrows else {
+                return;
+            };
+            if self.state.get() != 0 {
+                return;
+            }
+
+            let mut borrows = borrows.borrow_mut();
+            let count = borrows.entry(self.value).or_insert(0);
+            if *count < 0 {
+                panic!("value already mutably borrowed");
+            }
+
+            *count += 1;
+            self.state.set(1);
+        }
+
+        fn exclusive(&self) {
+            let Some(borrows) = self.borrows else {
+                return;
+            };
+            if self.state.get() < 0 {
+                return;
+            }
+
+            //a shared borrow held by this StackRef can be upgraded
+            let mut borrows = borrows.borrow_mut();
+            let count = borrows.entry(self.value).or_insert(0);
+            if *count != self.state.get() {
+                panic!("value already borrowed");
+            }
+
+            *count = -1;
+            self.state.set(-1);
+        }
+    }
+
+    #[cfg(feature = "borrow_check")]
+    impl<'a> Clone for BorrowFlag<'a> {
+        //a clone starts out without borrowing the value
+        fn clone(&self) -> Self {
+            BorrowFlag::new(self.borrows, self.value)
+        }
+    }
+
+    #[cfg(feature = "borrow_check")]
+    impl<'a> Drop for BorrowFlag<'a> {
+        fn drop(&mut self) {
+            let Some(borrows) = self.borrows else {
+                return;
+            };
+            if self.state.get() == 0 {
+                return;
+            }
+
+            let mut borrows = borrows.borrow_mut();
+            if let Some(count) = borrows.get_mut(&self.value) {
+                *count -= self.state.get();
+                if *count == 0 {
+                    borrows.remove(&self.value);
+                }
+            }
+        }
+    }
 
     /// Returned by StackFrameAllocator, StackFrameGeneralAllocator, and StackFrameDictAllocator
     /// 
@@ -35,9 +131,18 @@ pub mod unsafe_ref {
     /// that the borrow checker rules are followed.  The user can avoid 
     /// [get_mut](crate::stack_ref::StackRef::get_mut) by only using
     /// Allocators where values are wrapped in a type with interior mutability
-
+    ///
+    /// With the `borrow_check` feature, StackRefs from an allocator made with
+    /// [new_checked](crate::stack_frame_dict_allocator::StackFrameDictAllocator::new_checked)
+    /// keep track of how they borrow their value, like a RefCell does.
+    /// [get](crate::stack_ref::unsafe_ref::StackRef::get) panics if another StackRef
+    /// mutably borrows the value, and [get_mut](crate::stack_ref::unsafe_ref::StackRef::get_mut)
+    /// panics if any other StackRef borrows the value.  A StackRef holds its borrow until it's dropped,
+    /// references outliving the StackRef they came from aren't tracked.
     pub struct StackRef<'a, T> {
         pub(crate) value: *mut T,
+        #[cfg(feature = "borrow_check")]
+        pub(crate) borrow: BorrowFlag<'a>,
         pub(crate) phantom: PhantomData<&'a T>
     }
 
@@ -70,7 +175,10 @@ pub mod unsafe_ref {
         /// assert_eq!(*c, 69);
         /// ```
         pub fn get(&self) -> &'a T {
-            unsafe {self.value.as_ref_unchecked()}
+            #[cfg(feature = "borrow_check")]
+            self.borrow.shared();
+
+            unsafe {&*self.value}
         }
 
         /// Because StackRefs can be dynamically obtained
@@ -82,6 +190,11 @@ pub mod unsafe_ref {
         /// a Interior Mutable structure like RefCell.
         /// See also [get](crate::stack_ref::StackRef::get).
         /// 
+        /// # Safety
+        /// 
+        /// No other reference to the value can be alive
+        /// for as long as the returned mutable reference is.
+        /// 
         /// # Examples
         /// 
         /// ```edition2020
@@ -118,7 +231,181 @@ pub mod unsafe_ref {
         /// *a = 1;
         /// ```
         pub unsafe fn get_mut(&mut self) -> &'a mut T {
-            unsafe {self.value.as_mut_unchecked()}
+            #[cfg(feature = "borrow_check")]
+            self.borrow.exclusive();
+
+            unsafe {&mut *self.value}
+        }
+
+        /// Gets the raw pointer to the value StackRef points to.
+        ///
+        /// The pointer is only valid until the Frame the value is in is popped.
+        ///
+        /// # Examples
+        ///
+        /// ```edition2020
+        /// # use stack_frame_allocators::stack_frame_dict_allocator::StackFrameDictAllocator;
+        ///
+        /// let stack = StackFrameDictAllocator::<&str, usize>::new();
+        /// let a = stack.push("a", 1);
+        ///
+        /// assert_eq!(unsafe {*a.as_ptr()}, 1);
+        /// ```
+        pub fn as_ptr(&self) -> *const T {
+            self.value
+        }
+
+        /// Gets the raw mutable pointer to the value StackRef points to.
+        ///
+        /// The pointer is only valid until the Frame the value is in is popped.
+        /// Writing through it has the same obligations as
+        /// [get_mut](crate::stack_ref::unsafe_ref::StackRef::get_mut),
+        /// since other StackRefs can point to the same value.
+        pub fn as_mut_ptr(&mut self) -> *mut T {
+            self.value
+        }
+
+        /// Creates a StackRef from a raw pointer,
+        /// such as one handed back from across an FFI boundary.
+        ///
+        /// # Safety
+        ///
+        /// `ptr` must point to a value living in a Frame of a StackFrameDictAllocator,
+        /// for example a pointer obtained from
+        /// [as_ptr](crate::stack_ref::unsafe_ref::StackRef::as_ptr),
+        /// and that Frame must not be popped for as long as `'a`.
+        /// Nothing ties `'a` to the Frame, so choosing a lifetime
+        /// that outlives the Frame leaves the StackRef dangling.
+        ///
+        /// # Examples
+        ///
+        /// ```edition2020
+        /// # use stack_frame_allocators::stack_frame_dict_allocator::StackFrameDictAllocator;
+        /// use stack_frame_allocators::stack_ref::unsafe_ref::StackRef;
+        ///
+        /// let stack = StackFrameDictAllocator::<&str, usize>::new();
+        /// let ptr = stack.push("a", 1).as_ptr() as *mut usize;
+        ///
+        /// let a = unsafe {StackRef::from_raw(ptr)};
+        /// assert_eq!(*a.get(), 1);
+        /// ```
+        pub unsafe fn from_raw(ptr: *mut T) -> StackRef<'a, T> {
+            StackRef {
+                value: ptr,
+                #[cfg(feature = "borrow_check")]
+                borrow: BorrowFlag::new(None, ptr.cast()),
+                phantom: PhantomData
+            }
+        }
+
+        /// Makes a StackRef to a part of the value StackRef points to,
+        /// such as a field of a struct, living as long as the original StackRef.
+        ///
+        /// # Safety
+        ///
+        /// `f` is handed a mutable reference to the value,
+        /// so this has the same obligations as
+        /// [get_mut](crate::stack_ref::unsafe_ref::StackRef::get_mut).
+        ///
+        /// # Examples
+        ///
+        /// ```edition2020
+        /// # use stack_frame_allocators::stack_frame_dict_allocator::StackFrameDictAllocator;
+        ///
+        /// let stack = StackFrameDictAllocator::<&str, (usize, usize)>::new();
+        /// let point = stack.push("point", (1, 2));
+        ///
+        /// let y = unsafe {point.map(|point| &mut point.1)};
+        /// assert_eq!(*y.get(), 2);
+        /// ```
+        pub unsafe fn map<U, F>(mut self, f: F) -> StackRef<'a, U>
+        where
+            F: FnOnce(&mut T) -> &mut U
+        {
+            let value = f(self.get_mut());
+
+            //the mutable borrow of the whole value is handed over to the part of it
+            StackRef {
+                value,
+                #[cfg(feature = "borrow_check")]
+                borrow: self.borrow,
+                phantom: PhantomData
+            }
+        }
+
+        /// Checks if two StackRefs point to the same value,
+        /// rather than to equal values like `==` does.
+        ///
+        /// # Examples
+        ///
+        /// ```edition2020
+        /// # use stack_frame_allocators::stack_frame_dict_allocator::StackFrameDictAllocator;
+        ///
+        /// let stack = StackFrameDictAllocator::<&str, usize>::new();
+        /// let a = stack.push("a", 1);
+        /// let also_a = stack.get_in_frame("a").unwrap();
+        /// let b = stack.push("b", 1);
+        ///
+        /// assert!(a.ptr_eq(&also_a));
+        /// assert!(!a.ptr_eq(&b));
+        /// assert!(a == b);
+        /// ```
+        pub fn ptr_eq(&self, other: &StackRef<'_, T>) -> bool {
+            core::ptr::eq(self.value, other.value)
+        }
+    }
+
+    /// Makes another StackRef to the same value.
+    ///
+    /// Unlike [safe_ref::StackRef](crate::stack_ref::safe_ref::StackRef),
+    /// several StackRefs can already point to the same value,
+    /// so cloning one doesn't break any guarantees.
+    /// With the `borrow_check` feature, the clone doesn't share the original's borrow,
+    /// it borrows the value on its own once it's used.
+    ///
+    /// # Examples
+    ///
+    /// ```edition2020
+    /// # use stack_frame_allocators::stack_frame_dict_allocator::StackFrameDictAllocator;
+    ///
+    /// let stack = StackFrameDictAllocator::<&str, usize>::new();
+    /// let a = stack.push("a", 1);
+    /// let copy = a.clone();
+    ///
+    /// assert!(a.ptr_eq(&copy));
+    /// assert_eq!(*copy.get(), 1);
+    /// ```
+    impl<'a, T> Clone for StackRef<'a, T> {
+        fn clone(&self) -> Self {
+            StackRef {
+                value: self.value,
+                #[cfg(feature = "borrow_check")]
+                borrow: self.borrow.clone(),
+                phantom: PhantomData
+            }
+        }
+    }
+
+    /// Compares the values two StackRefs point to,
+    /// use [ptr_eq](crate::stack_ref::unsafe_ref::StackRef::ptr_eq)
+    /// to check if they point to the same value.
+    ///
+    /// # Examples
+    ///
+    /// ```edition2020
+    /// # use stack_frame_allocators::stack_frame_dict_allocator::StackFrameDictAllocator;
+    ///
+    /// let stack = StackFrameDictAllocator::<&str, usize>::new();
+    /// let a = stack.push("a", 1);
+    /// let b = stack.push("b", 1);
+    /// let c = stack.push("c", 2);
+    ///
+    /// assert!(a == b);
+    /// assert!(a != c);
+    /// ```
+    impl<'a, 'b, T: PartialEq> PartialEq<StackRef<'b, T>> for StackRef<'a, T> {
+        fn eq(&self, other: &StackRef<'b, T>) -> bool {
+            self.get() == other.get()
         }
     }
 }
@@ -127,7 +414,7 @@ pub mod unsafe_ref {
 /// and borrowing rules are validated at compile time by the borrow checker.
 /// StackRefs grabbed from a [StackFrameAllocator] will be [safe_ref::StackRef]
 pub mod safe_ref {
-    use std::marker::PhantomData;
+    use core::{marker::PhantomData, ops::{Deref, DerefMut}};
 
     /// Returned by StackFrameAllocator, StackFrameGeneralAllocator, and StackFrameDictAllocator
     /// 
@@ -141,13 +428,14 @@ pub mod safe_ref {
     /// that the borrow checker rules are followed.  The user can avoid 
     /// [get_mut](crate::stack_ref::StackRef::get_mut) by only using
     /// Allocators where values are wrapped in a type with interior mutability
-
-    pub struct StackRef<'a, T> {
+    pub struct StackRef<'a, 's, T: ?Sized> {
         pub(crate) value: *mut T,
-        pub(crate) phantom: PhantomData<&'a T>
+        /// `'a` borrows the allocator that pushed the value,
+        /// `'s` is the lifetime of the allocator itself.
+        pub(crate) phantom: PhantomData<(&'a T, &'s ())>
     }
 
-    impl<'a, T> StackRef<'a, T> {
+    impl<'a, 's, T: ?Sized> StackRef<'a, 's, T> {
         /// Grabs an immutable reference to the value StackRef points to
         /// 
         /// StackRef's will guarantee that any reference created by a StackRef
@@ -169,7 +457,7 @@ pub mod safe_ref {
         /// assert_eq!(*c, 69);
         /// ```
         pub fn get(&self) -> &'a T {
-            unsafe {self.value.as_ref_unchecked()}
+            unsafe {&*self.value}
         }
 
         /// Grabs a mutable reference to the value StackRef points to.
@@ -204,7 +492,199 @@ pub mod safe_ref {
         /// assert_eq!(*c, 69);
         /// ```
         pub fn get_mut(&mut self) -> &'a mut T {
-            unsafe {self.value.as_mut_unchecked()}
+            unsafe {&mut *self.value}
+        }
+
+        /// Gets the raw pointer to the value StackRef points to.
+        ///
+        /// The pointer is only valid until the Frame the value is in is popped.
+        ///
+        /// # Examples
+        ///
+        /// ```edition2020
+        /// # use stack_frame_allocators::stack_frame_allocator::StackFrameAllocator;
+        ///
+        /// let stack = StackFrameAllocator::<usize>::new();
+        /// let a = stack.push(1);
+        ///
+        /// assert_eq!(unsafe {*a.as_ptr()}, 1);
+        /// ```
+        pub fn as_ptr(&self) -> *const T {
+            self.value
+        }
+
+        /// Gets the raw mutable pointer to the value StackRef points to.
+        ///
+        /// The pointer is only valid until the Frame the value is in is popped,
+        /// and only while this StackRef is not used to access the value.
+        ///
+        /// # Examples
+        ///
+        /// ```edition2020
+        /// # use stack_frame_allocators::stack_frame_allocator::StackFrameAllocator;
+        ///
+        /// let stack = StackFrameAllocator::<usize>::new();
+        /// let mut a = stack.push(1);
+        ///
+        /// unsafe {*a.as_mut_ptr() = 2;}
+        /// assert_eq!(*a.get(), 2);
+        /// ```
+        pub fn as_mut_ptr(&mut self) -> *mut T {
+            self.value
+        }
+
+        /// Consumes the StackRef, returning a mutable reference to the value
+        /// that lives as long as the allocator's `'s`,
+        /// instead of as long as the borrow of the allocator that pushed it.
+        ///
+        /// # Safety
+        ///
+        /// The reference can't be used after the Frame the value is in is popped,
+        /// which for the root Frame is when the allocator is dropped.
+        /// The same rules as [get_mut](crate::stack_ref::safe_ref::StackRef::get_mut) apply,
+        /// no other reference to the value can be used while this reference is alive.
+        ///
+        /// # Examples
+        ///
+        /// ```edition2020
+        /// # use stack_frame_allocators::stack_frame_allocator::StackFrameAllocator;
+        ///
+        /// //the reference outlives the borrow of stack, but not its 's
+        /// fn push_root<'s>(stack: &StackFrameAllocator<'s, usize>, value: usize) -> &'s mut usize {
+        ///     unsafe {stack.push(value).leak()}
+        /// }
+        ///
+        /// let stack = StackFrameAllocator::<usize>::new();
+        /// let a = push_root(&stack, 1);
+        /// let b = push_root(&stack, 2);
+        ///
+        /// *a += *b;
+        /// assert_eq!(*a, 3);
+        /// ```
+        ///
+        /// The reference can't be stretched past `'s`.
+        ///
+        /// ```compile_fail
+        /// # use stack_frame_allocators::stack_frame_allocator::StackFrameAllocator;
+        ///
+        /// fn push_static<'s>(stack: &StackFrameAllocator<'s, usize>) -> &'static mut usize {
+        ///     unsafe {stack.push(1).leak()}
+        /// }
+        /// ```
+        pub unsafe fn leak(self) -> &'s mut T {
+            &mut *self.value
+        }
+
+        /// Creates a StackRef from a raw pointer,
+        /// such as one handed back from across an FFI boundary.
+        ///
+        /// # Safety
+        ///
+        /// `ptr` must point to a value living in a Frame of a StackFrameAllocator,
+        /// for example a pointer obtained from
+        /// [as_mut_ptr](crate::stack_ref::safe_ref::StackRef::as_mut_ptr),
+        /// and that Frame must not be popped for as long as `'a`.
+        /// Nothing ties `'a` to the Frame, so choosing a lifetime
+        /// that outlives the Frame leaves the StackRef dangling.
+        ///
+        /// Safe StackRefs are assumed to be the only way to reach their value,
+        /// so no other StackRef or reference to the value may be used for as long as `'a`.
+        ///
+        /// # Examples
+        ///
+        /// ```edition2020
+        /// # use stack_frame_allocators::stack_frame_allocator::StackFrameAllocator;
+        /// use stack_frame_allocators::stack_ref::safe_ref::StackRef;
+        ///
+        /// let stack = StackFrameAllocator::<usize>::new();
+        /// let ptr = stack.push(1).as_mut_ptr();
+        ///
+        /// let mut a = unsafe {StackRef::from_raw(ptr)};
+        /// *a.get_mut() += 1;
+        /// assert_eq!(*a.get(), 2);
+        /// ```
+        pub unsafe fn from_raw(ptr: *mut T) -> StackRef<'a, 's, T> {
+            StackRef {
+                value: ptr,
+                phantom: PhantomData
+            }
+        }
+
+        /// Makes a StackRef to a part of the value StackRef points to,
+        /// such as a field of a struct, living as long as the original StackRef,
+        /// like [RefMut::map](core::cell::RefMut::map).
+        ///
+        /// The original StackRef is consumed, so the part
+        /// is the only way left to reach the value.
+        ///
+        /// # Examples
+        ///
+        /// ```edition2020
+        /// # use stack_frame_allocators::stack_frame_allocator::StackFrameAllocator;
+        ///
+        /// struct Point {
+        ///     x: usize,
+        ///     y: usize
+        /// }
+        ///
+        /// let stack = StackFrameAllocator::<Point>::new();
+        /// let point = stack.push(Point { x: 1, y: 2 });
+        ///
+        /// let mut y = point.map(|point| &mut point.y);
+        /// *y.get_mut() += 1;
+        /// assert_eq!(*y.get(), 3);
+        /// ```
+        pub fn map<U: ?Sized, F>(mut self, f: F) -> StackRef<'a, 's, U>
+        where
+            F: FnOnce(&mut T) -> &mut U
+        {
+            StackRef {
+                value: f(self.get_mut()),
+                phantom: PhantomData
+            }
+        }
+    }
+
+    /// Only one safe StackRef can point to a value at any given time,
+    /// so it can be dereferenced directly instead of going through
+    /// [get](crate::stack_ref::safe_ref::StackRef::get).
+    ///
+    /// # Examples
+    ///
+    /// ```edition2020
+    /// # use stack_frame_allocators::stack_frame_allocator::StackFrameAllocator;
+    ///
+    /// let stack = StackFrameAllocator::<Vec<usize>>::new();
+    /// let list = stack.push(vec![1, 2, 3]);
+    ///
+    /// assert_eq!(list.len(), 3);
+    /// ```
+    impl<'a, 's, T: ?Sized> Deref for StackRef<'a, 's, T> {
+        type Target = T;
+
+        fn deref(&self) -> &T {
+            self.get()
+        }
+    }
+
+    /// Only one safe StackRef can point to a value at any given time,
+    /// so it can be mutably dereferenced directly instead of going through
+    /// [get_mut](crate::stack_ref::safe_ref::StackRef::get_mut).
+    ///
+    /// # Examples
+    ///
+    /// ```edition2020
+    /// # use stack_frame_allocators::stack_frame_allocator::StackFrameAllocator;
+    ///
+    /// let stack = StackFrameAllocator::<usize>::new();
+    /// let mut a = stack.push(5);
+    /// *a += 1;
+    ///
+    /// assert_eq!(*a, 6);
+    /// ```
+    impl<'a, 's, T: ?Sized> DerefMut for StackRef<'a, 's, T> {
+        fn deref_mut(&mut self) -> &mut T {
+            self.get_mut()
         }
     }
 }
\ No newline at end of file
@@ -1,11 +1,11 @@
 #[allow(missing_docs)]
 
-#[derive(Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub(crate) struct StackSize(pub(crate) usize);
 
 impl StackSize {
     pub fn from_num_bytes(bytes: usize) -> Self {
-        StackSize(bytes * std::mem::size_of::<u8>())
+        StackSize(bytes * core::mem::size_of::<u8>())
     }
 
     pub fn bytes(self) -> usize {
@@ -0,0 +1,250 @@
+use crate::{block_tail::BlockTail, stack_frame_header::StackFrameHeader};
+
+/// Walks the items of a stack from the top (last push)
+/// down to the bottom (first push), following block tails
+/// whenever a frame spans multiple blocks, and following
+/// frame headers whenever a frame has been fully walked.
+///
+/// Blocks can differ in size, the size of each block is read
+/// from the tail of the block after it.
+///
+/// Items are assumed to be laid out with a fixed stride,
+/// where the first item of a frame in any given block is placed
+/// at the first address aligned to `align` after the frame's header,
+/// or after the start of the block if the header lives in a previous block.
+#[derive(Clone)]
+pub(crate) struct StackWalker<'a> {
+    stack_frame: &'a StackFrameHeader<'a>,
+    peek_ptr: *mut u8,
+    bytes_remaining: usize,
+    real_size: usize,
+    stride: usize,
+    align: usize,
+    blocks_visited: usize,
+    block_bytes_visited: usize
+}
+
+impl<'a> StackWalker<'a> {
+    const SIZE_HEADER: usize = core::mem::size_of::<StackFrameHeader>();
+    const SIZE_TAIL: usize = core::mem::size_of::<BlockTail>();
+
+    /// Creates a walker starting at the top of `stack_frame`.
+    ///
+    /// # Safety
+    ///
+    /// `bytes_used` must be the amount of bytes used in the block
+    /// the top of `stack_frame` lives in, `real_size` must be the usable size
+    /// of that block, and every item on the stack must be laid out with
+    /// the given `stride` and `align`.
+    pub(crate) unsafe fn new(
+        stack_frame: &'a StackFrameHeader<'a>,
+        bytes_used: usize,
+        real_size: usize,
+        stride: usize,
+        align: usize
+    ) -> Self {
+        StackWalker {
+            stack_frame,
+            peek_ptr: stack_frame.current_frame_ptr,
+            bytes_remaining: bytes_used,
+            real_size,
+            stride,
+            align,
+            blocks_visited: 1,
+            block_bytes_visited: real_size + Self::SIZE_TAIL
+        }
+    }
+
+    /// The amount of blocks the walker has been in so far,
+    /// including the block it started in.
+    pub(crate) fn blocks_visited(&self) -> usize {
+        self.blocks_visited
+    }
+
+    /// The total size in bytes of every block the walker has been in so far.
+    pub(crate) fn block_bytes_visited(&self) -> usize {
+        self.block_bytes_visited
+    }
+
+    /// The size in bytes of the block the walker is currently in.
+    pub(crate) fn block_size(&self) -> usize {
+        self.real_size + Self::SIZE_TAIL
+    }
+
+    /// The pointer the walker is currently at,
+    /// and how many bytes of its block come before it.
+    pub(crate) fn position(&self) -> (*mut u8, usize) {
+        (self.peek_ptr, self.bytes_remaining)
+    }
+
+    fn block_start(&self) -> *mut u8 {
+        self.peek_ptr.wrapping_sub(self.bytes_remaining)
+    }
+
+    fn header_in_block(&self) -> bool {
+        let block_start = self.block_start();
+        let header_ptr = self.stack_frame as *const StackFrameHeader as *mut u8;
+
+        block_start <= header_ptr && header_ptr < block_start.wrapping_add(self.real_size)
+    }
+
+    unsafe fn block_tail(&self) -> &'a BlockTail {
+        self.block_start()
+            .add(self.real_size)
+            .cast::<BlockTail>()
+            .as_ref()
+            .expect("Error grabbing reference to BlockTail")
+    }
+
+    /// Steps down to the next item in the current frame,
+    /// returning None once the frame's header is reached.
+    pub(crate) fn next_in_frame(&mut self) -> Option<*mut u8> {
+        loop {
+            let header_in_block = self.header_in_block();
+            let block_start = self.block_start();
+
+            //if the header lives in a previous block,
+            //the frame's items in this block start at the beginning of the block
+            let items_start = if header_in_block {
+                let header_ptr = self.stack_frame as *const StackFrameHeader as *mut u8;
+                header_ptr.wrapping_add(Self::SIZE_HEADER)
+            } else {
+                block_start
+            };
+            let items_start = items_start.wrapping_add(items_start.align_offset(self.align));
+
+            if self.stride != 0 && self.peek_ptr > items_start {
+                //items are laid out back to back from items_start,
+                //so stepping down can never skip past it
+                debug_assert!(
+                    self.bytes_remaining >= self.stride &&
+                        self.peek_ptr as usize - items_start as usize >= self.stride,
+                    "stepped past the start of a frame's items, the stride doesn't match how items were pushed"
+                );
+
+                self.peek_ptr = self.peek_ptr.wrapping_sub(self.stride);
+                self.bytes_remaining -= self.stride;
+
+                return Some(self.peek_ptr);
+            }
+
+            if header_in_block {
+                //an empty frame's top sits right after its header, before any padding
+                let header_end = (self.stack_frame as *const StackFrameHeader as *mut u8)
+                    .wrapping_add(Self::SIZE_HEADER);
+                debug_assert!(
+                    self.stride == 0 || self.peek_ptr == items_start || self.peek_ptr == header_end,
+                    "the walk stopped between a frame's header and its first item"
+                );
+
+                return None;
+            }
+
+            debug_assert!(
+                self.stride == 0 || self.peek_ptr == items_start,
+                "the walk stopped between the start of a block and its first item"
+            );
+
+            let block_tail = unsafe {self.block_tail()};
+            if block_tail.prev_block.is_null() {
+                unreachable!("{}", concat!(
+                    "the previous block can only be null ",
+                    "if the block currently being looked at is the first block.  ",
+                    "The first block always holds the root header, ",
+                    "thus this should never be reached"
+                ))
+            }
+
+            self.move_to_prev_block(block_tail);
+        }
+    }
+
+    fn move_to_prev_block(&mut self, block_tail: &BlockTail) {
+        self.peek_ptr = block_tail.prev_block;
+        self.bytes_remaining = block_tail.prev_block_bytes_used;
+        self.real_size = block_tail.prev_block_size - Self::SIZE_TAIL;
+        self.blocks_visited += 1;
+        self.block_bytes_visited += block_tail.prev_block_size;
+    }
+
+    /// Moves on to the frame before the current frame,
+    /// returning false if the current frame is the root frame.
+    ///
+    /// Any items left in the current frame are skipped.
+    pub(crate) fn next_frame(&mut self) -> bool {
+        while self.next_in_frame().is_some() {}
+
+        let Some(previous_frame) = self.stack_frame.previous_frame else {
+            return false;
+        };
+
+        let block_start = self.block_start();
+        let previous_frame_ptr = previous_frame.current_frame_ptr;
+        let header_ptr = self.stack_frame as *const StackFrameHeader as *mut u8;
+
+        if block_start <= previous_frame_ptr && previous_frame_ptr <= header_ptr {
+            debug_assert!(
+                self.bytes_remaining >= self.peek_ptr as usize - previous_frame_ptr as usize,
+                "the previous frame's top lies before the start of its block"
+            );
+            self.bytes_remaining -= self.peek_ptr as usize - previous_frame_ptr as usize;
+            self.peek_ptr = previous_frame_ptr;
+        } else {
+            //the header was placed at the start of a new block,
+            //so the previous frame's top lives in the previous block
+            let block_tail = unsafe {self.block_tail()};
+            self.move_to_prev_block(block_tail);
+        }
+
+        self.stack_frame = previous_frame;
+
+        true
+    }
+
+    /// Counts the bytes the current frame takes up, from its header to the walker's position,
+    /// summed over every block the frame spans.
+    ///
+    /// Space left over at the end of a block, after the frame spilled over to the next block,
+    /// isn't counted.
+    pub(crate) fn frame_bytes(mut self) -> usize {
+        let header_ptr = self.stack_frame as *const StackFrameHeader as *mut u8;
+        let (mut top, _) = self.position();
+        let mut bottom = top;
+        let mut blocks_visited = self.blocks_visited;
+        let mut bytes = 0;
+
+        //blocks are aligned to the items in them, so the items
+        //of a frame in a block after the header's block start at the very start of the block
+        while let Some(item) = self.next_in_frame() {
+            if self.blocks_visited != blocks_visited {
+                bytes += top as usize - bottom as usize;
+                blocks_visited = self.blocks_visited;
+                top = item.wrapping_add(self.stride);
+            }
+            bottom = item;
+        }
+
+        //the header's block may not hold any of the frame's items
+        if self.blocks_visited != blocks_visited {
+            bytes += top as usize - bottom as usize;
+            (top, _) = self.position();
+        }
+
+        return bytes + (top as usize - header_ptr as usize);
+    }
+
+    /// Steps down to the next item in the stack,
+    /// moving on to previous frames as needed,
+    /// returning None once the bottom of the stack is reached.
+    pub(crate) fn next_in_stack(&mut self) -> Option<*mut u8> {
+        loop {
+            if let Some(item) = self.next_in_frame() {
+                return Some(item);
+            }
+
+            if !self.next_frame() {
+                return None;
+            }
+        }
+    }
+}
@@ -0,0 +1,22 @@
+use std::{alloc::Layout, cell::Cell, ptr::NonNull};
+
+use allocator_api2::alloc::{AllocError, Allocator, Global};
+
+/// Only allows allocating a limited amount of blocks,
+/// used to test how the allocators handle allocation failures.
+pub(crate) struct LimitedAllocator(pub(crate) Cell<usize>);
+
+unsafe impl Allocator for &LimitedAllocator {
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        if self.0.get() == 0 {
+            return Err(AllocError);
+        }
+
+        self.0.set(self.0.get() - 1);
+        return Global.allocate(layout);
+    }
+
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        Global.deallocate(ptr, layout)
+    }
+}